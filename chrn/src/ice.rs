@@ -0,0 +1,93 @@
+//! Turning panics into internal compiler error reports.
+//!
+//! The panic hook shows the ICE as a diagnostic and writes the full report, with a backtrace, to a
+//! crash file in the temporary directory. A closed stdout, as when output is piped to `head`, isn't
+//! a crash: the reader has stopped listening, so chrn stops quietly too.
+
+use std::{
+    backtrace::Backtrace,
+    env,
+    fs::{read_to_string, write},
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use charon::errors::{
+    ice::{context, Ice},
+    Renderer, SourceFile,
+};
+
+/// replaces the default panic hook with one reporting an ICE
+pub fn install() {
+    panic::set_hook(Box::new(|info| {
+        let message = message(info);
+        if is_broken_pipe(&message) {
+            process::exit(0);
+        }
+
+        let ice = Ice {
+            message,
+            location: info.location().map(|l| l.to_string()),
+            context: context(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+
+        let mut diagnostic = ice.diagnostic();
+        let crash = crash_file();
+        match write(&crash, ice.report()) {
+            Ok(()) => {
+                diagnostic = diagnostic.with_note(format!(
+                    "the crash report was written to `{}`",
+                    crash.display()
+                ))
+            }
+            Err(err) => {
+                diagnostic =
+                    diagnostic.with_note(format!("could not write a crash report: {}", err))
+            }
+        }
+
+        // the file is read again, as the source isn't kept anywhere the hook can reach
+        let source = ice
+            .context
+            .file
+            .as_ref()
+            .and_then(|f| read_to_string(f).ok());
+        match (&ice.context.file, &source) {
+            (Some(name), Some(source)) => {
+                let file = SourceFile::new(name, source);
+                eprint!("{}", Renderer::new(false).render(&diagnostic, &file));
+            }
+            _ => eprintln!("{}", diagnostic),
+        }
+    }));
+}
+
+/// the panic's message, if it was given one
+fn message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+/// whether the panic came from printing to a stdout nothing reads any more
+fn is_broken_pipe(message: &str) -> bool {
+    message.starts_with("failed printing to stdout") && message.contains("Broken pipe")
+}
+
+/// a fresh path for a crash report
+fn crash_file() -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    env::temp_dir().join(format!("chrn-ice-{}-{}.txt", seconds, process::id()))
+}
@@ -1,20 +1,461 @@
-use std::fs::write;
+use std::{
+    env,
+    fs::{canonicalize, create_dir_all, read_dir, read_to_string, write},
+    path::{Path, PathBuf},
+};
 
-use clap::{App, Arg, SubCommand};
-use rustyline::{Editor, Result};
+use charon::{
+    errors::{
+        explain, fix,
+        ice::{in_file, stage, Stage},
+        SourceFile,
+    },
+    fmt::{format_program, format_source, Config, FormatError},
+    lex::tokenise,
+    lint::{lint, Level, Levels},
+    optimise::{OptLevel, PassManager, LIST_ITERATIONS, PASSES},
+    parse::{
+        dump,
+        expr::{Node, Spanned},
+        parse,
+    },
+    pkg::{error::ResolveError, FsLoader, Manifest, Resolver, MANIFEST},
+    sema::{self, NameError},
+    vm::Interpreter,
+    Diagnostic,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rustyline::Editor;
+
+mod ice;
+mod report;
+mod watch;
+use report::Reporter;
+
+/// how many times `chrn fix` re-checks a file after applying fixes to it
+const MAX_FIX_PASSES: usize = 16;
+
+/// the status `chrn check` exits with when it finds errors
+const EXIT_ERRORS: i32 = 1;
+/// the status `chrn check` exits with when it finds warnings but no errors
+const EXIT_WARNINGS: i32 = 2;
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["json", "sexp"])
+        .default_value("sexp")
+        .help("the output format")
+}
+
+fn message_format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("message-format")
+        .long("message-format")
+        .takes_value(true)
+        .possible_values(&["human", "json"])
+        .default_value("human")
+        .help("how to print diagnostics")
+}
+
+/// `-A`, `-W` or `-D`, each taking one lint name and repeatable
+fn level_arg<'a, 'b>(name: &'a str, short: char, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .short(short.to_string())
+        .long(name)
+        .value_name("LINT")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help(help)
+}
+
+/// `-O`, `--passes`, `--print-after` and `--time-passes`, for `run` and `build`
+fn optimise_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("opt-level")
+            .short("O")
+            .takes_value(true)
+            .possible_values(&["0", "1", "2", "3"])
+            .default_value("0")
+            .help("how much to optimise: 0 for not at all, 1 folds constants, 2 also removes dead code, 3 also folds matches on literals"),
+        Arg::with_name("passes")
+            .long("passes")
+            .value_name("PASSES")
+            .takes_value(true)
+            .use_delimiter(true)
+            .possible_values(PASSES)
+            .help("the passes to run, in order, instead of those picked by -O"),
+        Arg::with_name("print-after")
+            .long("print-after")
+            .value_name("PASS")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .possible_values(PASSES)
+            .help("print the syntax tree each time this pass runs"),
+        Arg::with_name("time-passes")
+            .long("time-passes")
+            .help("report how long each pass took"),
+    ]
+}
+
+/// `ast` run through the passes picked by `-O` or `--passes`, printing what's asked for
+fn optimise(matches: &ArgMatches, ast: Vec<Spanned<Node>>) -> Vec<Spanned<Node>> {
+    let level = matches
+        .value_of("opt-level")
+        .and_then(OptLevel::parse)
+        .unwrap_or(OptLevel::O0);
+    let mut manager = match matches.values_of("passes") {
+        // names were checked against the known passes by clap
+        Some(names) => {
+            let names: Vec<&str> = names.collect();
+            PassManager::with_passes(&names, LIST_ITERATIONS).unwrap()
+        }
+        None => PassManager::for_level(level),
+    };
+
+    let print_after: Vec<&str> = matches
+        .values_of("print-after")
+        .map_or_else(Vec::new, Iterator::collect);
+    for name in &print_after {
+        if !manager.timings().iter().any(|timing| timing.pass == *name) {
+            eprintln!(
+                "error: the `{}` pass isn't in the pipeline, so `--print-after` can't print after it",
+                name
+            );
+            std::process::exit(1);
+        }
+    }
+    let ast = stage(Stage::Optimise, || {
+        manager.run_inspecting(ast, |pass, program| {
+            if print_after.contains(&pass) {
+                eprint!("; after {}\n{}", pass, dump::to_sexp(program));
+            }
+        })
+    });
+
+    if matches.is_present("time-passes") {
+        for timing in manager.timings() {
+            eprintln!(
+                "{:<12} {:>3} {} {:>12?}",
+                timing.pass,
+                timing.runs,
+                if timing.runs == 1 { "run " } else { "runs" },
+                timing.elapsed
+            );
+        }
+    }
+
+    ast
+}
+
+/// parses and checks the file, reporting every diagnostic and exiting if any are errors
+fn checked(reporter: &Reporter, file: &SourceFile) -> Vec<Spanned<Node>> {
+    let ast = reporter.parse(file);
+
+    let diagnostics = const_reassignments(&ast);
+    if reporter.report(file, &diagnostics) {
+        std::process::exit(1);
+    }
+
+    ast
+}
+
+/// the syntax tree of `source`, or the diagnostic for the first lexing or parsing error
+fn syntax_tree(source: &str) -> Result<Vec<Spanned<Node>>, Vec<Diagnostic>> {
+    let tokens = stage(Stage::Lex, || tokenise(source)).map_err(|err| vec![err.into()])?;
+    stage(Stage::Parse, || parse(tokens)).map_err(|err| vec![err.into()])
+}
+
+/// every assignment to a const in `ast`
+///
+/// Only consts are checked before a program runs; the interpreter reports the other name errors
+/// when it reaches them.
+fn const_reassignments(ast: &[Spanned<Node>]) -> Vec<Diagnostic> {
+    stage(Stage::Resolve, || sema::resolve(ast))
+        .errors
+        .into_iter()
+        .filter(|err| matches!(err, NameError::ConstReassignment { .. }))
+        .map(Diagnostic::from)
+        .collect()
+}
+
+/// every diagnostic from lexing, parsing and checking `source`, stopping at the first stage to fail
+fn diagnose(source: &str) -> Vec<Diagnostic> {
+    match syntax_tree(source) {
+        Ok(ast) => const_reassignments(&ast),
+        Err(diagnostics) => diagnostics,
+    }
+}
+
+/// every diagnostic from the front end for the module at `path`, whose imports are found under
+/// `root`, without running it
+///
+/// Lexing and parsing stop at the first error, but everything after them reports all it finds.
+fn validate(root: &Path, path: &Path, source: &str, levels: &Levels) -> Vec<Diagnostic> {
+    let ast = match syntax_tree(source) {
+        Ok(ast) => ast,
+        Err(errors) => return errors,
+    };
+
+    let mut diagnostics = Vec::new();
+
+    // problems in imported modules are reported when those modules are checked
+    let resolver = Resolver::new(root, FsLoader);
+    if let Err(errors) = stage(Stage::Resolve, || resolver.resolve(path)) {
+        diagnostics.extend(
+            errors
+                .into_iter()
+                .filter_map(|err| own_error(&resolver, &ast, path, err))
+                .map(Diagnostic::from),
+        );
+    }
+
+    let resolution = stage(Stage::Resolve, || sema::resolve(&ast));
+    let typing = stage(Stage::Typecheck, || sema::infer(&ast, &resolution));
+    diagnostics.extend(resolution.errors.into_iter().map(Diagnostic::from));
+    diagnostics.extend(typing.errors.into_iter().map(Diagnostic::from));
+    diagnostics.extend(typing.matches.into_iter().map(Diagnostic::from));
+    let findings = stage(Stage::Lint, || lint(&ast, levels));
+    diagnostics.extend(findings.into_iter().map(Diagnostic::from));
+
+    diagnostics
+}
+
+/// `err` if it belongs to the module at `path`, whose syntax tree is `ast`
+///
+/// A cycle is found at the import that closes it, which is never in the module the walk started
+/// from, so a cycle through `path` is moved onto the import in `path` that leads into it.
+fn own_error(
+    resolver: &Resolver<FsLoader>,
+    ast: &[Spanned<Node>],
+    path: &Path,
+    err: ResolveError,
+) -> Option<ResolveError> {
+    match err {
+        ResolveError::Cycle { file, cycle, .. } if file != path => {
+            let next = cycle
+                .iter()
+                .position(|m| m == path)
+                .map(|idx| &cycle[idx + 1])?;
+            let span = ast.iter().find_map(|node| match &node.node {
+                Node::Import { path: import, .. }
+                    if resolver.module_file(&import.node) == *next =>
+                {
+                    Some(import.span)
+                }
+                _ => None,
+            })?;
+
+            Some(ResolveError::Cycle {
+                file: path.to_path_buf(),
+                span,
+                cycle,
+            })
+        }
+        err if err.file() == path => Some(err),
+        _ => None,
+    }
+}
+
+/// validates each file, reporting everything found, and returns the status `chrn check` exits with
+fn check_files(reporter: &Reporter, root: &Path, files: &[PathBuf], matches: &ArgMatches) -> i32 {
+    let (mut errors, mut warnings) = (0, 0);
+    for path in files {
+        let levels = lint_levels(reporter, path, matches);
+        let _file = in_file(path.display().to_string());
+        let source = match read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("error: could not read {}: {}", path.display(), err);
+                errors += 1;
+                continue;
+            }
+        };
+
+        let diagnostics = validate(root, path, &source, &levels);
+        let name = path.display().to_string();
+        reporter.report(&SourceFile::new(&name, &source), &diagnostics);
+
+        let failed = diagnostics.iter().filter(|d| d.is_error()).count();
+        errors += failed;
+        warnings += diagnostics.len() - failed;
+    }
+
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    eprintln!(
+        "checked {}: {}, {}",
+        plural(files.len(), "file"),
+        plural(errors, "error"),
+        plural(warnings, "warning")
+    );
+
+    if errors > 0 {
+        EXIT_ERRORS
+    } else if warnings > 0 {
+        EXIT_WARNINGS
+    } else {
+        0
+    }
+}
+
+/// `path` from the root of the file system, so its ancestors go above the current directory
+fn absolute(path: &Path) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| env::current_dir().unwrap_or_default().join(path))
+}
+
+/// the nearest `pkg.plto` at or above `dir`, and what it holds, reporting why and exiting if it's
+/// invalid
+fn find_manifest(reporter: &Reporter, dir: &Path) -> Option<(PathBuf, Manifest)> {
+    let path = absolute(dir)
+        .ancestors()
+        .map(|dir| dir.join(MANIFEST))
+        .find(|manifest| manifest.is_file())?;
+
+    let text = read_to_string(&path).unwrap_or_default();
+    match Manifest::parse(&text) {
+        Ok(manifest) => Some((path, manifest)),
+        Err(err) => {
+            let cwd = absolute(Path::new("."));
+            let name = path
+                .strip_prefix(&cwd)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            reporter.fail(&SourceFile::new(&name, &text), err.into())
+        }
+    }
+}
+
+/// the files named by the FILES argument, or every `.chr` file under the current directory
+fn input_files(matches: &ArgMatches) -> Vec<PathBuf> {
+    match matches.values_of("FILES") {
+        Some(files) => files.map(PathBuf::from).collect(),
+        None => {
+            let mut files = Vec::new();
+            chr_files(Path::new("."), &mut files);
+            files.sort();
+            files
+        }
+    }
+}
+
+/// the lint levels in the nearest `pkg.plto` at or above `path`, then those given with `-A`, `-W`
+/// and `-D` in the order they were passed, exiting on an unknown lint
+fn lint_levels(reporter: &Reporter, path: &Path, matches: &ArgMatches) -> Levels {
+    let mut levels = Levels::default();
+
+    let manifest = path.parent().and_then(|dir| find_manifest(reporter, dir));
+    if let Some((_, manifest)) = manifest {
+        // names were checked when the manifest was parsed
+        for (name, level) in &manifest.lints {
+            levels.set_named(name, *level);
+        }
+    }
+
+    let mut flags: Vec<(usize, &str, Level)> = Vec::new();
+    for (arg, level) in [
+        ("allow", Level::Allow),
+        ("warn", Level::Warn),
+        ("deny", Level::Deny),
+    ] {
+        if let (Some(indices), Some(names)) = (matches.indices_of(arg), matches.values_of(arg)) {
+            flags.extend(indices.zip(names).map(|(idx, name)| (idx, name, level)));
+        }
+    }
+    flags.sort_by_key(|(idx, ..)| *idx);
+
+    for (_, name, level) in flags {
+        if !levels.set_named(name, level) {
+            eprintln!("error: unknown lint `{}`", name);
+            std::process::exit(1);
+        }
+    }
+
+    levels
+}
+
+/// reads the file named by the INPUT argument, exiting if it can't be read
+fn read_input(matches: &ArgMatches) -> String {
+    let path = matches.value_of("INPUT").unwrap();
+    match read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("error: could not read {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// every `.chr` file under `dir`, skipping hidden directories and build output
+fn chr_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                chr_files(&path, files);
+            }
+        } else if path.extension().is_some_and(|e| e == "chr") {
+            files.push(path);
+        }
+    }
+}
 
 fn main() {
+    ice::install();
+
     let app = App::new("lanner")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .global(true)
+                .help("when to color diagnostics"),
+        )
         .subcommand(
-            SubCommand::with_name("run").about("run a program").arg(
-                Arg::with_name("INPUT")
-                    .takes_value(true)
-                    .required(false)
-                    .help("the file to run"),
-            ),
+            SubCommand::with_name("run")
+                .about("run a program")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .takes_value(true)
+                        .required(false)
+                        .help("the file to run"),
+                )
+                .args(&optimise_args())
+                .arg(message_format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("check and optimise a program, writing it out to run later")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the file to build"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("where to write the program, defaulting to a file of the same name under target"),
+                )
+                .args(&optimise_args())
+                .arg(message_format_arg()),
         )
         .subcommand(
             SubCommand::with_name("doc")
@@ -28,18 +469,91 @@ fn main() {
         )
         .subcommand(
             SubCommand::with_name("check")
-                .about("validate your charon package")
-                .arg(Arg::with_name("name").takes_value(true).required(true)),
+                .about("validate your charon package without running it")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .takes_value(true)
+                        .required(false)
+                        .help("the file to check, defaulting to every .chr file in the package"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .help("check again whenever a file changes"),
+                )
+                .arg(level_arg("allow", 'A', "turn a lint off, or `all` of them"))
+                .arg(level_arg("warn", 'W', "report a lint as a warning"))
+                .arg(level_arg("deny", 'D', "report a lint as an error"))
+                .arg(message_format_arg()),
         )
         .subcommand(
             SubCommand::with_name("help")
-                .about("get help with charon")
-                .arg(Arg::with_name("name").takes_value(true).required(true)),
+                .visible_alias("explain")
+                .about("explain an error code, like C0001")
+                .arg(
+                    Arg::with_name("CODE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the code to explain"),
+                ),
         )
         .subcommand(SubCommand::with_name("new").about("create a new charon package"))
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("format charon source files")
+                .arg(Arg::with_name("FILES").multiple(true).help(
+                    "the files to format, defaulting to every .chr file under the current directory",
+                ))
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("report unformatted files instead of rewriting them"),
+                )
+                .arg(message_format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("fix")
+                .about("apply suggested fixes to charon source files")
+                .arg(Arg::with_name("FILES").multiple(true).help(
+                    "the files to fix, defaulting to every .chr file under the current directory",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("warn about likely mistakes in charon source files")
+                .arg(Arg::with_name("FILES").multiple(true).help(
+                    "the files to lint, defaulting to every .chr file under the current directory",
+                ))
+                .arg(level_arg("allow", 'A', "turn a lint off, or `all` of them"))
+                .arg(level_arg("warn", 'W', "report a lint as a warning"))
+                .arg(level_arg("deny", 'D', "report a lint as an error"))
+                .arg(message_format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("ast")
+                .about("print the syntax tree of a file")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the file to parse"),
+                )
+                .arg(format_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("tokens")
+                .about("print the tokens of a file")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the file to tokenise"),
+                )
+                .arg(format_arg()),
+        )
         .get_matches();
 
-    if let Some(v) = app.subcommand_matches("new") {
+    if app.subcommand_matches("new").is_some() {
         let mut rl = Editor::<()>::new();
         let config = vec!["name", "description", "version", "license", "source"];
         let mut res: Vec<String> = vec![];
@@ -55,4 +569,268 @@ fn main() {
         )
         .unwrap();
     }
+
+    if let Some(v) = app.subcommand_matches("run") {
+        if v.value_of("INPUT").is_none() {
+            eprintln!("error: no file to run");
+            std::process::exit(1);
+        }
+
+        let reporter = Reporter::new(v);
+        let _file = in_file(v.value_of("INPUT").unwrap());
+        let source = read_input(v);
+        let file = SourceFile::new(v.value_of("INPUT").unwrap(), &source);
+        let ast = optimise(v, checked(&reporter, &file));
+
+        if let Err(err) = stage(Stage::Run, || Interpreter::new().run(&ast)) {
+            reporter.fail(&file, err.into());
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("build") {
+        let input = v.value_of("INPUT").unwrap();
+        let reporter = Reporter::new(v);
+        let _file = in_file(input);
+        let source = read_input(v);
+        let file = SourceFile::new(input, &source);
+        let ast = optimise(v, checked(&reporter, &file));
+
+        let output = match v.value_of("output") {
+            Some(output) => PathBuf::from(output),
+            None => Path::new("target").join(Path::new(input).file_name().unwrap_or_default()),
+        };
+        let program = stage(Stage::Format, || {
+            format_program(&ast, &source, &Config::default())
+        });
+        let written = match output.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => create_dir_all(dir),
+            _ => Ok(()),
+        }
+        .and_then(|()| write(&output, program));
+        if let Err(err) = written {
+            eprintln!("error: could not write {}: {}", output.display(), err);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("check") {
+        let reporter = Reporter::new(v);
+        let cwd = absolute(Path::new("."));
+        let input = v.value_of("INPUT").map(|input| absolute(Path::new(input)));
+        let dir = input
+            .as_deref()
+            .and_then(Path::parent)
+            .map_or_else(|| cwd.clone(), Path::to_path_buf);
+
+        // imports are resolved from the package's source directory, or the file's own outside one
+        let manifest = find_manifest(&reporter, &dir);
+        let source_dir = match (&manifest, &input) {
+            (Some((path, manifest)), _) => path.with_file_name(&manifest.source),
+            (None, Some(_)) => dir,
+            (None, None) => {
+                eprintln!(
+                    "error: could not find `{}` in this directory or any above it",
+                    MANIFEST
+                );
+                std::process::exit(1);
+            }
+        };
+
+        // files are named relative to the current directory when they're under it
+        let root = match source_dir.strip_prefix(&cwd) {
+            Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => source_dir.clone(),
+        };
+        // and the input is named the way an import of it would be, so it's one module to the
+        // resolver
+        let input = input.map(|input| match input.strip_prefix(&source_dir) {
+            Ok(relative) => root.join(relative),
+            Err(_) => input
+                .strip_prefix(&cwd)
+                .map_or(input.clone(), Path::to_path_buf),
+        });
+
+        let files = || match &input {
+            Some(input) => vec![input.clone()],
+            None => {
+                let mut files = Vec::new();
+                chr_files(&root, &mut files);
+                files.sort();
+                files
+            }
+        };
+
+        if v.is_present("watch") {
+            let watched = || {
+                let mut watched = files();
+                watched.extend(manifest.iter().map(|(path, _)| path.clone()));
+                watched
+            };
+            watch::watch(watched, || {
+                check_files(&reporter, &root, &files(), v);
+                eprintln!("watching for changes...");
+            });
+        }
+
+        std::process::exit(check_files(&reporter, &root, &files(), v));
+    }
+
+    if let Some(v) = app.subcommand_matches("help") {
+        let code = v.value_of("CODE").unwrap();
+        match explain(code) {
+            Some(explanation) => print!("{}", explanation),
+            None => {
+                eprintln!("error: no explanation for `{}`", code);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("tokens") {
+        let _file = in_file(v.value_of("INPUT").unwrap());
+        let source = read_input(v);
+        let file = SourceFile::new(v.value_of("INPUT").unwrap(), &source);
+        let tokens = Reporter::new(v).lex(&file);
+
+        match v.value_of("format") {
+            Some("json") => println!("{}", dump::tokens_to_json(&tokens)),
+            _ => print!("{}", dump::tokens_to_sexp(&tokens)),
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("ast") {
+        let _file = in_file(v.value_of("INPUT").unwrap());
+        let source = read_input(v);
+        let file = SourceFile::new(v.value_of("INPUT").unwrap(), &source);
+        let ast = Reporter::new(v).parse(&file);
+
+        match v.value_of("format") {
+            Some("json") => println!("{}", dump::to_json(&ast)),
+            _ => print!("{}", dump::to_sexp(&ast)),
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("fmt") {
+        let reporter = Reporter::new(v);
+        let files = input_files(v);
+        let check = v.is_present("check");
+        let config = Config::default();
+        let mut unformatted = false;
+        for path in files {
+            let _file = in_file(path.display().to_string());
+            let source = match read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    eprintln!("error: could not read {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            };
+
+            let formatted = match stage(Stage::Format, || format_source(&source, &config)) {
+                Ok(formatted) => formatted,
+                Err(err) => {
+                    let name = path.display().to_string();
+                    let diagnostic = match err {
+                        FormatError::Lex(err) => err.into(),
+                        FormatError::Parse(err) => err.into(),
+                    };
+                    reporter.fail(&SourceFile::new(&name, &source), diagnostic)
+                }
+            };
+
+            if formatted == source {
+                continue;
+            }
+
+            if check {
+                println!("{} is not formatted", path.display());
+                unformatted = true;
+            } else {
+                write(&path, formatted).unwrap();
+            }
+        }
+
+        if unformatted {
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("fix") {
+        let reporter = Reporter::new(v);
+        let mut failed = false;
+        for path in input_files(v) {
+            let _file = in_file(path.display().to_string());
+            let source = match read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    eprintln!("error: could not read {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            };
+
+            // parsing stops at the first error, so fixing one can reveal another
+            let mut fixed = source.clone();
+            let mut total = 0;
+            let mut diagnostics = diagnose(&fixed);
+            for _ in 0..MAX_FIX_PASSES {
+                let (next, applied) = fix::apply(&fixed, &diagnostics);
+                if applied == 0 {
+                    break;
+                }
+
+                fixed = next;
+                total += applied;
+                diagnostics = diagnose(&fixed);
+            }
+
+            if fixed != source {
+                write(&path, &fixed).unwrap();
+                println!(
+                    "fixed {} {} in {}",
+                    total,
+                    if total == 1 { "problem" } else { "problems" },
+                    path.display()
+                );
+            }
+
+            let name = path.display().to_string();
+            failed |= reporter.report(&SourceFile::new(&name, &fixed), &diagnostics);
+        }
+
+        if failed {
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(v) = app.subcommand_matches("lint") {
+        let reporter = Reporter::new(v);
+        let mut failed = false;
+        for path in input_files(v) {
+            let levels = lint_levels(&reporter, &path, v);
+            let _file = in_file(path.display().to_string());
+            let source = match read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    eprintln!("error: could not read {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            };
+
+            let diagnostics = match syntax_tree(&source) {
+                Ok(ast) => stage(Stage::Lint, || lint(&ast, &levels))
+                    .into_iter()
+                    .map(Diagnostic::from)
+                    .collect(),
+                Err(diagnostics) => diagnostics,
+            };
+
+            let name = path.display().to_string();
+            failed |= reporter.report(&SourceFile::new(&name, &source), &diagnostics);
+        }
+
+        if failed {
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,91 @@
+//! Writing diagnostics, as chosen by the global options and `--message-format`.
+//!
+//! Human-readable diagnostics go to stderr; JSON diagnostics go to stdout, one per line.
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use charon::{
+    errors::{
+        ice::{stage, Stage},
+        json, ColorChoice, Renderer, SourceFile,
+    },
+    lex::{tokenise, Token},
+    parse::{
+        expr::{Node, Spanned},
+        parse,
+    },
+    Diagnostic,
+};
+use clap::ArgMatches;
+
+pub struct Reporter {
+    renderer: Renderer,
+    json: bool,
+}
+
+impl Reporter {
+    /// a reporter for the global `--color` option, honouring `NO_COLOR` unless color is forced,
+    /// and the `--message-format` option of subcommands that have one
+    pub fn new(matches: &ArgMatches) -> Reporter {
+        let choice = matches
+            .value_of("color")
+            .and_then(ColorChoice::parse)
+            .unwrap_or(ColorChoice::Auto);
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+        Reporter {
+            renderer: Renderer::new(choice.enabled(io::stderr().is_terminal(), no_color)),
+            json: matches.value_of("message-format") == Some("json"),
+        }
+    }
+
+    /// prints diagnostics found in `file`, returning true if any are errors
+    pub fn report(&self, file: &SourceFile, diagnostics: &[Diagnostic]) -> bool {
+        for diagnostic in diagnostics {
+            if self.json {
+                println!("{}", json::to_json(diagnostic, file));
+            } else {
+                eprintln!("{}", self.renderer.render(diagnostic, file));
+            }
+        }
+
+        if !self.json {
+            let mut codes: Vec<&str> = diagnostics
+                .iter()
+                .filter_map(|d| d.code.as_deref())
+                .collect();
+            codes.sort_unstable();
+            codes.dedup();
+
+            match codes.as_slice() {
+                [] => {}
+                [code] => eprintln!("for more information about this error, try `chrn explain {}`", code),
+                codes => eprintln!(
+                    "some errors have detailed explanations: {}\nfor more information about an error, try `chrn explain {}`",
+                    codes.join(", "),
+                    codes[0]
+                ),
+            }
+        }
+
+        diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn fail(&self, file: &SourceFile, diagnostic: Diagnostic) -> ! {
+        self.report(file, &[diagnostic]);
+        std::process::exit(1);
+    }
+
+    pub fn lex<'a>(&self, file: &SourceFile<'a>) -> Vec<Token<'a>> {
+        stage(Stage::Lex, || tokenise(file.source))
+            .unwrap_or_else(|err| self.fail(file, err.into()))
+    }
+
+    pub fn parse(&self, file: &SourceFile) -> Vec<Spanned<Node>> {
+        let tokens = self.lex(file);
+        stage(Stage::Parse, || parse(tokens)).unwrap_or_else(|err| self.fail(file, err.into()))
+    }
+}
@@ -0,0 +1,45 @@
+//! Re-running a command whenever the files it reads change.
+//!
+//! Files are polled for their modification times rather than watched with OS notifications, so a
+//! change is noticed within one `INTERVAL`. The set of files is listed again on every poll, so
+//! adding or removing a file counts as a change too.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// how long to wait between polls
+const INTERVAL: Duration = Duration::from_millis(500);
+
+/// each file's modification time, or `None` if it couldn't be read
+type Snapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+fn snapshot(files: Vec<PathBuf>) -> Snapshot {
+    files
+        .into_iter()
+        .map(|file| {
+            let modified = fs::metadata(&file).and_then(|m| m.modified()).ok();
+            (file, modified)
+        })
+        .collect()
+}
+
+/// calls `run` now and again after every change to the files `files` lists, never returning
+pub fn watch(files: impl Fn() -> Vec<PathBuf>, mut run: impl FnMut()) -> ! {
+    let mut last = snapshot(files());
+    run();
+
+    loop {
+        thread::sleep(INTERVAL);
+
+        let next = snapshot(files());
+        if next != last {
+            last = next;
+            run();
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// a fresh directory holding a `main.chr` that branches on a constant
+fn project(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chrn-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("main.chr"),
+        "// picks one\nprint(match 1 + 1 { 1 => \"one\", 2 => \"two\", _ => \"many\", });\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn chrn(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_chrn"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn writes_the_optimised_program_under_target() {
+    let dir = project("build-target");
+    chrn(&dir, &["build", "-O3", "main.chr"]);
+
+    let built = fs::read_to_string(dir.join("target/main.chr")).unwrap();
+    assert_eq!(built, "print(\"two\");\n");
+    assert_eq!(chrn(&dir, &["run", "target/main.chr"]), "two\n");
+}
+
+#[test]
+fn builds_to_the_output_given() {
+    let dir = project("build-output");
+    chrn(&dir, &["build", "-O1", "-o", "out/plain.chr", "main.chr"]);
+
+    let built = fs::read_to_string(dir.join("out/plain.chr")).unwrap();
+    assert!(built.starts_with("print(match 2 {"), "{}", built);
+    assert_eq!(chrn(&dir, &["run", "out/plain.chr"]), "two\n");
+}
+
+#[test]
+fn runs_pass_lists_to_a_fixed_point() {
+    let dir = project("build-passes");
+    fs::write(dir.join("f.chr"), "fn f() { 1 + 2; g() }\n").unwrap();
+    // the `3;` const-fold leaves is only removed by dead-code the second time round
+    chrn(
+        &dir,
+        &["build", "--passes", "dead-code,const-fold", "f.chr"],
+    );
+
+    assert_eq!(
+        fs::read_to_string(dir.join("target/f.chr")).unwrap(),
+        "fn f() {\n    g()\n}\n"
+    );
+}
+
+#[test]
+fn only_prints_after_passes_in_the_pipeline() {
+    let dir = project("build-print-after");
+    let output = Command::new(env!("CARGO_BIN_EXE_chrn"))
+        .args(["build", "-O1", "--print-after", "dead-code", "main.chr"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't in the pipeline"));
+}
@@ -0,0 +1,98 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// a package in a fresh directory whose `main.chr` imports `util.chr` from `src`
+fn package(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chrn-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("pkg.plto"), "name \"demo\"\nsource \"src\"\n").unwrap();
+    fs::write(
+        dir.join("src/main.chr"),
+        "import \"util\" { twice };\nprint(twice(2));\n",
+    )
+    .unwrap();
+    fs::write(dir.join("src/util.chr"), "pub fn twice(n) { n * 2 }\n").unwrap();
+    dir
+}
+
+/// `chrn check` run from `dir` with `args`
+fn check(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chrn"))
+        .arg("check")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn resolves_imports_from_the_package_root() {
+    let dir = package("check-root");
+    for args in [&["src/main.chr"][..], &[]] {
+        let output = check(&dir, args);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn resolves_imports_from_the_source_directory() {
+    let dir = package("check-source");
+    let output = check(&dir.join("src"), &["main.chr"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn resolves_imports_from_a_package_subdirectory() {
+    let dir = package("check-subdirectory");
+    fs::create_dir_all(dir.join("src/lib")).unwrap();
+    fs::write(
+        dir.join("src/lib/helper.chr"),
+        "import \"util\" { twice };\npub fn quadruple(n) { twice(twice(n)) }\n",
+    )
+    .unwrap();
+
+    for args in [&["helper.chr"][..], &["./helper.chr"]] {
+        let output = check(&dir.join("src/lib"), args);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn reports_cycles_in_each_module_they_pass_through() {
+    let dir = package("check-cycle");
+    fs::write(dir.join("src/main.chr"), "import \"a\";\n").unwrap();
+    fs::write(dir.join("src/a.chr"), "import \"b\";\n").unwrap();
+    fs::write(dir.join("src/b.chr"), "import \"a\";\n").unwrap();
+
+    for (args, errors) in [
+        (&["src/a.chr"][..], 1),
+        (&["src/b.chr"], 1),
+        (&["src/main.chr"], 0),
+        (&[], 2),
+    ] {
+        let output = check(&dir, args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("{} error", errors)),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// a fresh directory for one test's files
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chrn-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `source` after running `chrn fix` on it
+fn fix(dir: &Path, name: &str, source: &str) -> String {
+    let path = dir.join(name);
+    fs::write(&path, source).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_chrn"))
+        .arg("fix")
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::read_to_string(&path).unwrap()
+}
+
+#[test]
+fn inserts_semicolons_at_line_ends() {
+    let dir = scratch("fix-line-ends");
+    assert_eq!(
+        fix(&dir, "main.chr", "let x = 1\nprint(x)\n"),
+        "let x = 1;\nprint(x)\n"
+    );
+}
+
+#[test]
+fn leaves_same_line_statements_alone() {
+    let dir = scratch("fix-same-line");
+    for (name, source) in [
+        ("fn.chr", "fucntion add(a, b) { a + b }\n"),
+        ("names.chr", "foo bar;\n"),
+    ] {
+        assert_eq!(fix(&dir, name, source), source);
+    }
+}
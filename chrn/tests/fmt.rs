@@ -0,0 +1,24 @@
+use std::{env, fs, process::Command};
+
+#[test]
+fn reports_parse_errors_as_diagnostics() {
+    let dir = env::temp_dir().join(format!("chrn-fmt-invalid-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("x.chr"), "let x = (1;\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chrn"))
+        .args(["fmt", "x.chr"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("error[C0007]"), "{}", stderr);
+    assert!(stderr.contains("--> x.chr:1:11"), "{}", stderr);
+    assert_eq!(
+        fs::read_to_string(dir.join("x.chr")).unwrap(),
+        "let x = (1;\n"
+    );
+}
@@ -0,0 +1,53 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// a package in a fresh directory that denies the lint its `src/main.chr` trips
+fn package(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chrn-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src/sub")).unwrap();
+    fs::write(dir.join("pkg.plto"), "deny \"unused_variable\"\n").unwrap();
+    fs::write(dir.join("src/main.chr"), "let x = 1;\n").unwrap();
+    dir
+}
+
+#[test]
+fn reads_levels_from_the_manifest_for_relative_paths() {
+    let src = package("lint-relative").join("src");
+    for (cwd, path) in [
+        (src.clone(), "main.chr"),
+        (src.clone(), "./main.chr"),
+        (src.join("sub"), "../main.chr"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_chrn"))
+            .arg("lint")
+            .arg(path)
+            .current_dir(&cwd)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success(), "{}", path);
+        assert!(stderr.contains("error[C0030]"), "{}: {}", path, stderr);
+    }
+}
+
+#[test]
+fn reports_invalid_manifests_with_their_source() {
+    let dir = package("lint-invalid-manifest");
+    fs::write(dir.join("pkg.plto"), "name \"demo\"\ndeny \"bogus\"\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chrn"))
+        .args(["lint", "src/main.chr"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("error[C0043]: unknown lint `bogus`"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("--> pkg.plto:2:6"), "{}", stderr);
+}
@@ -0,0 +1,682 @@
+//! Conversions from each stage's errors into diagnostics.
+
+use lex::{
+    error::LexError,
+    suggest::{closest, closest_keyword},
+    Span,
+};
+use lint::{Finding, Level, Lint, Warning};
+use parse::error::ParseError;
+use pkg::error::{ManifestError, ResolveError};
+use rt::RuntimeError;
+use sema::{MatchError, NameError, TypeError};
+
+use crate::{Applicability, Diagnostic, Label, Severity};
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Diagnostic {
+        match err {
+            LexError::UnterminatedString(span) => Diagnostic::error("unterminated string")
+                .with_code("C0001")
+                .with_label(Label::primary(span, "this string is never closed"))
+                .with_help("end the string with the quote it started with"),
+            LexError::InvalidNumber(span) => Diagnostic::error("invalid number literal")
+                .with_code("C0002")
+                .with_label(Label::primary(span, "not a valid number")),
+            LexError::UnterminatedComment(span) => Diagnostic::error("unterminated comment")
+                .with_code("C0003")
+                .with_label(Label::primary(span, "this comment is never closed")),
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Diagnostic {
+        match err {
+            ParseError::UnexpectedTermination(span) => Diagnostic::error("unexpected end of input")
+                .with_code("C0004")
+                .with_label(Label::primary(span, "expected more after this")),
+            ParseError::InvalidType(span) => Diagnostic::error("expected a type")
+                .with_code("C0006")
+                .with_label(Label::primary(span, "not a type"))
+                .with_note(
+                    "types are names like `Number`, `List<String>` or `fn(Number) -> Number`",
+                ),
+            ParseError::UnexpectedToken(span) => Diagnostic::error("unexpected token")
+                .with_code("C0007")
+                .with_label(Label::primary(span, "unexpected here")),
+            ParseError::InvalidAssignment(span) => Diagnostic::error("invalid assignment target")
+                .with_code("C0008")
+                .with_label(Label::primary(span, "can't be assigned to"))
+                .with_help("only names and indexes, like `x` or `xs[0]`, can be assigned to"),
+            ParseError::RefutablePattern(span) => Diagnostic::error("refutable pattern in binding")
+                .with_code("C0009")
+                .with_label(Label::primary(span, "this might not match"))
+                .with_note("`let` and parameters need patterns that match every value"),
+            ParseError::MissingSemicolon { span, line_ends } => {
+                // on the same line as what follows, the two may be one mistyped statement instead
+                let applicability = if line_ends {
+                    Applicability::MachineApplicable
+                } else {
+                    Applicability::MaybeIncorrect
+                };
+
+                Diagnostic::error("expected `;`")
+                    .with_code("C0023")
+                    .with_label(Label::primary(span, "expected `;` here"))
+                    .with_suggestion("insert `;`", span, ";", applicability)
+            }
+            ParseError::MisspelledKeyword { span, keyword } => {
+                Diagnostic::error("expected a keyword")
+                    .with_code("C0024")
+                    .with_label(Label::primary(span, "unknown keyword"))
+                    .with_suggestion(
+                        format!("did you mean `{}`?", keyword),
+                        span,
+                        keyword,
+                        Applicability::MaybeIncorrect,
+                    )
+            }
+        }
+    }
+}
+
+/// a name found nowhere in scope, suggesting a similar name or else a keyword it may be a typo of
+fn undefined(name: &str, span: Span, similar: Option<String>) -> Diagnostic {
+    let diagnostic = Diagnostic::error(format!("cannot find `{}` in this scope", name))
+        .with_code("C0016")
+        .with_label(Label::primary(span, "not defined"));
+
+    match similar {
+        Some(similar) => diagnostic.with_suggestion(
+            format!("a name with a similar spelling exists: `{}`", similar),
+            span,
+            similar,
+            Applicability::MaybeIncorrect,
+        ),
+        None => match closest_keyword(name) {
+            Some(keyword) => diagnostic.with_suggestion(
+                format!("did you mean the keyword `{}`?", keyword),
+                span,
+                keyword,
+                Applicability::MaybeIncorrect,
+            ),
+            None => diagnostic,
+        },
+    }
+}
+
+/// a variant the enum doesn't declare, suggesting one with a similar name
+fn unknown_variant(name: &str, variant: &str, span: Span, similar: Option<String>) -> Diagnostic {
+    let diagnostic = Diagnostic::error(format!("no variant `{}` in enum `{}`", variant, name))
+        .with_code("C0039")
+        .with_label(Label::primary(span, "not a variant"));
+
+    match similar {
+        Some(similar) => diagnostic.with_suggestion(
+            format!("a variant with a similar name exists: `{}`", similar),
+            span,
+            similar,
+            Applicability::MaybeIncorrect,
+        ),
+        None => diagnostic,
+    }
+}
+
+impl From<NameError> for Diagnostic {
+    fn from(err: NameError) -> Diagnostic {
+        match err {
+            NameError::Undefined {
+                name,
+                span,
+                similar,
+            } => undefined(&name, span, similar),
+            NameError::Duplicate {
+                name,
+                span,
+                previous,
+            } => Diagnostic::error(format!("`{}` is declared twice in the same scope", name))
+                .with_code("C0025")
+                .with_label(Label::primary(span, "declared again here"))
+                .with_label(Label::secondary(previous, "first declared here"))
+                .with_help("rename one of them, or assign to the first instead"),
+            NameError::UseBeforeDeclaration {
+                name,
+                span,
+                declaration,
+            } => Diagnostic::error(format!("`{}` is used before it's declared", name))
+                .with_code("C0026")
+                .with_label(Label::primary(span, "used here"))
+                .with_label(Label::secondary(declaration, "declared later here"))
+                .with_help(format!("move the declaration of `{}` before this", name)),
+            NameError::ConstReassignment {
+                name,
+                declaration,
+                assignment,
+            } => Diagnostic::error(format!("cannot assign twice to constant `{}`", name))
+                .with_code("C0010")
+                .with_label(Label::primary(assignment, "cannot assign to a constant"))
+                .with_label(Label::secondary(declaration, "declared as a constant here"))
+                .with_help(format!(
+                    "declare `{}` with `let` to allow reassignment",
+                    name
+                )),
+        }
+    }
+}
+
+impl From<TypeError> for Diagnostic {
+    fn from(err: TypeError) -> Diagnostic {
+        match err {
+            TypeError::Mismatch {
+                expected,
+                found,
+                span,
+                cause,
+            } => Diagnostic::error("mismatched types")
+                .with_code("C0017")
+                .with_label(Label::primary(
+                    span,
+                    format!("expected `{}`, found `{}`", expected, found),
+                ))
+                .with_label(Label::secondary(cause, "expected because of this")),
+            TypeError::WrongArity {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("wrong number of arguments")
+                .with_code("C0022")
+                .with_label(Label::primary(
+                    span,
+                    format!("expected {}, found {}", plural(expected), found),
+                )),
+            TypeError::NotCallable { ty, span } => Diagnostic::error("not a function")
+                .with_code("C0018")
+                .with_label(Label::primary(span, format!("a `{}` can't be called", ty))),
+            TypeError::NotIndexable { ty, span } => Diagnostic::error("mismatched types")
+                .with_code("C0017")
+                .with_label(Label::primary(
+                    span,
+                    format!("expected a list, map or string, found `{}`", ty),
+                )),
+            TypeError::UnknownType { name, span } => {
+                Diagnostic::error(format!("cannot find type `{}`", name))
+                    .with_code("C0027")
+                    .with_label(Label::primary(span, "not a type"))
+                    .with_note("generic types can only be introduced in a function's signature")
+            }
+            TypeError::TypeArguments {
+                name,
+                expected,
+                found,
+                span,
+            } => Diagnostic::error(format!(
+                "`{}` takes {} type {}",
+                name,
+                expected,
+                if expected == 1 {
+                    "argument"
+                } else {
+                    "arguments"
+                }
+            ))
+            .with_code("C0028")
+            .with_label(Label::primary(span, format!("given {}", found))),
+            TypeError::UnknownVariant {
+                name,
+                variant,
+                span,
+                similar,
+            } => unknown_variant(&name, &variant, span, similar),
+        }
+    }
+}
+
+impl From<MatchError> for Diagnostic {
+    fn from(err: MatchError) -> Diagnostic {
+        match err {
+            MatchError::NonExhaustive { span, missing } => {
+                let patterns: Vec<String> = missing.iter().map(|p| format!("`{}`", p)).collect();
+                let message = match &patterns[..] {
+                    [pattern] => format!("pattern {} not covered", pattern),
+                    patterns => format!("patterns {} not covered", patterns.join(", ")),
+                };
+
+                Diagnostic::error(format!("non-exhaustive patterns: {}", message))
+                    .with_code("C0037")
+                    .with_label(Label::primary(span, message))
+                    .with_help(
+                        "add an arm for each missing pattern, or a `_` arm to match the rest",
+                    )
+            }
+            MatchError::UnreachableArm { span } => Diagnostic::warning("unreachable pattern")
+                .with_code("C0038")
+                .with_label(Label::primary(
+                    span,
+                    "every value this matches is matched by an earlier arm",
+                )),
+        }
+    }
+}
+
+/// a warning, or an error if its lint is denied, with the lint's own code and a note naming the lint
+/// and its level
+impl From<Finding> for Diagnostic {
+    fn from(finding: Finding) -> Diagnostic {
+        let lint = finding.warning.lint();
+        let diagnostic = match finding.warning {
+            Warning::UnusedVariable { name, span } => {
+                Diagnostic::warning(format!("unused variable `{}`", name))
+                    .with_label(Label::primary(span, "never read"))
+                    .with_suggestion(
+                        "if this is intentional, prefix it with an underscore",
+                        span,
+                        format!("_{}", name),
+                        Applicability::MaybeIncorrect,
+                    )
+            }
+            Warning::UnusedImport { name, span } => {
+                Diagnostic::warning(format!("unused import `{}`", name))
+                    .with_label(Label::primary(span, "never used"))
+            }
+            Warning::UnreachableCode { span, after } => Diagnostic::warning("unreachable code")
+                .with_label(Label::primary(span, "this is never run"))
+                .with_label(Label::secondary(
+                    after,
+                    "any code after this is unreachable",
+                )),
+            Warning::ShadowedBinding {
+                name,
+                span,
+                previous,
+            } => Diagnostic::warning(format!("`{}` shadows an earlier binding", name))
+                .with_label(Label::primary(span, "this binding"))
+                .with_label(Label::secondary(previous, "shadows this one")),
+            Warning::ConstantCondition { span } => Diagnostic::warning("constant condition")
+                .with_label(Label::primary(span, "this always has the same result")),
+            Warning::LooseEquality {
+                span,
+                operator,
+                strict,
+            } => Diagnostic::warning("comparison converts to a number")
+                .with_label(Label::primary(span, "compared with loose equality"))
+                .with_note("strings and booleans equal numbers they convert to, like `\"1\" == 1`")
+                .with_suggestion(
+                    format!("use `{}` to compare without converting", strict),
+                    operator,
+                    format!(" {} ", strict),
+                    Applicability::MaybeIncorrect,
+                ),
+            Warning::UnknownLint { name, span } => {
+                Diagnostic::warning(format!("unknown lint `{}`", name))
+                    .with_label(Label::primary(span, "not a lint"))
+            }
+        };
+
+        let diagnostic = Diagnostic {
+            severity: match finding.level {
+                Level::Deny => Severity::Error,
+                _ => Severity::Warning,
+            },
+            ..diagnostic.with_code(lint_code(lint))
+        };
+        diagnostic.with_note(format!("`{}` is set to `{}`", lint, finding.level))
+    }
+}
+
+/// the code each lint's findings are reported with, whatever level it's set to
+fn lint_code(lint: Lint) -> &'static str {
+    match lint {
+        Lint::UnusedVariable => "C0030",
+        Lint::UnusedImport => "C0031",
+        Lint::UnreachableCode => "C0032",
+        Lint::ShadowedBinding => "C0033",
+        Lint::ConstantCondition => "C0034",
+        Lint::LooseEquality => "C0035",
+        Lint::UnknownLint => "C0036",
+    }
+}
+
+/// the diagnostic points into the `pkg.plto` that failed to parse
+impl From<ManifestError> for Diagnostic {
+    fn from(err: ManifestError) -> Diagnostic {
+        match err {
+            ManifestError::InvalidLine(span) => Diagnostic::error("invalid manifest line")
+                .with_code("C0041")
+                .with_label(Label::primary(span, "expected `key \"value\"`"))
+                .with_help("each line is a key, then its value in double quotes"),
+            ManifestError::UnknownKey(span, key) => {
+                Diagnostic::error(format!("unknown manifest key `{}`", key))
+                    .with_code("C0042")
+                    .with_label(Label::primary(span, "not a manifest key"))
+                    .with_note(
+                        "the keys are `name`, `description`, `version`, `license`, `source`, \
+                         `allow`, `warn` and `deny`",
+                    )
+            }
+            ManifestError::UnknownLint(span, name) => {
+                let diagnostic = Diagnostic::error(format!("unknown lint `{}`", name))
+                    .with_code("C0043")
+                    .with_label(Label::primary(span, "not a lint"));
+                let names = Lint::ALL.iter().map(|lint| lint.name()).chain(["all"]);
+                match closest(&name, names) {
+                    Some(similar) => diagnostic.with_suggestion(
+                        "a lint with a similar name exists",
+                        span,
+                        format!("\"{}\"", similar),
+                        Applicability::MaybeIncorrect,
+                    ),
+                    None => diagnostic,
+                }
+            }
+        }
+    }
+}
+
+/// the diagnostic points into the module named by `ResolveError::file`
+impl From<ResolveError> for Diagnostic {
+    fn from(err: ResolveError) -> Diagnostic {
+        match err {
+            ResolveError::Io { file, error } => {
+                Diagnostic::error(format!("could not read module `{}`", file.display()))
+                    .with_code("C0015")
+                    .with_note(error.to_string())
+            }
+            ResolveError::Lex { error, .. } => error.into(),
+            ResolveError::Parse { error, .. } => error.into(),
+            ResolveError::Unresolved { span, module, .. } => Diagnostic::error("unresolved import")
+                .with_code("C0011")
+                .with_label(Label::primary(
+                    span,
+                    format!("no module at `{}`", module.display()),
+                )),
+            ResolveError::Cycle { span, cycle, .. } => {
+                let modules: Vec<String> =
+                    cycle.iter().map(|m| format!("`{}`", m.display())).collect();
+
+                Diagnostic::error("import cycle")
+                    .with_code("C0012")
+                    .with_label(Label::primary(span, "this import leads back to itself"))
+                    .with_note(format!("the cycle is {}", modules.join(" -> ")))
+            }
+            ResolveError::MissingItem {
+                span, module, name, ..
+            } => Diagnostic::error(format!("no `{}` in `{}`", name, module.display()))
+                .with_code("C0013")
+                .with_label(Label::primary(span, "not defined in that module")),
+            ResolveError::Private {
+                span, module, name, ..
+            } => Diagnostic::error(format!("`{}` is private", name))
+                .with_code("C0014")
+                .with_label(Label::primary(span, "private item"))
+                .with_help(format!(
+                    "mark `{}` as `pub` in `{}` to import it",
+                    name,
+                    module.display()
+                )),
+        }
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(err: RuntimeError) -> Diagnostic {
+        match err {
+            RuntimeError::UndefinedVariable {
+                name,
+                span,
+                similar,
+            } => undefined(&name, span, similar),
+            RuntimeError::TypeMismatch {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("mismatched types")
+                .with_code("C0017")
+                .with_label(Label::primary(
+                    span,
+                    format!("expected {}, found {}", expected, found),
+                )),
+            RuntimeError::NotCallable(span) => Diagnostic::error("not a function")
+                .with_code("C0018")
+                .with_label(Label::primary(span, "this value can't be called")),
+            RuntimeError::IndexOutOfBounds { index, len, span } => {
+                Diagnostic::error("index out of bounds")
+                    .with_code("C0019")
+                    .with_label(Label::primary(
+                        span,
+                        format!("index {} is past the length of {}", index, len),
+                    ))
+            }
+            RuntimeError::ReversedSlice { start, end, span } => {
+                Diagnostic::error("slice bounds out of order")
+                    .with_code("C0044")
+                    .with_label(Label::primary(
+                        span,
+                        format!("slice start {} is after its end {}", start, end),
+                    ))
+            }
+            RuntimeError::MissingKey(key, span) => Diagnostic::error("missing map key")
+                .with_code("C0020")
+                .with_label(Label::primary(span, format!("no entry for {}", key))),
+            RuntimeError::PatternMismatch(span) => Diagnostic::error("value doesn't match pattern")
+                .with_code("C0021")
+                .with_label(Label::primary(span, "the value doesn't have this shape")),
+            RuntimeError::WrongArity {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("wrong number of arguments")
+                .with_code("C0022")
+                .with_label(Label::primary(
+                    span,
+                    format!("expected {}, found {}", plural(expected), found),
+                )),
+            RuntimeError::UnsupportedImport(span) => {
+                Diagnostic::error("imports are not supported by `run`")
+                    .with_code("C0029")
+                    .with_label(Label::primary(span, "this module can't be loaded"))
+                    .with_help("use `chrn check` to check a package with several modules")
+            }
+            RuntimeError::UnknownVariant {
+                name,
+                variant,
+                span,
+                similar,
+            } => unknown_variant(&name, &variant, span, similar),
+            RuntimeError::NoMatchingArm(span) => Diagnostic::error("no arm matches the value")
+                .with_code("C0040")
+                .with_label(Label::primary(span, "no pattern matches this value")),
+        }
+    }
+}
+
+fn plural(arguments: usize) -> String {
+    match arguments {
+        1 => String::from("1 argument"),
+        n => format!("{} arguments", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use sema::Type;
+
+    use super::*;
+    use crate::LabelStyle;
+
+    #[test]
+    fn const_reassignment_points_at_both_spans() {
+        let ast = parse::parse(tokenise("const x = 1;\nx = 2;").unwrap()).unwrap();
+        let err = sema::resolve(&ast).errors.remove(0);
+        let diagnostic = Diagnostic::from(err);
+
+        let labels: Vec<_> = diagnostic
+            .labels
+            .iter()
+            .map(|l| (l.style, l.span))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (LabelStyle::Primary, (13, 18)),
+                (LabelStyle::Secondary, (6, 7))
+            ]
+        );
+    }
+
+    #[test]
+    fn every_stage_converts() {
+        let lexed = Diagnostic::from(tokenise("\"open").unwrap_err());
+        let parsed = Diagnostic::from(parse::parse(tokenise("let = 1").unwrap()).unwrap_err());
+        let ran = Diagnostic::from(RuntimeError::WrongArity {
+            expected: 1,
+            found: 2,
+            span: (0, 4),
+        });
+
+        assert_eq!(lexed.primary().unwrap().span, (0, 5));
+        assert_eq!(parsed.primary().unwrap().span, (4, 5));
+        assert_eq!(ran.labels[0].message, "expected 1 argument, found 2");
+    }
+
+    #[test]
+    fn suggests_similar_names_then_keywords() {
+        let undefined = |name: &str, similar: Option<&str>| {
+            Diagnostic::from(RuntimeError::UndefinedVariable {
+                name: name.to_string(),
+                span: (0, name.len()),
+                similar: similar.map(str::to_string),
+            })
+        };
+
+        let similar = undefined("cuont", Some("count"));
+        assert_eq!(similar.suggestions[0].replacement, "count");
+        let keyword = undefined("retrun", None);
+        assert_eq!(
+            keyword.suggestions[0].message,
+            "did you mean the keyword `return`?"
+        );
+        assert!(undefined("value", None).suggestions.is_empty());
+    }
+
+    #[test]
+    fn name_errors_point_at_both_names() {
+        let diagnostic = Diagnostic::from(NameError::UseBeforeDeclaration {
+            name: String::from("limit"),
+            span: (6, 11),
+            declaration: (18, 23),
+        });
+
+        assert_eq!(diagnostic.code.as_deref(), Some("C0026"));
+        assert_eq!(diagnostic.labels.len(), 2);
+        let undefined = Diagnostic::from(NameError::Undefined {
+            name: String::from("cuont"),
+            span: (0, 5),
+            similar: Some(String::from("count")),
+        });
+        assert_eq!(undefined.code.as_deref(), Some("C0016"));
+        assert_eq!(undefined.suggestions[0].replacement, "count");
+    }
+
+    #[test]
+    fn type_errors_point_at_their_cause() {
+        let diagnostic = Diagnostic::from(TypeError::Mismatch {
+            expected: Type::Number,
+            found: Type::list(Type::String),
+            span: (16, 21),
+            cause: (7, 13),
+        });
+
+        assert_eq!(diagnostic.code.as_deref(), Some("C0017"));
+        assert_eq!(
+            diagnostic.primary().unwrap().message,
+            "expected `Number`, found `List<String>`"
+        );
+        assert_eq!(diagnostic.labels[1].span, (7, 13));
+    }
+
+    #[test]
+    fn names_missing_patterns() {
+        let src = "enum Shape { Circle(Number), Empty }\nfn f(s) { match s { Shape::Empty => 0, Shape::Empty => 1 } }";
+        let ast = parse::parse(tokenise(src).unwrap()).unwrap();
+        let typing = sema::infer(&ast, &sema::resolve(&ast));
+        let diagnostics: Vec<Diagnostic> =
+            typing.matches.into_iter().map(Diagnostic::from).collect();
+
+        assert_eq!(
+            diagnostics[0].message,
+            "non-exhaustive patterns: pattern `Shape::Circle(_)` not covered"
+        );
+        assert_eq!(diagnostics[0].primary().unwrap().span, (53, 54));
+        assert!(!diagnostics[1].is_error());
+        assert_eq!(diagnostics[1].code.as_deref(), Some("C0038"));
+    }
+
+    #[test]
+    fn denied_lints_are_errors() {
+        let finding = |level| Finding {
+            level,
+            warning: Warning::UnusedVariable {
+                name: String::from("x"),
+                span: (4, 5),
+            },
+        };
+
+        let warned = Diagnostic::from(finding(Level::Warn));
+        assert!(!warned.is_error());
+        assert_eq!(warned.suggestions[0].replacement, "_x");
+        let denied = Diagnostic::from(finding(Level::Deny));
+        assert!(denied.is_error());
+        assert_eq!(denied.notes, vec!["`unused_variable` is set to `deny`"]);
+    }
+
+    #[test]
+    fn codes_are_explained() {
+        let diagnostics = [
+            Diagnostic::from(tokenise("\"open").unwrap_err()),
+            Diagnostic::from(parse::parse(tokenise("let = 1").unwrap()).unwrap_err()),
+            Diagnostic::from(parse::parse(tokenise("let x = 1 x").unwrap()).unwrap_err()),
+            Diagnostic::from(parse::parse(tokenise("retrun x").unwrap()).unwrap_err()),
+            Diagnostic::from(RuntimeError::NotCallable((0, 1))),
+            Diagnostic::from(RuntimeError::NoMatchingArm((0, 1))),
+            Diagnostic::from(RuntimeError::ReversedSlice {
+                start: 2,
+                end: 1,
+                span: (0, 6),
+            }),
+            Diagnostic::from(MatchError::UnreachableArm { span: (0, 1) }),
+            Diagnostic::from(ManifestError::InvalidLine((0, 4))),
+            Diagnostic::from(ManifestError::UnknownKey((0, 5), String::from("entry"))),
+            Diagnostic::from(ManifestError::UnknownLint((6, 14), String::from("unused"))),
+            Diagnostic::from(ResolveError::Cycle {
+                file: "a.chr".into(),
+                span: (0, 9),
+                cycle: vec!["a.chr".into(), "a.chr".into()],
+            }),
+        ];
+
+        for diagnostic in &diagnostics {
+            let code = diagnostic.code.as_deref().unwrap();
+            assert!(crate::explain(code).is_some(), "{} is unexplained", code);
+        }
+    }
+
+    #[test]
+    fn each_lint_has_its_own_code() {
+        let mut codes: Vec<&str> = Lint::ALL.iter().map(|lint| lint_code(*lint)).collect();
+        for code in &codes {
+            assert!(crate::explain(code).is_some(), "{} is unexplained", code);
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), Lint::ALL.len());
+
+        let warning = Warning::ConstantCondition { span: (0, 5) };
+        let diagnostic = Diagnostic::from(Finding {
+            level: Level::Warn,
+            warning,
+        });
+        assert_eq!(diagnostic.code.as_deref(), Some("C0034"));
+    }
+}
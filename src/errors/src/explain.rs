@@ -0,0 +1,96 @@
+//! Long-form explanations of error codes, as Markdown.
+//!
+//! Every diagnostic with a code has an explanation here, read from `explanations/` at build time.
+//! Codes are never reused or renumbered: new kinds of problem get the next unused code. A code that's
+//! no longer emitted keeps its explanation, saying so, for anyone looking up an old report.
+
+/// every code and its explanation, in order
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("C0001", include_str!("../explanations/C0001.md")),
+    ("C0002", include_str!("../explanations/C0002.md")),
+    ("C0003", include_str!("../explanations/C0003.md")),
+    ("C0004", include_str!("../explanations/C0004.md")),
+    ("C0005", include_str!("../explanations/C0005.md")),
+    ("C0006", include_str!("../explanations/C0006.md")),
+    ("C0007", include_str!("../explanations/C0007.md")),
+    ("C0008", include_str!("../explanations/C0008.md")),
+    ("C0009", include_str!("../explanations/C0009.md")),
+    ("C0010", include_str!("../explanations/C0010.md")),
+    ("C0011", include_str!("../explanations/C0011.md")),
+    ("C0012", include_str!("../explanations/C0012.md")),
+    ("C0013", include_str!("../explanations/C0013.md")),
+    ("C0014", include_str!("../explanations/C0014.md")),
+    ("C0015", include_str!("../explanations/C0015.md")),
+    ("C0016", include_str!("../explanations/C0016.md")),
+    ("C0017", include_str!("../explanations/C0017.md")),
+    ("C0018", include_str!("../explanations/C0018.md")),
+    ("C0019", include_str!("../explanations/C0019.md")),
+    ("C0020", include_str!("../explanations/C0020.md")),
+    ("C0021", include_str!("../explanations/C0021.md")),
+    ("C0022", include_str!("../explanations/C0022.md")),
+    ("C0023", include_str!("../explanations/C0023.md")),
+    ("C0024", include_str!("../explanations/C0024.md")),
+    ("C0025", include_str!("../explanations/C0025.md")),
+    ("C0026", include_str!("../explanations/C0026.md")),
+    ("C0027", include_str!("../explanations/C0027.md")),
+    ("C0028", include_str!("../explanations/C0028.md")),
+    ("C0029", include_str!("../explanations/C0029.md")),
+    ("C0030", include_str!("../explanations/C0030.md")),
+    ("C0031", include_str!("../explanations/C0031.md")),
+    ("C0032", include_str!("../explanations/C0032.md")),
+    ("C0033", include_str!("../explanations/C0033.md")),
+    ("C0034", include_str!("../explanations/C0034.md")),
+    ("C0035", include_str!("../explanations/C0035.md")),
+    ("C0036", include_str!("../explanations/C0036.md")),
+    ("C0037", include_str!("../explanations/C0037.md")),
+    ("C0038", include_str!("../explanations/C0038.md")),
+    ("C0039", include_str!("../explanations/C0039.md")),
+    ("C0040", include_str!("../explanations/C0040.md")),
+    ("C0041", include_str!("../explanations/C0041.md")),
+    ("C0042", include_str!("../explanations/C0042.md")),
+    ("C0043", include_str!("../explanations/C0043.md")),
+    ("C0044", include_str!("../explanations/C0044.md")),
+];
+
+/// the explanation of a code like `C0001`, ignoring its case
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+/// every code with an explanation
+pub fn codes() -> impl Iterator<Item = &'static str> {
+    EXPLANATIONS.iter().map(|(code, _)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_numbered_in_order() {
+        for (i, code) in codes().enumerate() {
+            assert_eq!(code, format!("C{:04}", i + 1));
+        }
+    }
+
+    #[test]
+    fn explanations_are_titled_with_their_code() {
+        for (code, explanation) in EXPLANATIONS {
+            assert!(
+                explanation.starts_with(&format!("# {}: ", code)),
+                "{} should start with its title",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn looks_up_codes_in_any_case() {
+        assert_eq!(explain("c0010"), explain("C0010"));
+        assert!(explain("C0010").unwrap().contains("const"));
+        assert_eq!(explain("C9999"), None);
+    }
+}
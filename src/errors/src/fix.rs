@@ -0,0 +1,111 @@
+//! Applying suggested fixes to source.
+//!
+//! Only machine-applicable suggestions are applied. Where two would edit overlapping spans, or
+//! meet at an offset where one of them inserts, the one starting first wins and the other is left
+//! for a later pass, once the source has been checked again.
+
+use crate::{Applicability, Diagnostic, Suggestion};
+
+/// `source` with every machine-applicable suggestion from `diagnostics` applied, and how many were
+pub fn apply(source: &str, diagnostics: &[Diagnostic]) -> (String, usize) {
+    let mut suggestions: Vec<&Suggestion> = diagnostics
+        .iter()
+        .flat_map(|diagnostic| &diagnostic.suggestions)
+        .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable)
+        .collect();
+    suggestions.sort_by_key(|suggestion| suggestion.span);
+    suggestions.dedup_by(|a, b| a.span == b.span && a.replacement == b.replacement);
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut applied = 0;
+    let mut end = 0;
+    // the span of the last edit applied, as two edits meeting at an insertion have no order
+    let mut last: Option<(usize, usize)> = None;
+    for suggestion in suggestions {
+        let (start, stop) = suggestion.span;
+        let meets_insertion = last.is_some_and(|(last_start, last_stop)| {
+            start == last_stop && (start == stop || last_start == last_stop)
+        });
+        if start < end || meets_insertion || stop > source.len() {
+            continue;
+        }
+
+        fixed.push_str(&source[end..start]);
+        fixed.push_str(&suggestion.replacement);
+        end = stop;
+        last = Some(suggestion.span);
+        applied += 1;
+    }
+    fixed.push_str(&source[end..]);
+
+    (fixed, applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_only_machine_applicable_suggestions() {
+        let diagnostics = [
+            Diagnostic::error("expected `;`").with_suggestion(
+                "insert `;`",
+                (9, 9),
+                ";",
+                Applicability::MachineApplicable,
+            ),
+            Diagnostic::warning("`x` is never reassigned").with_suggestion(
+                "use `const`",
+                (0, 3),
+                "const",
+                Applicability::MaybeIncorrect,
+            ),
+        ];
+
+        assert_eq!(
+            apply("let x = 1\nx", &diagnostics),
+            (String::from("let x = 1;\nx"), 1)
+        );
+    }
+
+    #[test]
+    fn skips_overlapping_and_duplicate_edits() {
+        let edit = |span, replacement| {
+            Diagnostic::error("").with_suggestion(
+                "",
+                span,
+                replacement,
+                Applicability::MachineApplicable,
+            )
+        };
+        let diagnostics = [
+            edit((4, 7), "b"),
+            edit((0, 5), "a"),
+            edit((8, 8), ";"),
+            edit((8, 8), ";"),
+        ];
+
+        assert_eq!(
+            apply("let abc d", &diagnostics),
+            (String::from("abc ;d"), 2)
+        );
+    }
+
+    #[test]
+    fn skips_edits_meeting_at_an_insertion() {
+        let edit = |span, replacement| {
+            Diagnostic::error("").with_suggestion(
+                "",
+                span,
+                replacement,
+                Applicability::MachineApplicable,
+            )
+        };
+
+        let insertions = [edit((5, 5), ";"), edit((5, 5), ",")];
+        assert_eq!(apply("let x", &insertions), (String::from("let x;"), 1));
+
+        let replacements = [edit((4, 5), "y"), edit((5, 5), ";"), edit((0, 3), "const")];
+        assert_eq!(apply("let x", &replacements), (String::from("const y"), 2));
+    }
+}
@@ -0,0 +1,251 @@
+//! Internal compiler errors: panics inside a stage, reported as bugs in charon rather than in the
+//! program being compiled.
+//!
+//! An ICE is shown to the user as a diagnostic, and written out in full, backtrace included, as a
+//! crash report to attach to a bug report.
+//!
+//! What the compiler was doing is tracked per thread as it goes: the driver enters each stage and
+//! names each file, and the stages mark the span they've reached with `lex::trace`. Entering a
+//! stage or file gives a guard that puts back what was there before when it's dropped, so nothing
+//! done after a stage finishes is blamed on it.
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Write},
+};
+
+use lex::{trace, Span};
+
+use crate::{Diagnostic, Label};
+
+const ISSUES: &str = "https://github.com/hvlck/charon/issues";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Lex,
+    Parse,
+    /// resolving names, in the program and across the modules of a package
+    Resolve,
+    Typecheck,
+    Lint,
+    Optimise,
+    Format,
+    Run,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Lex => write!(f, "lexing"),
+            Stage::Parse => write!(f, "parsing"),
+            Stage::Resolve => write!(f, "resolving"),
+            Stage::Typecheck => write!(f, "type checking"),
+            Stage::Lint => write!(f, "linting"),
+            Stage::Optimise => write!(f, "optimising"),
+            Stage::Format => write!(f, "formatting"),
+            Stage::Run => write!(f, "running"),
+        }
+    }
+}
+
+/// the stage running, the file being worked on and the span last reached
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    pub stage: Option<Stage>,
+    pub file: Option<String>,
+    pub span: Option<Span>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::new(Context::default());
+}
+
+/// the context on this thread
+pub fn context() -> Context {
+    CONTEXT.with(|context| Context {
+        span: trace::reached(),
+        ..context.borrow().clone()
+    })
+}
+
+/// restores the context from before a stage or file was entered when dropped
+#[must_use = "the stage is left as soon as the guard is dropped"]
+pub struct Guard {
+    previous: Context,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let previous = std::mem::take(&mut self.previous);
+        trace::replace(previous.span);
+        CONTEXT.with(|context| *context.borrow_mut() = previous);
+    }
+}
+
+fn replace(update: impl FnOnce(&mut Context)) -> Guard {
+    let previous = context();
+    CONTEXT.with(|context| update(&mut context.borrow_mut()));
+    trace::replace(None);
+
+    Guard { previous }
+}
+
+/// marks the start of a stage, which lasts until the guard is dropped
+pub fn enter(stage: Stage) -> Guard {
+    replace(|context| context.stage = Some(stage))
+}
+
+/// names the file worked on until the guard is dropped
+pub fn in_file(file: impl Into<String>) -> Guard {
+    let file = file.into();
+    replace(|context| {
+        context.file = Some(file);
+        context.stage = None;
+    })
+}
+
+/// runs `f` as `stage`
+pub fn stage<T>(stage: Stage, f: impl FnOnce() -> T) -> T {
+    let _stage = enter(stage);
+    f()
+}
+
+/// a panic, with what the front end was doing when it happened
+#[derive(Debug, Clone)]
+pub struct Ice {
+    /// the panic's message
+    pub message: String,
+    /// where in charon's source it panicked, as `file:line:column`
+    pub location: Option<String>,
+    pub context: Context,
+    /// the version of charon that panicked
+    pub version: String,
+    pub backtrace: String,
+}
+
+impl Ice {
+    /// the diagnostic to show, pointing at the span being worked on if there was one
+    pub fn diagnostic(&self) -> Diagnostic {
+        let mut diagnostic =
+            Diagnostic::error(format!("internal compiler error: {}", self.message));
+        if let Some(span) = self.context.span {
+            diagnostic = diagnostic.with_label(Label::primary(span, "while working on this"));
+        }
+
+        let stage = match (self.context.stage, &self.context.file) {
+            (Some(stage), Some(file)) => format!("while {} `{}`", stage, file),
+            (Some(stage), None) => format!("while {}", stage),
+            (None, _) => String::from("outside of any stage"),
+        };
+        diagnostic
+            .with_note(format!("charon {} panicked {}", self.version, stage))
+            .with_help(format!(
+                "this is a bug in charon; please report it at {} with the crash report",
+                ISSUES
+            ))
+    }
+
+    /// the full crash report, to be written to a file
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "charon internal compiler error").unwrap();
+        writeln!(out, "version: {}", self.version).unwrap();
+        writeln!(out, "message: {}", self.message).unwrap();
+        if let Some(location) = &self.location {
+            writeln!(out, "panicked at: {}", location).unwrap();
+        }
+        if let Some(stage) = self.context.stage {
+            writeln!(out, "stage: {}", stage).unwrap();
+        }
+        if let Some(file) = &self.context.file {
+            writeln!(out, "file: {}", file).unwrap();
+        }
+        if let Some((start, end)) = self.context.span {
+            writeln!(out, "span: {}..{}", start, end).unwrap();
+        }
+        write!(out, "\nbacktrace:\n{}", self.backtrace).unwrap();
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ice(context: Context) -> Ice {
+        Ice {
+            message: String::from("index out of bounds"),
+            location: Some(String::from("src/parse/src/lib.rs:10:5")),
+            context,
+            version: String::from("0.1.0"),
+            backtrace: String::from("0: main\n"),
+        }
+    }
+
+    #[test]
+    fn reports_the_stage_file_and_span() {
+        let ice = ice(Context {
+            stage: Some(Stage::Parse),
+            file: Some(String::from("main.chr")),
+            span: Some((4, 9)),
+        });
+
+        assert_eq!(
+            ice.report(),
+            "charon internal compiler error
+version: 0.1.0
+message: index out of bounds
+panicked at: src/parse/src/lib.rs:10:5
+stage: parsing
+file: main.chr
+span: 4..9
+
+backtrace:
+0: main
+"
+        );
+
+        let diagnostic = ice.diagnostic();
+        assert_eq!(diagnostic.primary().unwrap().span, (4, 9));
+        assert_eq!(
+            diagnostic.notes,
+            vec!["charon 0.1.0 panicked while parsing `main.chr`"]
+        );
+    }
+
+    #[test]
+    fn leaving_a_stage_restores_the_context() {
+        let file = in_file("main.chr");
+        {
+            let _lex = enter(Stage::Lex);
+            trace::at((3, 4));
+            assert_eq!(context().span, Some((3, 4)));
+
+            let parse = enter(Stage::Parse);
+            assert_eq!(
+                context(),
+                Context {
+                    stage: Some(Stage::Parse),
+                    file: Some(String::from("main.chr")),
+                    span: None,
+                }
+            );
+            drop(parse);
+            assert_eq!(context().stage, Some(Stage::Lex));
+            assert_eq!(context().span, Some((3, 4)));
+        }
+
+        assert_eq!(context().stage, None);
+        drop(file);
+        assert_eq!(context(), Context::default());
+    }
+
+    #[test]
+    fn leaves_out_missing_context() {
+        let ice = ice(Context::default());
+
+        assert!(ice.diagnostic().labels.is_empty());
+        assert!(!ice.report().contains("stage:"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Diagnostics as JSON, for editors and CI.
+//!
+//! Each diagnostic is a single line holding one object:
+//!
+//! ```text
+//! {"code":"C0001","severity":"error","message":"...","spans":[...],"notes":[],"help":[],
+//!  "suggestions":[...],"rendered":"..."}
+//! ```
+//!
+//! Every span gives its file, byte offsets, and one-based lines and columns, where columns count
+//! characters. Spans from labels also say whether they're primary and carry the label's message.
+//! Suggestions carry their applicability: `machine-applicable`, `maybe-incorrect` or
+//! `has-placeholders`.
+//! `rendered` is the human-readable form, without color.
+
+use std::fmt::Write;
+
+use lex::Span;
+
+use crate::{Diagnostic, LabelStyle, Renderer, SourceFile};
+
+/// a JSON object being written, with its fields in the order they're added
+struct Object(String);
+
+impl Object {
+    fn new() -> Object {
+        Object(String::from("{"))
+    }
+
+    /// adds a field whose value is already JSON
+    fn field(mut self, key: &str, value: impl ToString) -> Object {
+        if self.0.len() > 1 {
+            self.0.push(',');
+        }
+        self.0.push_str(&string(key));
+        self.0.push(':');
+        self.0.push_str(&value.to_string());
+        self
+    }
+
+    fn finish(mut self) -> String {
+        self.0.push('}');
+        self.0
+    }
+}
+
+/// `s` as a JSON string, quoted and escaped
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// a JSON array of values that are already JSON
+fn array(values: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", values.into_iter().collect::<Vec<_>>().join(","))
+}
+
+fn strings(values: &[String]) -> String {
+    array(values.iter().map(|value| string(value)))
+}
+
+fn span(file: &SourceFile, span: Span) -> Object {
+    let (line_start, column_start) = file.line_col(span.0);
+    let (line_end, column_end) = file.line_col(span.1);
+
+    Object::new()
+        .field("file", string(file.name))
+        .field("byte_start", span.0)
+        .field("byte_end", span.1)
+        .field("line_start", line_start + 1)
+        .field("column_start", column_start + 1)
+        .field("line_end", line_end + 1)
+        .field("column_end", column_end + 1)
+}
+
+/// the diagnostic as a JSON object on a single line
+pub fn to_json(diagnostic: &Diagnostic, file: &SourceFile) -> String {
+    let spans = diagnostic.labels.iter().map(|label| {
+        span(file, label.span)
+            .field("primary", label.style == LabelStyle::Primary)
+            .field("label", string(&label.message))
+            .finish()
+    });
+
+    let suggestions = diagnostic.suggestions.iter().map(|suggestion| {
+        Object::new()
+            .field("message", string(&suggestion.message))
+            .field("span", span(file, suggestion.span).finish())
+            .field("replacement", string(&suggestion.replacement))
+            .field(
+                "applicability",
+                string(&suggestion.applicability.to_string()),
+            )
+            .finish()
+    });
+
+    let code = match &diagnostic.code {
+        Some(code) => string(code),
+        None => String::from("null"),
+    };
+
+    Object::new()
+        .field("code", code)
+        .field("severity", string(&diagnostic.severity.to_string()))
+        .field("message", string(&diagnostic.message))
+        .field("spans", array(spans))
+        .field("notes", strings(&diagnostic.notes))
+        .field("help", strings(&diagnostic.help))
+        .field("suggestions", array(suggestions))
+        .field(
+            "rendered",
+            string(&Renderer::new(false).render(diagnostic, file)),
+        )
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{Applicability, Label};
+
+    #[test]
+    fn spans_have_offsets_lines_and_columns() {
+        let file = SourceFile::new("main.chr", "const x = 1;\nx = 2;");
+        let diagnostic = Diagnostic::error("cannot assign twice to constant `x`")
+            .with_code("C0001")
+            .with_label(Label::primary((13, 18), "cannot assign"))
+            .with_label(Label::secondary((6, 7), "declared here"))
+            .with_suggestion(
+                "make it mutable",
+                (0, 5),
+                "let",
+                Applicability::MaybeIncorrect,
+            );
+
+        let value: Value = serde_json::from_str(&to_json(&diagnostic, &file)).unwrap();
+
+        assert_eq!(value["code"], "C0001");
+        assert_eq!(value["severity"], "error");
+        assert_eq!(
+            value["spans"][0],
+            json!({
+                "file": "main.chr",
+                "byte_start": 13,
+                "byte_end": 18,
+                "line_start": 2,
+                "column_start": 1,
+                "line_end": 2,
+                "column_end": 6,
+                "primary": true,
+                "label": "cannot assign",
+            })
+        );
+        assert_eq!(value["spans"][1]["primary"], false);
+        assert_eq!(value["suggestions"][0]["replacement"], "let");
+        assert_eq!(value["suggestions"][0]["span"]["column_end"], 6);
+        assert_eq!(value["suggestions"][0]["applicability"], "maybe-incorrect");
+        assert!(value["rendered"]
+            .as_str()
+            .unwrap()
+            .starts_with("error[C0001]"));
+    }
+
+    #[test]
+    fn is_a_single_line() {
+        let file = SourceFile::new("main.chr", "a\nb");
+        let diagnostic = Diagnostic::warning("two\nlines").with_note("a\nnote");
+
+        assert_eq!(to_json(&diagnostic, &file).lines().count(), 1);
+    }
+
+    #[test]
+    fn escapes_strings() {
+        let file = SourceFile::new("main.chr", "");
+        let diagnostic = Diagnostic::error("a \"quote\", a \\ and a \u{7}");
+
+        let value: Value = serde_json::from_str(&to_json(&diagnostic, &file)).unwrap();
+        assert_eq!(value["message"], "a \"quote\", a \\ and a \u{7}");
+        assert_eq!(value["code"], Value::Null);
+    }
+}
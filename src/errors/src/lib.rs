@@ -1,54 +1,219 @@
-// use std::fmt;
-
-// pub enum Error {
-//     LexError(LexError),
-//     ParseError(ParseError),
-//     TypeError(TypeError),
-//     ICE(Ice),
-// }
-
-// impl std::fmt::Display for Error {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         match self {
-//             Error::LexError(err) => write!(f, "{}", err.display()),
-//             Error::ParseError(_) => {}
-//             Error::TypeError(_) => {}
-//             Error::ICE(_) => {}
-//         }
-//     }
-// }
-
-// pub struct ErrorMessage<'a> {
-//     file: &'a str,
-//     line: usize,
-//     column: usize,
-//     // length from column to end of error
-//     len: usize,
-//     source_text: &'a str,
-//     message: Error,
-// }
-
-// impl<'a> ErrorMessage<'a> {
-//     pub fn new(
-//         line: usize,
-//         column: usize,
-//         len: usize,
-//         source: &'a str,
-//         message: Error,
-//     ) -> ErrorMessage {
-//         ErrorMessage {
-//             line,
-//             column,
-//             len,
-//             source_text: source,
-//             message,
-//         }
-//     }
-// }
-
-// pub fn formatted(error: ErrorMessage) {
-//     println!(
-//         "error: line {}:{}\n{}\n{}",
-//         error.line, error.column, error.source_text, error.message
-//     )
-// }
+//! Diagnostics: the errors and warnings reported by every stage, in one shape.
+//!
+//! Each stage keeps its own error type, close to where the problem is found, and converts it into
+//! a `Diagnostic` to be reported. A diagnostic points into a single source with labeled spans: one
+//! primary label where the problem is, and any secondary labels giving context.
+
+use std::fmt;
+
+use lex::Span;
+
+mod convert;
+mod explain;
+pub mod fix;
+pub mod ice;
+pub mod json;
+pub mod render;
+
+pub use explain::{codes, explain};
+pub use render::{ColorChoice, Renderer, SourceFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelStyle {
+    /// where the problem is
+    Primary,
+    /// context for the problem, like where something was declared
+    Secondary,
+}
+
+/// a span of source with a message about it, which may be empty
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub style: LabelStyle,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn primary(span: Span, message: impl Into<String>) -> Label {
+        Label {
+            style: LabelStyle::Primary,
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub fn secondary(span: Span, message: impl Into<String>) -> Label {
+        Label {
+            style: LabelStyle::Secondary,
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+/// how sure a suggestion is to be what was meant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applicability {
+    /// certainly right, so it can be applied without asking, as `chrn fix` does
+    MachineApplicable,
+    /// probably right, but it might change what the program means
+    MaybeIncorrect,
+    /// has parts like `<name>` that must be filled in by hand
+    HasPlaceholders,
+}
+
+impl fmt::Display for Applicability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Applicability::MachineApplicable => write!(f, "machine-applicable"),
+            Applicability::MaybeIncorrect => write!(f, "maybe-incorrect"),
+            Applicability::HasPlaceholders => write!(f, "has-placeholders"),
+        }
+    }
+}
+
+/// a replacement for a span of source that would fix the problem; an empty span inserts, and an
+/// empty replacement removes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// a stable code identifying the kind of problem, like `C0001`
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Diagnostic {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Diagnostic {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        message: impl Into<String>,
+        span: Span,
+        replacement: impl Into<String>,
+        applicability: Applicability,
+    ) -> Diagnostic {
+        self.suggestions.push(Suggestion {
+            message: message.into(),
+            span,
+            replacement: replacement.into(),
+            applicability,
+        });
+        self
+    }
+
+    /// the first primary label, where the problem is
+    pub fn primary(&self) -> Option<&Label> {
+        self.labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// the severity and message, then the notes and help, without any source
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{}[{}]: {}", self.severity, code, self.message)?,
+            None => write!(f, "{}: {}", self.severity, self.message)?,
+        }
+        for note in &self.notes {
+            write!(f, "\n  = note: {}", note)?;
+        }
+        for help in &self.help {
+            write!(f, "\n  = help: {}", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_diagnostics() {
+        let diagnostic = Diagnostic::error("mismatched types")
+            .with_label(Label::secondary((0, 3), "declared here"))
+            .with_label(Label::primary((10, 12), "expected a number"))
+            .with_note("numbers and strings don't mix")
+            .with_help("convert it first");
+
+        assert_eq!(diagnostic.primary().unwrap().span, (10, 12));
+        assert!(diagnostic.is_error());
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: mismatched types\n  = note: numbers and strings don't mix\n  = help: convert it first"
+        );
+    }
+}
@@ -0,0 +1,382 @@
+//! Rendering diagnostics for a terminal, in the style of rustc.
+//!
+//! ```text
+//! error: cannot assign twice to constant `x`
+//!  --> main.chr:2:1
+//!   |
+//! 1 | const x = 1;
+//!   |       - declared as a constant here
+//! 2 | x = 2;
+//!   | ^^^^^ cannot assign to a constant
+//!   |
+//!   = help: declare `x` with `let` to allow reassignment
+//! ```
+//!
+//! Labels underline only the first line of the span they cover.
+
+use std::fmt::Write;
+
+use crate::{Diagnostic, Label, LabelStyle, Severity};
+
+/// a named source, with the offset each line starts at
+pub struct SourceFile<'a> {
+    pub name: &'a str,
+    pub source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceFile<'a> {
+    pub fn new(name: &'a str, source: &'a str) -> SourceFile<'a> {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+
+        SourceFile {
+            name,
+            source,
+            line_starts,
+        }
+    }
+
+    /// the zero-based line and character column of a byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let column = self.source[self.line_starts[line]..offset].chars().count();
+
+        (line, column)
+    }
+
+    /// the text of a zero-based line, without its line ending
+    pub fn line(&self, line: usize) -> &'a str {
+        self.source[self.line_starts[line]..self.line_end(line)].trim_end_matches('\r')
+    }
+
+    /// the offset of the end of a zero-based line, before its newline
+    fn line_end(&self, line: usize) -> usize {
+        self.line_starts
+            .get(line + 1)
+            .map_or(self.source.len(), |next| next - 1)
+    }
+}
+
+/// whether to color output, as chosen with `--color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// only when writing to a terminal, and `NO_COLOR` isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// `auto`, `always` or `never`
+    pub fn parse(choice: &str) -> Option<ColorChoice> {
+        match choice {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// true if output should be colored, given whether it's going to a terminal and whether
+    /// `NO_COLOR` is set to anything but an empty string
+    pub fn enabled(self, terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal && !no_color,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// columns a tab is drawn as
+const TAB_WIDTH: usize = 4;
+
+pub struct Renderer {
+    pub color: bool,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Renderer {
+        Renderer { color }
+    }
+
+    /// `text` wrapped in an escape code, if coloring
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color && !text.is_empty() {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn severity_style(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+
+    /// a diagnostic with the lines of `file` it points at, ending in a newline
+    pub fn render(&self, diagnostic: &Diagnostic, file: &SourceFile) -> String {
+        let mut out = String::new();
+        let style = Renderer::severity_style(diagnostic.severity);
+
+        let head = match &diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        writeln!(
+            out,
+            "{}{}",
+            self.paint(style, &head),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        )
+        .unwrap();
+
+        let mut labels: Vec<(usize, usize, &Label)> = diagnostic
+            .labels
+            .iter()
+            .map(|label| {
+                let (line, column) = file.line_col(label.span.0);
+                (line, column, label)
+            })
+            .collect();
+        labels.sort_by_key(|(line, column, _)| (*line, *column));
+
+        let last_line = labels.iter().map(|(line, ..)| line + 1).max().unwrap_or(0);
+        let gutter = " ".repeat(last_line.to_string().len());
+        let bar = self.paint(BLUE, "|");
+
+        let location = match diagnostic.primary().or_else(|| diagnostic.labels.first()) {
+            Some(label) => {
+                let (line, column) = file.line_col(label.span.0);
+                format!("{}:{}:{}", file.name, line + 1, column + 1)
+            }
+            None => file.name.to_string(),
+        };
+        writeln!(out, "{}{} {}", gutter, self.paint(BLUE, "-->"), location).unwrap();
+
+        if !labels.is_empty() {
+            writeln!(out, "{} {}", gutter, bar).unwrap();
+        }
+
+        let mut previous: Option<usize> = None;
+        for (line, column, label) in &labels {
+            if previous != Some(*line) {
+                // lines between labels are left out, marked by `...` if there's more than one
+                if let Some(previous) = previous {
+                    if line - previous > 2 {
+                        writeln!(out, "{}", self.paint(BLUE, "...")).unwrap();
+                    } else if line - previous == 2 {
+                        self.source_line(&mut out, file, previous + 1, &gutter);
+                    }
+                }
+                self.source_line(&mut out, file, *line, &gutter);
+                previous = Some(*line);
+            }
+
+            // spans running over several lines are underlined to the end of their first
+            let text = file.line(*line);
+            let (_, end_column) = file.line_col(label.span.1.min(file.line_end(*line)));
+            let start: usize = text.chars().take(*column).map(display_width).sum();
+            let width: usize = text
+                .chars()
+                .skip(*column)
+                .take(end_column.saturating_sub(*column))
+                .map(display_width)
+                .sum();
+
+            let (marker, style) = match label.style {
+                LabelStyle::Primary => ("^", style),
+                LabelStyle::Secondary => ("-", BLUE),
+            };
+            let underline = marker.repeat(width.max(1));
+            let message = if label.message.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", label.message)
+            };
+            writeln!(
+                out,
+                "{} {} {}{}",
+                gutter,
+                bar,
+                " ".repeat(start),
+                self.paint(style, &(underline + &message))
+            )
+            .unwrap();
+        }
+
+        if !diagnostic.notes.is_empty()
+            || !diagnostic.help.is_empty()
+            || !diagnostic.suggestions.is_empty()
+        {
+            if !labels.is_empty() {
+                writeln!(out, "{} {}", gutter, bar).unwrap();
+            }
+            for note in &diagnostic.notes {
+                writeln!(out, "{} {} note: {}", gutter, self.paint(BLUE, "="), note).unwrap();
+            }
+            for help in &diagnostic.help {
+                writeln!(out, "{} {} help: {}", gutter, self.paint(BLUE, "="), help).unwrap();
+            }
+            for suggestion in &diagnostic.suggestions {
+                writeln!(
+                    out,
+                    "{} {} help: {}",
+                    gutter,
+                    self.paint(BLUE, "="),
+                    suggestion.message
+                )
+                .unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// a numbered line of source, with tabs expanded
+    fn source_line(&self, out: &mut String, file: &SourceFile, line: usize, gutter: &str) {
+        let number = format!("{:>width$}", line + 1, width = gutter.len());
+        let text = file.line(line).replace('\t', &" ".repeat(TAB_WIDTH));
+        let text = text.trim_end();
+        if text.is_empty() {
+            writeln!(
+                out,
+                "{} {}",
+                self.paint(BLUE, &number),
+                self.paint(BLUE, "|")
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "{} {} {}",
+                self.paint(BLUE, &number),
+                self.paint(BLUE, "|"),
+                text
+            )
+            .unwrap();
+        }
+    }
+}
+
+fn display_width(c: char) -> usize {
+    if c == '\t' {
+        TAB_WIDTH
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diagnostic: &Diagnostic, source: &str) -> String {
+        Renderer::new(false).render(diagnostic, &SourceFile::new("main.chr", source))
+    }
+
+    #[test]
+    fn finds_lines_and_columns() {
+        let file = SourceFile::new("a", "ab\r\nc\n\né");
+
+        assert_eq!(file.line_col(0), (0, 0));
+        assert_eq!(file.line_col(4), (1, 0));
+        assert_eq!(file.line_col(7), (3, 0));
+        assert_eq!(file.line_col(9), (3, 1));
+        assert_eq!(file.line(0), "ab");
+        assert_eq!(file.line(2), "");
+    }
+
+    #[test]
+    fn renders_primary_and_secondary_labels() {
+        let diagnostic = Diagnostic::error("cannot assign twice to constant `x`")
+            .with_code("C0001")
+            .with_label(Label::primary((13, 18), "cannot assign to a constant"))
+            .with_label(Label::secondary((6, 7), "declared as a constant here"))
+            .with_note("constants never change")
+            .with_help("declare `x` with `let` to allow reassignment");
+
+        assert_eq!(
+            render(&diagnostic, "const x = 1;\nx = 2;\n"),
+            "error[C0001]: cannot assign twice to constant `x`
+ --> main.chr:2:1
+  |
+1 | const x = 1;
+  |       - declared as a constant here
+2 | x = 2;
+  | ^^^^^ cannot assign to a constant
+  |
+  = note: constants never change
+  = help: declare `x` with `let` to allow reassignment
+"
+        );
+    }
+
+    #[test]
+    fn elides_distant_lines() {
+        let source = "let a = 1;\n\nlet b = 2;\nlet c = 3;\n\n\n\n\n\n\nlet a = 4;";
+        let diagnostic = Diagnostic::warning("shadowed")
+            .with_label(Label::secondary((4, 5), "first"))
+            .with_label(Label::primary((4, 5), ""))
+            .with_label(Label::primary((40, 50), "again"));
+
+        assert_eq!(
+            render(&diagnostic, source),
+            "warning: shadowed
+  --> main.chr:1:5
+   |
+ 1 | let a = 1;
+   |     - first
+   |     ^
+...
+11 | let a = 4;
+   | ^^^^^^^^^^ again
+"
+        );
+    }
+
+    #[test]
+    fn underlines_the_end_of_input_and_tabs() {
+        let diagnostic = Diagnostic::error("unexpected end of input")
+            .with_label(Label::primary((6, 6), "expected more"));
+
+        assert_eq!(
+            render(&diagnostic, "\tlet x"),
+            "error: unexpected end of input
+ --> main.chr:1:7
+  |
+1 |     let x
+  |          ^ expected more
+"
+        );
+    }
+
+    #[test]
+    fn colors_only_when_asked() {
+        let diagnostic = Diagnostic::error("oops");
+        let file = SourceFile::new("main.chr", "");
+
+        assert!(Renderer::new(true)
+            .render(&diagnostic, &file)
+            .starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: oops\x1b[0m"));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+        assert!(ColorChoice::Auto.enabled(true, false));
+        assert!(ColorChoice::Always.enabled(false, true));
+        assert_eq!(ColorChoice::parse("never"), Some(ColorChoice::Never));
+    }
+}
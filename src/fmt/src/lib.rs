@@ -0,0 +1,1001 @@
+//! Canonical formatting for charon source.
+//!
+//! The program is printed back from the AST, while comments come from the lossless token stream
+//! and are placed before the statement that follows them, or kept at the end of the line they
+//! trailed.
+
+use lex::{error::LexError, tokenise, trace, Span, TokenType};
+use parse::{
+    error::ParseError,
+    expr::{ModulePath, Node, Op, Pattern, Spanned, TypeExpr, Var},
+    parse,
+};
+
+pub struct Config {
+    /// preferred maximum line width
+    pub width: usize,
+    /// spaces per level of indentation
+    pub indent: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            width: 100,
+            indent: 4,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Lex(LexError),
+    Parse(ParseError),
+}
+
+/// formats a whole source file
+pub fn format_source(source: &str, config: &Config) -> Result<String, FormatError> {
+    let tokens = tokenise(source).map_err(FormatError::Lex)?;
+    let comments = tokens
+        .iter()
+        .filter(|t| matches!(t.token_type, TokenType::Comment | TokenType::DocComment(_)))
+        .map(|t| t.span)
+        .collect();
+    let ast = parse(tokens).map_err(FormatError::Parse)?;
+
+    let mut formatter = Formatter {
+        source,
+        comments,
+        next_comment: 0,
+        trailing: 0,
+        config,
+    };
+
+    Ok(formatter.statements(&ast, None, 0, source.len()))
+}
+
+/// formats a program that's been rewritten since it was parsed from `source`, like an optimised one
+///
+/// Blank lines are kept where `source` has them between statements, but comments are dropped, as
+/// the code they were about may be gone.
+pub fn format_program(program: &[Spanned<Node>], source: &str, config: &Config) -> String {
+    let mut formatter = Formatter {
+        source,
+        comments: Vec::new(),
+        next_comment: 0,
+        trailing: 0,
+        config,
+    };
+
+    formatter.statements(program, None, 0, source.len())
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    /// spans of every comment, in source order
+    comments: Vec<Span>,
+    /// index of the first comment not yet printed
+    next_comment: usize,
+    /// width of what follows the node being printed on its last line, like a statement's `;`
+    trailing: usize,
+    config: &'a Config,
+}
+
+impl<'a> Formatter<'a> {
+    fn indent(&self, depth: usize) -> String {
+        " ".repeat(depth * self.config.indent)
+    }
+
+    fn comment_text(&self, span: Span) -> &'a str {
+        self.source[span.0..span.1].trim_end()
+    }
+
+    /// true if the source between two offsets holds an empty line
+    fn blank_line_between(&self, from: usize, to: usize) -> bool {
+        from < to && self.source[from..to].matches('\n').count() >= 2
+    }
+
+    /// prints the comments starting before `limit`, each on its own line
+    fn leading_comments(
+        &mut self,
+        out: &mut String,
+        last: &mut Option<usize>,
+        limit: usize,
+        depth: usize,
+    ) {
+        while let Some(&comment) = self.comments.get(self.next_comment) {
+            if comment.0 >= limit {
+                break;
+            }
+
+            if let Some(last) = *last {
+                if self.blank_line_between(last, comment.0) {
+                    out.push('\n');
+                }
+            }
+
+            out.push_str(&self.indent(depth));
+            out.push_str(self.comment_text(comment));
+            out.push('\n');
+
+            *last = Some(comment.1);
+            self.next_comment += 1;
+        }
+    }
+
+    /// a comment following `end` on the same line, with only `;` in between
+    fn trailing_comment(&self, end: usize) -> Option<Span> {
+        let comment = *self.comments.get(self.next_comment)?;
+        if comment.0 < end {
+            return None;
+        }
+
+        let between = &self.source[end..comment.0];
+        if between.chars().all(|c| c == ';' || c == ' ' || c == '\t') {
+            Some(comment)
+        } else {
+            None
+        }
+    }
+
+    /// prints a sequence of statements, one per line, then the comments before `end`
+    fn statements(
+        &mut self,
+        nodes: &[Spanned<Node>],
+        value: Option<&Spanned<Node>>,
+        depth: usize,
+        end: usize,
+    ) -> String {
+        let mut out = String::new();
+        let mut last = None;
+
+        let statements = nodes.iter().map(|n| (n, true));
+        for (node, terminated) in statements.chain(value.map(|v| (v, false))) {
+            trace::at(node.span);
+            self.leading_comments(&mut out, &mut last, node.span.0, depth);
+            if let Some(last) = last {
+                if self.blank_line_between(last, node.span.0) {
+                    out.push('\n');
+                }
+            }
+
+            out.push_str(&self.indent(depth));
+            let checkpoint = self.next_comment;
+            let mut printed = self.statement(node, depth, terminated);
+            if self
+                .comments
+                .get(self.next_comment)
+                .is_some_and(|c| c.0 < node.span.1)
+            {
+                // a comment inside an expression has no place once it's reflowed, so the whole
+                // statement is kept as written
+                self.next_comment = checkpoint;
+                printed = self.verbatim(node, terminated);
+            }
+            out.push_str(&printed);
+
+            let mut item_end = node.span.1;
+            if let Some(comment) = self.trailing_comment(node.span.1) {
+                out.push(' ');
+                out.push_str(self.comment_text(comment));
+                item_end = comment.1;
+                self.next_comment += 1;
+            }
+
+            out.push('\n');
+            last = Some(item_end);
+        }
+
+        self.leading_comments(&mut out, &mut last, end, depth);
+
+        out
+    }
+
+    fn statement(&mut self, node: &Spanned<Node>, depth: usize, terminated: bool) -> String {
+        let column = depth * self.config.indent;
+        let terminator = terminator(node, terminated);
+        self.trailing = terminator.len();
+        self.node(node, depth, column) + terminator
+    }
+
+    /// prints a statement exactly as it's written in the source, skipping the comments inside it
+    fn verbatim(&mut self, node: &Spanned<Node>, terminated: bool) -> String {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.0 >= node.span.1 {
+                break;
+            }
+            self.next_comment += 1;
+        }
+
+        let text = &self.source[node.span.0..node.span.1];
+        text.to_string() + terminator(node, terminated)
+    }
+
+    fn block(&mut self, node: &Spanned<Node>, depth: usize) -> String {
+        if let Node::Block { body, value } = &node.node {
+            // a comment after the `{` stays on its line
+            let mut open = String::from("{");
+            if let Some(comment) = self.trailing_comment(node.span.0 + 1) {
+                open.push(' ');
+                open.push_str(self.comment_text(comment));
+                self.next_comment += 1;
+            }
+
+            let inner = self.statements(body, value.as_deref(), depth + 1, node.span.1);
+            if inner.is_empty() && open.len() == 1 {
+                String::from("{}")
+            } else {
+                format!("{}\n{}{}}}", open, inner, self.indent(depth))
+            }
+        } else {
+            self.node(node, depth, depth * self.config.indent)
+        }
+    }
+
+    /// prints an operand of `parent`, parenthesised if it binds more loosely
+    fn operand(
+        &mut self,
+        node: &Spanned<Node>,
+        parent: &Op,
+        is_rhs: bool,
+        depth: usize,
+        column: usize,
+    ) -> String {
+        let needs_parentheses = match &node.node {
+            // operators are left associative, so an equal-precedence right operand keeps its group
+            Node::BinaryExpr { operation, .. } => {
+                operation.precedence() < parent.precedence()
+                    || (is_rhs && operation.precedence() == parent.precedence())
+            }
+            Node::Assign { .. } => true,
+            // the body of `|x| body` would take in the rest of the expression
+            _ => is_short_closure(node),
+        };
+
+        if needs_parentheses {
+            format!("({})", self.node(node, depth, column + 1))
+        } else {
+            self.node(node, depth, column)
+        }
+    }
+
+    /// prints any node as an expression, starting at `column`
+    fn node(&mut self, node: &Spanned<Node>, depth: usize, column: usize) -> String {
+        match &node.node {
+            Node::Variable {
+                public,
+                variable_type,
+                pattern: bound,
+                ty,
+                value,
+            } => {
+                let keyword = match variable_type {
+                    Var::Let => "let",
+                    Var::Const => "const",
+                };
+
+                let head = format!(
+                    "{}{} {}{} = ",
+                    visibility(*public),
+                    keyword,
+                    pattern(bound),
+                    annotation(ty.as_ref())
+                );
+                let value = self.node(value, depth, advance(column, &head));
+                head + &value
+            }
+            Node::BinaryExpr {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let lhs = self.operand(lhs, operation, false, depth, column);
+                let op = format!(" {} ", op_symbol(operation));
+                let rhs_column = advance(advance(column, &lhs), &op);
+                let rhs = self.operand(rhs, operation, true, depth, rhs_column);
+                lhs + &op + &rhs
+            }
+            Node::UnaryExpr { operation, body } => {
+                let symbol = match operation {
+                    TokenType::Not => "!",
+                    _ => "-",
+                };
+
+                if let Node::BinaryExpr { .. } = body.node {
+                    format!("{}({})", symbol, self.node(body, depth, column + 2))
+                } else {
+                    format!("{}{}", symbol, self.node(body, depth, column + 1))
+                }
+            }
+            Node::Fn {
+                public,
+                name,
+                args,
+                ret,
+                body,
+            } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|a| pattern(&a.pattern) + &annotation(a.ty.as_ref()))
+                    .collect();
+                let ret = match ret {
+                    Some(ret) => format!(" -> {}", type_expr(ret)),
+                    None => String::new(),
+                };
+
+                format!(
+                    "{}fn {}({}){} {}",
+                    visibility(*public),
+                    self.node(name, depth, column),
+                    args.join(", "),
+                    ret,
+                    self.block(body, depth)
+                )
+            }
+            Node::Closure { args, ret, body } => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|a| pattern(&a.pattern) + &annotation(a.ty.as_ref()))
+                    .collect();
+
+                if is_short_closure(node) {
+                    let head = format!("|{}| ", args.join(", "));
+                    let body = self.node(body, depth, advance(column, &head));
+                    return head + &body;
+                }
+
+                let ret = match ret {
+                    Some(ret) => format!(" -> {}", type_expr(ret)),
+                    None => String::new(),
+                };
+                format!("fn({}){} {}", args.join(", "), ret, self.block(body, depth))
+            }
+            Node::FnCall { function, args } => {
+                let callee = self.postfix_target(function, depth, column);
+                let open = format!("{}(", callee);
+                self.delimited(open, args, ")", depth, column)
+            }
+            Node::Assign {
+                operation,
+                target,
+                value,
+            } => {
+                let symbol = operation.as_ref().map_or("", op_symbol);
+                let target = format!("{} {}= ", self.node(target, depth, column), symbol);
+                let value = self.node(value, depth, advance(column, &target));
+                target + &value
+            }
+            Node::List(items) => self.delimited(String::from("["), items, "]", depth, column),
+            // a single item needs its comma to stay a tuple
+            Node::Tuple(items) if items.len() == 1 => {
+                format!("({},)", self.node(&items[0], depth, column + 1))
+            }
+            Node::Tuple(items) => self.delimited(String::from("("), items, ")", depth, column),
+            Node::Map(entries) => {
+                let mut out = String::from("#{");
+                for (idx, entry) in entries.iter().enumerate() {
+                    if idx > 0 {
+                        out.push_str(", ");
+                    }
+                    let key = self.node(&entry.key, depth, advance(column, &out)) + ": ";
+                    out.push_str(&key);
+                    let value = self.node(&entry.value, depth, advance(column, &out));
+                    out.push_str(&value);
+                }
+                out.push('}');
+
+                out
+            }
+            Node::Index { target, index } => {
+                let target = self.postfix_target(target, depth, column) + "[";
+                let index = self.node(index, depth, advance(column, &target));
+                format!("{}{}]", target, index)
+            }
+            Node::Slice { target, start, end } => {
+                let mut out = self.postfix_target(target, depth, column) + "[";
+                if let Some(start) = start {
+                    let start = self.node(start, depth, advance(column, &out));
+                    out.push_str(&start);
+                }
+                out.push_str("..");
+                if let Some(end) = end {
+                    let end = self.node(end, depth, advance(column, &out));
+                    out.push_str(&end);
+                }
+                out.push(']');
+
+                out
+            }
+            Node::Block { .. } => self.block(node, depth),
+            Node::Return(value) => match value {
+                Some(value) => format!("return {}", self.node(value, depth, column + 7)),
+                None => String::from("return"),
+            },
+            Node::Struct {
+                public,
+                name,
+                fields,
+            } => {
+                let name = self.node(name, depth, column);
+                if fields.is_empty() {
+                    return format!("{}struct {} {{}}", visibility(*public), name);
+                }
+
+                let indent = self.indent(depth + 1);
+                let mut out = format!("{}struct {} {{\n", visibility(*public), name);
+                for field in fields {
+                    let field_name = self.node(&field.name, depth + 1, indent.len());
+                    out.push_str(&format!(
+                        "{}{}{},\n",
+                        indent,
+                        field_name,
+                        annotation(field.ty.as_ref())
+                    ));
+                }
+                out.push_str(&format!("{}}}", self.indent(depth)));
+
+                out
+            }
+            Node::Enum {
+                public,
+                name,
+                variants,
+            } => {
+                let name = self.node(name, depth, column);
+                if variants.is_empty() {
+                    return format!("{}enum {} {{}}", visibility(*public), name);
+                }
+
+                let indent = self.indent(depth + 1);
+                let mut out = format!("{}enum {} {{\n", visibility(*public), name);
+                for variant in variants {
+                    let variant_name = self.node(&variant.name, depth + 1, indent.len());
+                    out.push_str(&format!("{}{}", indent, variant_name));
+                    if !variant.fields.is_empty() {
+                        let fields: Vec<String> = variant.fields.iter().map(type_expr).collect();
+                        out.push_str(&format!("({})", fields.join(", ")));
+                    }
+                    out.push_str(",\n");
+                }
+                out.push_str(&format!("{}}}", self.indent(depth)));
+
+                out
+            }
+            Node::Path { name, variant } => {
+                format!("{}::{}", self.node(name, depth, column), variant.node)
+            }
+            // arms always go one per line
+            Node::Match { value, arms } => {
+                let mut out = String::from("match ");
+                let value = self.node(value, depth, column + out.len());
+                out.push_str(&value);
+                out.push_str(" {\n");
+
+                let trailing = self.trailing;
+                let indent = self.indent(depth + 1);
+                for arm in arms {
+                    let head = format!("{}{} => ", indent, pattern(&arm.pattern));
+                    self.trailing = 1;
+                    let value = self.node(&arm.value, depth + 1, head.len());
+                    out.push_str(&format!("{}{},\n", head, value));
+                }
+                self.trailing = trailing;
+                out.push_str(&format!("{}}}", self.indent(depth)));
+
+                out
+            }
+            Node::Import { path, alias, items } => {
+                let mut out = match &path.node {
+                    ModulePath::File(file) => format!("import {}", string_literal(file)),
+                    ModulePath::Dotted(segments) => format!("import {}", segments.join(".")),
+                };
+
+                if let Some(alias) = alias {
+                    out.push_str(" as ");
+                    out.push_str(&self.node(alias, depth, column));
+                } else if !items.is_empty() {
+                    let items: Vec<String> =
+                        items.iter().map(|i| self.node(i, depth, column)).collect();
+                    out.push_str(&format!(" {{ {} }}", items.join(", ")));
+                }
+
+                out
+            }
+            Node::Attribute { inner, name, args } => {
+                let bang = if *inner { "!" } else { "" };
+                if args.is_empty() {
+                    format!("#{}[{}]", bang, name.node)
+                } else {
+                    let args: Vec<&str> = args.iter().map(|a| a.node.as_str()).collect();
+                    format!("#{}[{}({})]", bang, name.node, args.join(", "))
+                }
+            }
+            Node::Number(n) => format!("{}", n),
+            Node::String(s) => string_literal(s),
+            Node::Boolean(b) => format!("{}", b),
+            Node::Identifier(i) => i.clone(),
+        }
+    }
+
+    /// prints the target of a call, index or slice, parenthesised if it isn't a single term
+    fn postfix_target(&mut self, target: &Spanned<Node>, depth: usize, column: usize) -> String {
+        match target.node {
+            Node::BinaryExpr { .. } | Node::UnaryExpr { .. } | Node::Assign { .. } => {
+                format!("({})", self.node(target, depth, column + 1))
+            }
+            _ if is_short_closure(target) => {
+                format!("({})", self.node(target, depth, column + 1))
+            }
+            _ => self.node(target, depth, column),
+        }
+    }
+
+    /// prints items between `open` and `close` on one line, or one per line if that would be too
+    /// wide
+    fn delimited(
+        &mut self,
+        open: String,
+        items: &[Spanned<Node>],
+        close: &str,
+        depth: usize,
+        column: usize,
+    ) -> String {
+        // printing consumes comments, so both layouts start from the same place
+        let checkpoint = self.next_comment;
+        let trailing = self.trailing;
+
+        let mut flat = open.clone();
+        for (idx, item) in items.iter().enumerate() {
+            if idx > 0 {
+                flat.push_str(", ");
+            }
+            self.trailing = if idx + 1 == items.len() {
+                close.len() + trailing
+            } else {
+                1
+            };
+            let item = self.node(item, depth, advance(column, &flat));
+            flat.push_str(&item);
+        }
+        flat.push_str(close);
+
+        // what follows has to fit on the line too, unless the flat layout already broke a line
+        let width = match flat.lines().nth(1) {
+            None => column + flat.len() + trailing,
+            Some(_) => column + flat.lines().next().unwrap_or("").len(),
+        };
+        if items.is_empty() || width <= self.config.width {
+            self.trailing = trailing;
+            return flat;
+        }
+
+        self.next_comment = checkpoint;
+
+        let indent = self.indent(depth + 1);
+        let mut broken = format!("{}\n", open);
+        for item in items {
+            self.trailing = 1;
+            let item = self.node(item, depth + 1, indent.len());
+            broken.push_str(&format!("{}{},\n", indent, item));
+        }
+        self.trailing = trailing;
+        broken.push_str(&format!("{}{}", self.indent(depth), close));
+
+        broken
+    }
+}
+
+/// the punctuation printed after a statement
+fn terminator(node: &Spanned<Node>, terminated: bool) -> &'static str {
+    match node.node {
+        Node::Fn { .. } | Node::Struct { .. } | Node::Enum { .. } | Node::Attribute { .. } => "",
+        _ if terminated => ";",
+        _ => "",
+    }
+}
+
+fn type_expr(ty: &Spanned<TypeExpr>) -> String {
+    match &ty.node {
+        TypeExpr::Named { name, generics } if generics.is_empty() => name.clone(),
+        TypeExpr::Named { name, generics } => {
+            let generics: Vec<String> = generics.iter().map(type_expr).collect();
+            format!("{}<{}>", name, generics.join(", "))
+        }
+        TypeExpr::Fn { params, ret } => {
+            let params: Vec<String> = params.iter().map(type_expr).collect();
+            match ret {
+                Some(ret) => format!("fn({}) -> {}", params.join(", "), type_expr(ret)),
+                None => format!("fn({})", params.join(", ")),
+            }
+        }
+    }
+}
+
+fn pattern(p: &Spanned<Pattern>) -> String {
+    match &p.node {
+        Pattern::Identifier(name) => name.clone(),
+        Pattern::Wildcard => String::from("_"),
+        Pattern::Number(n) => format!("{}", n),
+        Pattern::String(s) => string_literal(s),
+        Pattern::Boolean(b) => format!("{}", b),
+        Pattern::Tuple(items) if items.len() == 1 => format!("({},)", pattern(&items[0])),
+        Pattern::Tuple(items) => {
+            let items: Vec<String> = items.iter().map(pattern).collect();
+            format!("({})", items.join(", "))
+        }
+        Pattern::List { items, rest } => {
+            let mut items: Vec<String> = items.iter().map(pattern).collect();
+            match rest.as_deref() {
+                Some(Spanned {
+                    node: Pattern::Wildcard,
+                    ..
+                }) => items.push(String::from("..")),
+                Some(rest) => items.push(format!("..{}", pattern(rest))),
+                None => {}
+            }
+            format!("[{}]", items.join(", "))
+        }
+        Pattern::Struct { name, fields } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|f| match &f.pattern.node {
+                    Pattern::Identifier(binding) if *binding == f.name => f.name.clone(),
+                    _ => format!("{}: {}", f.name, pattern(&f.pattern)),
+                })
+                .collect();
+
+            let name = match name {
+                Some(name) => format!("{} ", name),
+                None => String::new(),
+            };
+            if fields.is_empty() {
+                format!("{}{{}}", name)
+            } else {
+                format!("{}{{ {} }}", name, fields.join(", "))
+            }
+        }
+        Pattern::Variant {
+            name,
+            variant,
+            fields,
+        } if fields.is_empty() => format!("{}::{}", name, variant),
+        Pattern::Variant {
+            name,
+            variant,
+            fields,
+        } => {
+            let fields: Vec<String> = fields.iter().map(pattern).collect();
+            format!("{}::{}({})", name, variant, fields.join(", "))
+        }
+    }
+}
+
+/// `|args| body`, the closure form without braces around the body
+fn is_short_closure(node: &Spanned<Node>) -> bool {
+    match &node.node {
+        Node::Closure { body, .. } => !matches!(body.node, Node::Block { .. }),
+        _ => false,
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    // strings have no escapes, so keep a quote that doesn't appear inside
+    let quote = if s.contains('"') { '`' } else { '"' };
+    format!("{}{}{}", quote, s, quote)
+}
+
+/// `pub ` for exported items, or nothing
+fn visibility(public: bool) -> &'static str {
+    if public {
+        "pub "
+    } else {
+        ""
+    }
+}
+
+/// `: Type` for an annotated binding, or nothing
+fn annotation(ty: Option<&Spanned<TypeExpr>>) -> String {
+    match ty {
+        Some(ty) => format!(": {}", type_expr(ty)),
+        None => String::new(),
+    }
+}
+
+/// the column reached after printing `text` from `column`
+fn advance(column: usize, text: &str) -> usize {
+    match text.rfind('\n') {
+        Some(idx) => text.len() - idx - 1,
+        None => column + text.len(),
+    }
+}
+
+fn op_symbol(op: &Op) -> &'static str {
+    match op {
+        Op::Add => "+",
+        Op::Subtract => "-",
+        Op::Multiply => "*",
+        Op::Divide => "/",
+        Op::Equal => "==",
+        Op::NotEqual => "!=",
+        Op::StrictEqual => "===",
+        Op::NotStrictEqual => "!==",
+        Op::Greater => ">",
+        Op::GreaterEqual => ">=",
+        Op::Less => "<",
+        Op::LessEqual => "<=",
+        Op::And => "and",
+        Op::Or => "or",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parse::visit::{walk_node_mut, VisitorMut};
+
+    use super::*;
+
+    fn format(source: &str) -> String {
+        format_source(source, &Config::default()).unwrap()
+    }
+
+    /// inputs covering every construct, in both tidy and untidy layouts
+    const CORPUS: &[&str] = &[
+        "let t = 2+2",
+        "const   x=1;let y =x*-2;",
+        "fn add(a,b){return a+b;}\nadd(1,2);",
+        "fn f() {\n\n\n    g();\n\n\n\n    h()\n}\n",
+        "// leading\nlet a = 1; // trailing\n\n\n/// docs ///\nfn b() {\n    // inside\n}\n",
+        "let x = (1 + 2) * (3 - (4 - 5)) / -(6 + 7);",
+        "let b = !true and x >= 2 or y !== \"s\";",
+        "call(aaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbb, cccccccccccccccccccc, dddddddddddddddddddd, eeeeeeeeee, ffff);",
+        "fn outer() {\n    fn inner(x) {\n        return;\n    }\n    inner(1)\n}",
+        "let s = `say \"hi\"`;",
+        "let m=#{\"a\":[1,2,],3:#{}};xs[0][n]=(ys[2]=[]);(a+b)[i..];f()[0]",
+        "let f=fn( x:Number )->Number{x*2};map(xs,|x,y|x+y);(|x|x)(1);(|x|x)+1;1+(||2)()",
+        "let x :List< Number >= 1;fn f(a:Number,b)->fn( Number,String )->Map<String,Number>{a}",
+        "struct Point{x:Number,y:Number,label}\nstruct Unit {}",
+        "import \"util/str\";import std.math as m;\nimport std.io{read,write};\npub fn f(){}pub const c=1;",
+        "x=1;x+=y-=2;xs[0]*=(a/=b);",
+        "#![deny( loose_equality )]\n#[allow(unused_variable,shadowed_binding)]let x=1;fn f(){#[inline]g()}",
+        "let {x,y:[a,..rest]}=p;let [..]=xs;let [h,..]=xs;fn f((a,_),Point{x,y:y2}){}let t=((1,),(),(a,b));",
+        "pub enum Shape{Circle(Number),Rect(Number,Number),Empty}enum Never{}\nlet s=Shape::Circle(1);",
+        "fn f(s){let n=match s{Shape::Circle(r)=>r*r,Shape::Empty=>0,_=>match r{[]=>1,[x,..]=>x}};n}",
+        "",
+        "// only a comment",
+    ];
+
+    #[test]
+    fn formatting_is_idempotent() {
+        for source in CORPUS {
+            let once = format(source);
+            let twice = format(&once);
+            assert_eq!(once, twice, "formatting {:?} twice changed it", source);
+        }
+    }
+
+    /// zeroes every span, so trees can be compared by shape
+    struct ClearSpans;
+
+    impl VisitorMut for ClearSpans {
+        fn visit_node_mut(&mut self, node: &mut Spanned<Node>) {
+            node.span = (0, 0);
+            match &mut node.node {
+                Node::Import { path, .. } => path.span = (0, 0),
+                Node::Path { variant, .. } => variant.span = (0, 0),
+                Node::Attribute { name, args, .. } => {
+                    name.span = (0, 0);
+                    args.iter_mut().for_each(|a| a.span = (0, 0));
+                }
+                _ => {}
+            }
+            walk_node_mut(self, node);
+        }
+
+        fn visit_pattern_mut(&mut self, p: &mut Spanned<Pattern>) {
+            p.span = (0, 0);
+            match &mut p.node {
+                Pattern::Tuple(items) => items.iter_mut().for_each(|i| self.visit_pattern_mut(i)),
+                Pattern::List { items, rest } => {
+                    items.iter_mut().for_each(|i| self.visit_pattern_mut(i));
+                    if let Some(rest) = rest {
+                        self.visit_pattern_mut(rest);
+                    }
+                }
+                Pattern::Struct { fields, .. } => fields
+                    .iter_mut()
+                    .for_each(|f| self.visit_pattern_mut(&mut f.pattern)),
+                Pattern::Variant { fields, .. } => {
+                    fields.iter_mut().for_each(|f| self.visit_pattern_mut(f))
+                }
+                _ => {}
+            }
+        }
+
+        fn visit_type_expr_mut(&mut self, ty: &mut Spanned<TypeExpr>) {
+            ty.span = (0, 0);
+            match &mut ty.node {
+                TypeExpr::Named { generics, .. } => generics
+                    .iter_mut()
+                    .for_each(|g| self.visit_type_expr_mut(g)),
+                TypeExpr::Fn { params, ret } => {
+                    params.iter_mut().for_each(|p| self.visit_type_expr_mut(p));
+                    if let Some(ret) = ret {
+                        self.visit_type_expr_mut(ret);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn formatting_preserves_the_ast() {
+        for source in CORPUS {
+            let shape = |src: &str| {
+                let mut ast = parse(tokenise(src).unwrap()).unwrap();
+                ast.iter_mut().for_each(|n| ClearSpans.visit_node_mut(n));
+                ast
+            };
+
+            assert_eq!(shape(source), shape(&format(source)), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn attributes_stand_alone() {
+        assert_eq!(
+            format("#![deny( loose_equality )]\n#[allow(a,b)]let x=1;"),
+            "#![deny(loose_equality)]\n#[allow(a, b)]\nlet x = 1;\n"
+        );
+    }
+
+    #[test]
+    fn normalises_spacing_and_semicolons() {
+        assert_eq!(
+            format("const   x=1;let y =x*-2"),
+            "const x = 1;\nlet y = x * -2;\n"
+        );
+        assert_eq!(
+            format("fn add(a,b){return a+b;}"),
+            "fn add(a, b) {\n    return a + b;\n}\n"
+        );
+    }
+
+    #[test]
+    fn prints_enums_and_match_arms_one_per_line() {
+        assert_eq!(
+            format("enum Shape{Circle(Number),Empty}\nlet a=match s{Shape::Circle(r)=>r*r,_=>0};"),
+            "enum Shape {
+    Circle(Number),
+    Empty,
+}
+let a = match s {
+    Shape::Circle(r) => r * r,
+    _ => 0,
+};
+"
+        );
+    }
+
+    #[test]
+    fn prints_imports_and_visibility() {
+        assert_eq!(
+            format("import std.math   as m;import \"a/b\"{x,y};\npub struct S{}pub let v=1"),
+            "import std.math as m;\nimport \"a/b\" { x, y };\npub struct S {}\npub let v = 1;\n"
+        );
+    }
+
+    #[test]
+    fn prints_closures() {
+        assert_eq!(
+            format("let f=fn( x:Number )->Number{x*2};map(xs,|x,y|x+y);(|x|x)(1);(|x|x)+1"),
+            "let f = fn(x: Number) -> Number {\n    x * 2\n};\nmap(xs, |x, y| x + y);\n(|x| x)(1);\n(|x| x) + 1;\n"
+        );
+    }
+
+    #[test]
+    fn prints_collections() {
+        assert_eq!(
+            format("let m=#{\"a\" :[1,2,],3:#{}};xs[i]=ys[ j ]=1;(a+b)[i..];xs[..2]"),
+            "let m = #{\"a\": [1, 2], 3: #{}};\nxs[i] = ys[j] = 1;\n(a + b)[i..];\nxs[..2];\n"
+        );
+        assert_eq!(
+            format("let long = [aaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbb, cccccccccccccccccccc, dddddddddddddddddddd, eeee];"),
+            "let long = [\n    aaaaaaaaaaaaaaaaaaaa,\n    bbbbbbbbbbbbbbbbbbbb,\n    cccccccccccccccccccc,\n    dddddddddddddddddddd,\n    eeee,\n];\n"
+        );
+    }
+
+    #[test]
+    fn prints_assignments() {
+        assert_eq!(
+            format("x=1;x+=y-=2;xs[0] *=-1"),
+            "x = 1;\nx += y -= 2;\nxs[0] *= -1;\n"
+        );
+    }
+
+    #[test]
+    fn prints_patterns_and_tuples() {
+        assert_eq!(
+            format("let {x,y:[a,..rest]}=p;let (a,b)=(1,);let [h,..]=xs;fn f(P{x},_){}"),
+            "let { x, y: [a, ..rest] } = p;\nlet (a, b) = (1,);\nlet [h, ..] = xs;\nfn f(P { x }, _) {}\n"
+        );
+    }
+
+    #[test]
+    fn prints_annotations() {
+        assert_eq!(
+            format("let x :List< Number >= 1;fn f(a:Number,b)->fn( Number )->Number{a}"),
+            "let x: List<Number> = 1;\nfn f(a: Number, b) -> fn(Number) -> Number {\n    a\n}\n"
+        );
+        assert_eq!(
+            format("struct Point{x:Number,y:Number,label}"),
+            "struct Point {\n    x: Number,\n    y: Number,\n    label,\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_needed_parentheses_only() {
+        assert_eq!(
+            format("let x = ((1 + 2)) * (3 - (4 - 5)) + (6 * 7);"),
+            "let x = (1 + 2) * (3 - (4 - 5)) + 6 * 7;\n"
+        );
+    }
+
+    #[test]
+    fn collapses_blank_lines() {
+        assert_eq!(
+            format("\n\na();\n\n\n\nb();\nc();\n\n"),
+            "a();\n\nb();\nc();\n"
+        );
+        assert_eq!(
+            format("fn f() {\n\n    g();\n\n}"),
+            "fn f() {\n    g();\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source =
+            "// leading\nlet a = 1; // trailing\n\n/// docs ///\nfn b() {\n    // inside\n}\n";
+        assert_eq!(format(source), source);
+    }
+
+    #[test]
+    fn wraps_long_calls() {
+        let source = "call(aaaaaaaaaaaaaaaaaaaaaaaaa, bbbbbbbbbbbbbbbbbbbbbbbbbbbbbb, cccccccccccccccccccccccccccccc, dddd);";
+        assert_eq!(
+            format(source),
+            "call(\n    aaaaaaaaaaaaaaaaaaaaaaaaa,\n    bbbbbbbbbbbbbbbbbbbbbbbbbbbbbb,\n    cccccccccccccccccccccccccccccc,\n    dddd,\n);\n"
+        );
+    }
+
+    #[test]
+    fn counts_the_semicolon_in_the_width() {
+        let source = "call(aaaa, bbbb);\n";
+        let at = |width| format_source(source, &Config { width, indent: 4 }).unwrap();
+
+        assert_eq!(at(source.len() - 1), source);
+        assert_eq!(at(source.len() - 2), "call(\n    aaaa,\n    bbbb,\n);\n");
+    }
+
+    #[test]
+    fn keeps_comments_next_to_their_tokens() {
+        let source =
+            "fn f(a) { // trailing\n    let x = 1 + // inside expr\n        2;\n    g(x)\n}\n";
+        assert_eq!(format(source), source);
+        assert_eq!(format("fn e() { // empty\n}"), "fn e() { // empty\n}\n");
+    }
+
+    #[test]
+    fn prints_rewritten_programs_without_comments() {
+        let source = "// gone\nlet a = 1 + 2;\n\nprint(a);\n";
+        let mut program = parse(tokenise(source).unwrap()).unwrap();
+        if let Node::Variable { value, .. } = &mut program[0].node {
+            value.node = Node::Number(3.0);
+        }
+
+        assert_eq!(
+            format_program(&program, source, &Config::default()),
+            "let a = 3;\n\nprint(a);\n"
+        );
+    }
+}
@@ -1,6 +1,9 @@
-#[derive(Debug)]
+use crate::Span;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LexError {
-    UnterminatedString,
-    InvalidNumber,
-    UnterminatedComment
+    /// a string missing its closing quote, spanning from the opening quote to the end of input
+    UnterminatedString(Span),
+    InvalidNumber(Span),
+    UnterminatedComment(Span),
 }
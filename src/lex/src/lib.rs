@@ -1,10 +1,28 @@
 pub mod error;
-use std::collections::HashMap;
-
 use error::LexError;
 
+pub mod suggest;
 pub mod tokens;
-pub use tokens::{Token, TokenType};
+pub mod trace;
+pub use tokens::{Span, Token, TokenType};
+
+/// every reserved word and the token it lexes to
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("and", TokenType::And),
+    ("or", TokenType::Or),
+    ("match", TokenType::Match),
+    ("enum", TokenType::Enum),
+    ("struct", TokenType::Struct),
+    ("let", TokenType::Let),
+    ("const", TokenType::Const),
+    ("false", TokenType::False),
+    ("true", TokenType::True),
+    ("fn", TokenType::Fn),
+    ("return", TokenType::Return),
+    ("import", TokenType::Import),
+    ("as", TokenType::As),
+    ("pub", TokenType::Pub),
+];
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
@@ -15,7 +33,7 @@ pub struct Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
-    fn new(source: &str) -> Lexer {
+    fn new(source: &str) -> Lexer<'_> {
         Lexer {
             source,
             start: 0,
@@ -26,29 +44,30 @@ impl<'a> Lexer<'a> {
 
     // returns current character without advancing lexer
     fn peek(&self) -> Option<char> {
-        self.source.chars().nth(self.index)
+        self.source.get(self.index..)?.chars().next()
     }
 
     // returns the nth character after index without advancing lexer
     fn peek_nth(&self, idx: usize) -> Option<char> {
-        self.source.chars().nth(self.index + idx)
+        self.source.get(self.index..)?.chars().nth(idx)
     }
 
     // returns next character and advances lexer by one
     fn next(&mut self) -> Option<char> {
         if !self.is_ended() {
-            self.index += 1;
+            // index is a byte offset, so step over the whole character
+            self.index += self.peek().map_or(1, char::len_utf8);
         }
 
         self.peek()
     }
 
     fn next_nth(&mut self, idx: usize) -> Option<char> {
-        if !self.is_ended() {
-            self.index += idx;
+        for _ in 0..idx {
+            self.next();
         }
 
-        self.peek_nth(idx)
+        self.peek()
     }
 
     fn is_ended(&self) -> bool {
@@ -56,7 +75,7 @@ impl<'a> Lexer<'a> {
     }
 
     /// creates a span and updates lexer placing
-    fn span(&mut self) -> (usize, usize) {
+    fn span(&mut self) -> Span {
         let s = (self.start, self.index);
         self.start = self.index;
 
@@ -64,8 +83,6 @@ impl<'a> Lexer<'a> {
     }
 
     fn advance_line(&mut self) {
-        self.start = 0;
-        self.index = 0;
         self.line += 1;
     }
 
@@ -78,18 +95,21 @@ impl<'a> Lexer<'a> {
     }
 
     fn string(&mut self, starting_char: char) -> Result<String, LexError> {
-        while self.peek().unwrap() != starting_char && self.is_ended() == false {
-            if let Some(c) = self.peek() {
-                if c == '\n' {
-                    self.advance_line()
-                }
-            };
+        while let Some(c) = self.peek() {
+            if c == starting_char {
+                break;
+            } else if c == '\n' {
+                self.advance_line()
+            }
 
             self.next();
         }
 
-        if self.is_ended() == true {
-            return Err(LexError::UnterminatedString);
+        if self.peek().is_none() {
+            return Err(LexError::UnterminatedString((
+                self.start,
+                self.source.len(),
+            )));
         }
 
         // closing starting_char
@@ -104,7 +124,7 @@ impl<'a> Lexer<'a> {
     fn number(&mut self) -> Result<f64, LexError> {
         // scans until end of numbers, only advances lexer if there's a number
         while let Some(v) = self.peek() {
-            if v.is_numeric() {
+            if v.is_ascii_digit() {
                 self.next();
             } else {
                 break;
@@ -116,14 +136,14 @@ impl<'a> Lexer<'a> {
             if p == '.' {
                 // ensures that there are numbers after decimal point, consumes if there are
                 if let Some(v) = self.peek_nth(1) {
-                    if v.is_numeric() {
+                    if v.is_ascii_digit() {
                         self.next();
                     }
                 }
             }
 
             while let Some(v) = self.peek() {
-                if v.is_numeric() {
+                if v.is_ascii_digit() {
                     self.next();
                 } else {
                     break;
@@ -135,26 +155,13 @@ impl<'a> Lexer<'a> {
 
         match value.parse::<f64>() {
             Ok(v) => Ok(v),
-            Err(_) => Err(LexError::InvalidNumber),
+            Err(_) => Err(LexError::InvalidNumber((self.start, self.index))),
         }
     }
 
     fn identifier(&mut self) -> TokenType {
-        let mut reserved: HashMap<&'static str, TokenType> = HashMap::new();
-        reserved.insert("and", TokenType::And);
-        reserved.insert("or", TokenType::Or);
-        reserved.insert("match", TokenType::Match);
-        reserved.insert("enum", TokenType::Enum);
-        reserved.insert("struct", TokenType::Struct);
-        reserved.insert("let", TokenType::Let);
-        reserved.insert("const", TokenType::Const);
-        reserved.insert("false", TokenType::False);
-        reserved.insert("true", TokenType::True);
-        reserved.insert("fn", TokenType::Fn);
-        reserved.insert("return", TokenType::Return);
-
         while let Some(i) = self.peek() {
-            if i.is_alphabetic() || i == '_' {
+            if i.is_alphanumeric() || i == '_' {
                 self.next();
             } else {
                 break;
@@ -162,38 +169,41 @@ impl<'a> Lexer<'a> {
         }
 
         let identifier = &self.source[self.start..self.index];
-        if let Some(token_type) = reserved.get(identifier) {
-            token_type.to_owned()
-        } else {
-            TokenType::Identifier(identifier.to_string())
+        match KEYWORDS.iter().find(|(keyword, _)| *keyword == identifier) {
+            Some((_, token_type)) => token_type.clone(),
+            None => TokenType::Identifier(identifier.to_string()),
+        }
+    }
+
+    // consumes a line comment, leaving the newline for the main loop
+    fn comment(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+
+            self.next();
         }
     }
 
     fn doc_comment(&mut self) -> Result<String, LexError> {
-        let mut ends_with_newline = false;
+        // doc comments end at a newline or a closing `///`
+        let mut closed = false;
         while let Some(i) = self.peek() {
-            if i != '/' || i != '\n' {
-                self.next();
-            } else {
-                if i == '\n' {
-                    ends_with_newline = true;
-                } else if i == '/' {
-                    // todo: remove unwrap
-                    let next = self.peek().unwrap();
-                    let fin = self.peek_nth(1).unwrap();
-
-                    if fin != '/' || next != '/' {
-                        return Err(LexError::UnterminatedComment);
-                    }
-                }
-
+            if i == '\n' {
+                break;
+            } else if i == '/' && self.peek_nth(1) == Some('/') && self.peek_nth(2) == Some('/') {
+                self.next_nth(3);
+                closed = true;
                 break;
             }
+
+            self.next();
         }
 
-        let idx = match ends_with_newline {
-            true => self.index,
-            false => self.index - 3,
+        let idx = match closed {
+            true => self.index - 3,
+            false => self.index,
         };
 
         let comment = &self.source[self.start + 3..idx];
@@ -202,11 +212,12 @@ impl<'a> Lexer<'a> {
     }
 }
 
-pub fn tokenise(src: &str) -> Result<Vec<Token>, LexError> {
+pub fn tokenise(src: &str) -> Result<Vec<Token<'_>>, LexError> {
     let mut tokens = Vec::new();
     let mut lexer = Lexer::new(src);
 
     while !lexer.is_ended() {
+        trace::at((lexer.index, lexer.index));
         let c = lexer.peek();
         lexer.next();
 
@@ -218,127 +229,131 @@ pub fn tokenise(src: &str) -> Result<Vec<Token>, LexError> {
                     '{' => TokenType::LBrace,
                     '}' => TokenType::RBrace,
                     ',' => TokenType::Comma,
-                    '.' => TokenType::Period,
-                    '+' => TokenType::Plus,
-                    '-' => TokenType::Minus,
-                    '*' => TokenType::Asterisk,
+                    '[' => TokenType::LBracket,
+                    ']' => TokenType::RBracket,
+                    '#' => TokenType::Hash,
+                    '.' => match lexer.peek() {
+                        Some('.') => {
+                            lexer.next();
+                            TokenType::Range
+                        }
+                        _ => TokenType::Period,
+                    },
+                    '+' => match lexer.peek() {
+                        Some('=') => {
+                            lexer.next();
+                            TokenType::PlusEqual
+                        }
+                        _ => TokenType::Plus,
+                    },
+                    '-' => match lexer.peek() {
+                        Some('>') => {
+                            lexer.next();
+                            TokenType::Arrow
+                        }
+                        Some('=') => {
+                            lexer.next();
+                            TokenType::MinusEqual
+                        }
+                        _ => TokenType::Minus,
+                    },
+                    '*' => match lexer.peek() {
+                        Some('=') => {
+                            lexer.next();
+                            TokenType::AsteriskEqual
+                        }
+                        _ => TokenType::Asterisk,
+                    },
                     '/' => match lexer.peek() {
-                        Some(c) if c == '/' => {
-                            if let Some(c) = lexer.peek_nth(1) {
-                                match c {
-                                    '/' => {
-                                        lexer.next_nth(2);
-                                        let d = lexer.doc_comment();
-                                        match d {
-                                            Ok(d) => TokenType::DocComment(d),
-                                            Err(_) => todo!(),
-                                        }
-                                    }
-                                    _ => {
-                                        lexer.advance_line();
-                                        TokenType::Comment
-                                    }
-                                }
-                            } else {
-                                TokenType::EOI
-                            }
+                        Some('=') => {
+                            lexer.next();
+                            TokenType::SlashEqual
                         }
-                        Some(_) => TokenType::Slash,
-                        None => TokenType::EOI,
+                        Some('/') => match lexer.peek_nth(1) {
+                            Some('/') => {
+                                lexer.next_nth(2);
+                                TokenType::DocComment(lexer.doc_comment()?)
+                            }
+                            _ => {
+                                lexer.comment();
+                                TokenType::Comment
+                            }
+                        },
+                        _ => TokenType::Slash,
                     },
                     ';' => TokenType::Semicolon,
+                    '|' => TokenType::OrPipe,
+                    '&' => TokenType::AndAmpersand,
+                    ':' => match lexer.peek() {
+                        Some(':') => {
+                            lexer.next();
+                            TokenType::PathSeparator
+                        }
+                        _ => TokenType::Colon,
+                    },
                     '!' => match lexer.peek() {
-                        Some(c) => match c {
-                            '=' => {
-                                if let Some(c) = lexer.peek_nth(1) {
-                                    match c {
-                                        '=' => {
-                                            lexer.next_nth(2);
-                                            TokenType::NotStrictEqual
-                                        }
-                                        _ => {
-                                            lexer.next();
-                                            TokenType::NotEqual
-                                        }
-                                    }
-                                } else {
-                                    TokenType::EOI
-                                }
+                        Some('=') => match lexer.peek_nth(1) {
+                            Some('=') => {
+                                lexer.next_nth(2);
+                                TokenType::NotStrictEqual
+                            }
+                            _ => {
+                                lexer.next();
+                                TokenType::NotEqual
                             }
-                            _ => TokenType::Not,
                         },
-                        None => TokenType::EOI,
+                        _ => TokenType::Not,
                     },
                     '=' => match lexer.peek() {
-                        Some(c) => match c {
-                            '=' => {
-                                if let Some(c) = lexer.peek_nth(1) {
-                                    match c {
-                                        '=' => {
-                                            lexer.next_nth(2);
-                                            TokenType::StrictComparisonEqual
-                                        }
-                                        _ => {
-                                            lexer.next();
-                                            TokenType::ComparisonEqual
-                                        }
-                                    }
-                                } else {
-                                    TokenType::EOI
-                                }
+                        Some('=') => match lexer.peek_nth(1) {
+                            Some('=') => {
+                                lexer.next_nth(2);
+                                TokenType::StrictComparisonEqual
+                            }
+                            _ => {
+                                lexer.next();
+                                TokenType::ComparisonEqual
                             }
-                            _ => TokenType::Equal,
                         },
-                        None => TokenType::EOI,
+                        Some('>') => {
+                            lexer.next();
+                            TokenType::FatArrow
+                        }
+                        _ => TokenType::Equal,
                     },
                     '>' => match lexer.peek() {
-                        Some(c) => {
-                            if c == '=' {
-                                lexer.next();
-                                TokenType::GreaterThanEqualTo
-                            } else {
-                                TokenType::Greater
-                            }
+                        Some('=') => {
+                            lexer.next();
+                            TokenType::GreaterThanEqualTo
                         }
-                        None => TokenType::EOI,
+                        _ => TokenType::Greater,
                     },
                     '<' => match lexer.peek() {
-                        Some(c) => {
-                            if c == '=' {
-                                lexer.next();
-                                TokenType::LessThanEqualTo
-                            } else {
-                                TokenType::Less
-                            }
+                        Some('=') => {
+                            lexer.next();
+                            TokenType::LessThanEqualTo
                         }
-                        None => TokenType::EOI,
+                        _ => TokenType::Less,
                     },
                     '\t' | '\r' | ' ' => TokenType::Whitespace,
                     '\n' => {
                         lexer.advance_line();
                         TokenType::Whitespace
                     }
-                    '"' | '`' => {
-                        let s = lexer.string(c);
-                        match s {
-                            Ok(s) => TokenType::String(s),
-                            Err(_) => todo!(),
-                        }
-                    }
-                    '0'..='9' => {
-                        let n = lexer.number();
-                        match n {
-                            Ok(n) => TokenType::Number(n),
-                            Err(_) => todo!(),
-                        }
-                    }
+                    '"' | '`' => TokenType::String(lexer.string(c)?),
+                    '0'..='9' => TokenType::Number(lexer.number()?),
                     'a'..='z' | 'A'..='Z' | '_' => lexer.identifier(),
                     _ => TokenType::Unknown,
                 };
 
                 tokens.push(lexer.create_token(tok))
             }
-            None => tokens.push(lexer.create_token(TokenType::EOI)),
+            None => {
+                // the end of input token is zero-width, placed at the end of the source
+                lexer.index = src.len();
+                tokens.push(lexer.create_token(TokenType::EOI));
+                break;
+            }
         }
     }
 
@@ -346,19 +361,21 @@ pub fn tokenise(src: &str) -> Result<Vec<Token>, LexError> {
 }
 
 #[cfg(test)]
+#[allow(clippy::get_first, clippy::assertions_on_constants)]
 mod tests {
     use super::*;
 
     #[test]
     fn lexemes() {
-        assert_eq!(tokenise(r"!*+-/=<> <= >= == === !==").unwrap().len(), 19);
+        // `=` comes first, as `!=` and `/=` would be lexed as one token
+        assert_eq!(tokenise(r"=!*+-/<> <= >= == === !==").unwrap().len(), 19);
         // should be
-        // 1. 0-1 NOT
-        // 2. 1-2 ASTERISK
-        // 3. 2-3 PLUS
-        // 4. 0-1 MINUS
-        // 5. 0-1 SLASH
-        // 6. 0-1 EQUAL
+        // 1. 0-1 EQUAL
+        // 2. 1-2 NOT
+        // 3. 2-3 ASTERISK
+        // 4. 3-4 PLUS
+        // 5. 0-1 MINUS
+        // 6. 0-1 SLASH
         // 7. 0-1 LESS
         // 8. 0-1 GREATER
         // 9. 0-1 WHITESPACE
@@ -374,6 +391,91 @@ mod tests {
         // 19. 0-1 EOI
     }
 
+    #[test]
+    fn annotation_lexemes() {
+        let tok: Vec<TokenType> = tokenise("a: b -> -c")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token_type)
+            .filter(|t| *t != TokenType::Whitespace)
+            .collect();
+
+        assert_eq!(
+            tok,
+            vec![
+                TokenType::Identifier(String::from("a")),
+                TokenType::Colon,
+                TokenType::Identifier(String::from("b")),
+                TokenType::Arrow,
+                TokenType::Minus,
+                TokenType::Identifier(String::from("c")),
+                TokenType::EOI,
+            ]
+        );
+    }
+
+    #[test]
+    fn match_lexemes() {
+        let tok: Vec<TokenType> = tokenise("Shape::Circle(r) => a == b")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token_type)
+            .filter(|t| *t != TokenType::Whitespace)
+            .collect();
+
+        assert_eq!(
+            tok,
+            vec![
+                TokenType::Identifier(String::from("Shape")),
+                TokenType::PathSeparator,
+                TokenType::Identifier(String::from("Circle")),
+                TokenType::LParen,
+                TokenType::Identifier(String::from("r")),
+                TokenType::RParen,
+                TokenType::FatArrow,
+                TokenType::Identifier(String::from("a")),
+                TokenType::ComparisonEqual,
+                TokenType::Identifier(String::from("b")),
+                TokenType::EOI,
+            ]
+        );
+    }
+
+    #[test]
+    fn compound_assignment_lexemes() {
+        let tok: Vec<TokenType> = tokenise("a += b-=c *= -d /=e")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token_type)
+            .filter(|t| *t != TokenType::Whitespace)
+            .collect();
+
+        assert_eq!(
+            tok,
+            vec![
+                TokenType::Identifier(String::from("a")),
+                TokenType::PlusEqual,
+                TokenType::Identifier(String::from("b")),
+                TokenType::MinusEqual,
+                TokenType::Identifier(String::from("c")),
+                TokenType::AsteriskEqual,
+                TokenType::Minus,
+                TokenType::Identifier(String::from("d")),
+                TokenType::SlashEqual,
+                TokenType::Identifier(String::from("e")),
+                TokenType::EOI,
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_strings() {
+        assert_eq!(
+            tokenise("let s = \"abc").unwrap_err(),
+            LexError::UnterminatedString((8, 12))
+        );
+    }
+
     #[test]
     fn string_literals() {
         let tok = tokenise("\"This is a test.\"").unwrap();
@@ -435,6 +537,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn collections() {
+        let tokens = tokenise("#{ xs[1..2] }").unwrap();
+        let types: Vec<_> = tokens
+            .into_iter()
+            .filter(|t| t.token_type != TokenType::Whitespace)
+            .map(|t| (t.token_type, t.span))
+            .collect();
+
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::Hash, (0, 1)),
+                (TokenType::LBrace, (1, 2)),
+                (TokenType::Identifier(String::from("xs")), (3, 5)),
+                (TokenType::LBracket, (5, 6)),
+                (TokenType::Number(1.0), (6, 7)),
+                (TokenType::Range, (7, 9)),
+                (TokenType::Number(2.0), (9, 10)),
+                (TokenType::RBracket, (10, 11)),
+                (TokenType::RBrace, (12, 13)),
+                (TokenType::EOI, (13, 13)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipes() {
+        let tokens = tokenise("|x| &").unwrap();
+
+        assert_eq!(tokens[0].token_type, TokenType::OrPipe);
+        assert_eq!(tokens[2].token_type, TokenType::OrPipe);
+        assert_eq!(tokens[4].token_type, TokenType::AndAmpersand);
+    }
+
     #[test]
     fn keyword_identifiers() {
         let invalid = tokenise("and").unwrap();
@@ -450,9 +587,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn module_keywords() {
+        let import = tokenise("pub import as").unwrap();
+        let types: Vec<_> = import.into_iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Pub,
+                TokenType::Whitespace,
+                TokenType::Import,
+                TokenType::Whitespace,
+                TokenType::As,
+                TokenType::EOI
+            ]
+        );
+    }
+
     #[test]
     fn comment_tests() {}
 
+    #[test]
+    fn line_comments() {
+        let comment = tokenise("// a comment\n1").unwrap();
+        assert_eq!(comment.len(), 4);
+        // 1. Comment
+        // 2. Whitespace
+        // 3. Number
+        // 4. EOI
+
+        assert_eq!(comment[0].token_type, TokenType::Comment);
+        assert_eq!(comment[0].span, (0, 12));
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let tok = tokenise("let a\n= 1;").unwrap();
+
+        let spans: Vec<Span> = tok.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0, 3),
+                (3, 4),
+                (4, 5),
+                (5, 6),
+                (6, 7),
+                (7, 8),
+                (8, 9),
+                (9, 10),
+                (10, 10)
+            ]
+        );
+        assert_eq!(tok.last().unwrap().token_type, TokenType::EOI);
+    }
+
     #[test]
     fn doc_comment_tests() {
         let doc_comment = tokenise("/// This is a doc comment ///").unwrap();
@@ -0,0 +1,118 @@
+//! Finding the closest of a set of names to one that was misspelled.
+
+/// the number of single character insertions, deletions, substitutions and swaps of neighbouring
+/// characters turning `a` into `b`
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // d[i][j] is the distance between the first i characters of a and the first j of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j - 1] + cost)
+                .min(d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+/// the candidate closest to `name`, if any is near enough to be a likely typo: within a third of
+/// its length, so names under three characters never get one. Ties go to the first candidate.
+pub fn closest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let limit = name.chars().count() / 3;
+
+    let mut best: Option<(usize, &'a str)> = None;
+    for candidate in candidates {
+        if candidate == name {
+            continue;
+        }
+
+        let d = distance(name, candidate);
+        if d <= limit && best.is_none_or(|(best, _)| d < best) {
+            best = Some((d, candidate));
+        }
+    }
+
+    best.map(|(_, candidate)| candidate)
+}
+
+/// words other languages use for a keyword, which typos are matched against too, since a typo of
+/// `function` is too far from `fn` to be found
+const ALIASES: &[(&str, &str)] = &[
+    ("function", "fn"),
+    ("func", "fn"),
+    ("def", "fn"),
+    ("var", "let"),
+];
+
+/// the keyword closest to `name`, like `return` for `retrun` or `fn` for `fucntion`
+pub fn closest_keyword(name: &str) -> Option<&'static str> {
+    let keyword = |word: &'static str| {
+        ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == word)
+            .map_or(word, |(_, keyword)| *keyword)
+    };
+    if let Some((_, keyword)) = ALIASES.iter().find(|(alias, _)| *alias == name) {
+        return Some(keyword);
+    }
+
+    let keywords = crate::KEYWORDS.iter().map(|(keyword, _)| *keyword);
+    closest(
+        name,
+        keywords.chain(ALIASES.iter().map(|(alias, _)| *alias)),
+    )
+    .map(keyword)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_edits() {
+        assert_eq!(distance("retrun", "return"), 1);
+        assert_eq!(distance("", "let"), 3);
+        assert_eq!(distance("cosnt", "const"), 1);
+        assert_eq!(distance("fucntion", "fn"), 6);
+        assert_eq!(distance("héllo", "hello"), 1);
+    }
+
+    #[test]
+    fn suggests_only_near_names() {
+        let names = ["count", "total", "counter"];
+
+        assert_eq!(closest("cuont", names.iter().copied()), Some("count"));
+        assert_eq!(closest("x", ["y"].iter().copied()), None);
+        assert_eq!(closest("count", names.iter().copied()), None);
+        assert_eq!(closest_keyword("retrun"), Some("return"));
+        assert_eq!(closest_keyword("improt"), Some("import"));
+        assert_eq!(closest_keyword("value"), None);
+    }
+
+    #[test]
+    fn suggests_keywords_for_other_languages_words() {
+        assert_eq!(closest_keyword("fucntion"), Some("fn"));
+        assert_eq!(closest_keyword("function"), Some("fn"));
+        assert_eq!(closest_keyword("var"), Some("let"));
+        assert_eq!(closest_keyword("fn"), None);
+    }
+}
@@ -1,20 +1,26 @@
+/// byte range of a piece of source, end exclusive
+pub type Span = (usize, usize);
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Token<'a> {
     /// the type of token
     pub token_type: TokenType,
     /// token placement
-    pub span: (usize, usize),
+    pub span: Span,
     /// token source
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub source: &'a str,
 }
 
 #[derive(Debug)]
 pub struct Position {
-    line: usize,
-    col: usize,
+    pub line: usize,
+    pub col: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TokenType {
     // single characters
     // (
@@ -25,6 +31,12 @@ pub enum TokenType {
     LBrace,
     // }
     RBrace,
+    // [
+    LBracket,
+    // ]
+    RBracket,
+    // #
+    Hash,
     // ,
     Comma,
     // .
@@ -39,6 +51,8 @@ pub enum TokenType {
     Slash,
     // ;
     Semicolon,
+    // :
+    Colon,
 
     // 1-3 characters
     // !=
@@ -62,6 +76,22 @@ pub enum TokenType {
     LessThanEqualTo,
     // `!`
     Not,
+    // ->
+    Arrow,
+    // =>
+    FatArrow,
+    // ::
+    PathSeparator,
+    // +=
+    PlusEqual,
+    // -=
+    MinusEqual,
+    // *=
+    AsteriskEqual,
+    // /=
+    SlashEqual,
+    // ..
+    Range,
 
     // literals
     String(String),
@@ -97,6 +127,12 @@ pub enum TokenType {
     Fn,
     // 'return'
     Return,
+    // 'import'
+    Import,
+    // 'as'
+    As,
+    // 'pub'
+    Pub,
 
     // end of input
     EOI,
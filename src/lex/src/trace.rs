@@ -0,0 +1,42 @@
+//! The span the front end last reached, for reporting internal compiler errors.
+//!
+//! Each stage marks the span it's working on as it goes, so a panic partway through can be
+//! reported with where it happened. The mark is per thread and only ever overwritten, so keeping
+//! it costs a store per token or node. Which stage is running, and on which file, is tracked by
+//! the caller, which clears the mark as each stage starts.
+
+use std::cell::Cell;
+
+use crate::Span;
+
+thread_local! {
+    static REACHED: Cell<Option<Span>> = const { Cell::new(None) };
+}
+
+/// records the span the current stage has reached
+pub fn at(span: Span) {
+    REACHED.with(|reached| reached.set(Some(span)));
+}
+
+/// the span last reached on this thread, if one has been since the mark was last cleared
+pub fn reached() -> Option<Span> {
+    REACHED.with(Cell::get)
+}
+
+/// replaces the mark, returning the one it replaces
+pub fn replace(span: Option<Span>) -> Option<Span> {
+    REACHED.with(|reached| reached.replace(span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacing_returns_the_last_mark() {
+        at((3, 4));
+        assert_eq!(reached(), Some((3, 4)));
+        assert_eq!(replace(None), Some((3, 4)));
+        assert_eq!(reached(), None);
+    }
+}
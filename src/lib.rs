@@ -1,5 +1,14 @@
-// pub mod errors;
-// use errors::Error;
+pub use errors;
+pub use errors::Diagnostic;
+pub use fmt;
+pub use lex;
+pub use lint;
+pub use optimise;
+pub use parse;
+pub use pkg;
+pub use rt;
+pub use sema;
+pub use vm;
 
 #[cfg(test)]
 mod tests {
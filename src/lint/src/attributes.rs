@@ -0,0 +1,118 @@
+//! `#[allow(...)]`, `#[warn(...)]` and `#[deny(...)]` attributes, and the code they cover.
+//!
+//! An attribute covers the statement after it, skipping any other attributes in between. An inner
+//! attribute, `#![...]`, covers the block it's in, or the whole file at the top level.
+
+use lex::Span;
+use parse::{
+    expr::{Node, Spanned},
+    visit::{walk_children, Visitor},
+};
+
+use crate::{Level, Levels, Lint, Warning};
+
+/// a level set for some lints over a range of source
+struct Scoped {
+    range: Span,
+    lints: Vec<Lint>,
+    level: Level,
+}
+
+#[derive(Default)]
+pub(crate) struct Attributes {
+    scoped: Vec<Scoped>,
+}
+
+impl Attributes {
+    /// the level of `warning`, from `levels` unless attributes covering it say otherwise
+    pub(crate) fn level(&self, warning: &Warning, levels: &Levels) -> Level {
+        let lint = warning.lint();
+        let span = warning.span();
+
+        let mut covering: Vec<&Scoped> = self
+            .scoped
+            .iter()
+            .filter(|s| s.range.0 <= span.0 && span.1 <= s.range.1 && s.lints.contains(&lint))
+            .collect();
+        // outermost first, so the nearest attribute is applied last
+        covering.sort_by_key(|s| std::cmp::Reverse(s.range.1 - s.range.0));
+
+        covering
+            .last()
+            .map_or_else(|| levels.get(lint), |scoped| scoped.level)
+    }
+}
+
+/// every lint attribute in `program`, and the lint names in them that aren't lints
+pub(crate) fn collect(program: &[Spanned<Node>]) -> (Attributes, Vec<Warning>) {
+    let mut collector = Collector::default();
+    collector.statements(program, None, (0, usize::MAX));
+    for node in program {
+        collector.visit_node(node);
+    }
+
+    (collector.attributes, collector.unknown)
+}
+
+#[derive(Default)]
+struct Collector {
+    attributes: Attributes,
+    unknown: Vec<Warning>,
+}
+
+impl Collector {
+    /// reads the attributes in a list of statements, where `range` is the block or file
+    fn statements(&mut self, body: &[Spanned<Node>], value: Option<&Spanned<Node>>, range: Span) {
+        let nodes: Vec<&Spanned<Node>> = body.iter().chain(value).collect();
+
+        for (idx, node) in nodes.iter().enumerate() {
+            if let Node::Attribute { inner, name, args } = &node.node {
+                let level = match Level::parse(&name.node) {
+                    Some(level) => level,
+                    None => continue,
+                };
+
+                let range = if *inner {
+                    range
+                } else {
+                    let next = nodes[idx + 1..]
+                        .iter()
+                        .find(|n| !matches!(n.node, Node::Attribute { .. }));
+                    match next {
+                        Some(next) => next.span,
+                        None => continue,
+                    }
+                };
+
+                let mut lints = Vec::new();
+                for arg in args {
+                    if arg.node == "all" {
+                        lints.extend_from_slice(Lint::ALL);
+                    } else if let Some(lint) = Lint::from_name(&arg.node) {
+                        lints.push(lint);
+                    } else {
+                        self.unknown.push(Warning::UnknownLint {
+                            name: arg.node.clone(),
+                            span: arg.span,
+                        });
+                    }
+                }
+
+                self.attributes.scoped.push(Scoped {
+                    range,
+                    lints,
+                    level,
+                });
+            }
+        }
+    }
+}
+
+impl Visitor for Collector {
+    fn visit_block(&mut self, node: &Spanned<Node>) {
+        if let Node::Block { body, value } = &node.node {
+            self.statements(body, value.as_deref(), node.span);
+        }
+        walk_children(self, node);
+    }
+}
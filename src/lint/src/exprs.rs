@@ -0,0 +1,156 @@
+//! Lints that look at a single node: unreachable code, constant conditions and loose equality.
+
+use lex::trace;
+use parse::{
+    expr::{Node, Op, Spanned},
+    visit::{walk_children, Visitor},
+};
+
+use crate::Warning;
+
+pub(crate) fn check(program: &[Spanned<Node>]) -> Vec<Warning> {
+    let mut checker = Exprs::default();
+    checker.statements(program, None);
+    for node in program {
+        trace::at(node.span);
+        checker.visit_node(node);
+    }
+
+    checker.warnings
+}
+
+#[derive(Default)]
+struct Exprs {
+    warnings: Vec<Warning>,
+}
+
+fn is_literal(node: &Spanned<Node>) -> bool {
+    matches!(
+        node.node,
+        Node::Number(_) | Node::String(_) | Node::Boolean(_)
+    )
+}
+
+impl Exprs {
+    /// reports everything after a `return` in a list of statements
+    fn statements(&mut self, body: &[Spanned<Node>], value: Option<&Spanned<Node>>) {
+        let returned = body
+            .iter()
+            .position(|statement| matches!(statement.node, Node::Return(_)));
+
+        if let Some(idx) = returned {
+            let rest: Vec<&Spanned<Node>> = body[idx + 1..]
+                .iter()
+                .chain(value)
+                .filter(|n| !matches!(n.node, Node::Attribute { .. }))
+                .collect();
+
+            if let (Some(first), Some(last)) = (rest.first(), rest.last()) {
+                self.warnings.push(Warning::UnreachableCode {
+                    span: (first.span.0, last.span.1),
+                    after: body[idx].span,
+                });
+            }
+        }
+    }
+}
+
+impl Visitor for Exprs {
+    fn visit_block(&mut self, node: &Spanned<Node>) {
+        if let Node::Block { body, value } = &node.node {
+            self.statements(body, value.as_deref());
+        }
+        walk_children(self, node);
+    }
+
+    fn visit_binary_expr(&mut self, node: &Spanned<Node>) {
+        if let Node::BinaryExpr {
+            operation,
+            lhs,
+            rhs,
+        } = &node.node
+        {
+            let constant = match operation {
+                Op::And | Op::Or => {
+                    matches!(lhs.node, Node::Boolean(_)) || matches!(rhs.node, Node::Boolean(_))
+                }
+                Op::Add | Op::Subtract | Op::Multiply | Op::Divide => false,
+                _ => is_literal(lhs) && is_literal(rhs),
+            };
+            let converts = |n: &Spanned<Node>| matches!(n.node, Node::String(_) | Node::Boolean(_));
+
+            if constant {
+                self.warnings
+                    .push(Warning::ConstantCondition { span: node.span });
+            } else if matches!(operation, Op::Equal | Op::NotEqual)
+                && (converts(lhs) || converts(rhs))
+            {
+                self.warnings.push(Warning::LooseEquality {
+                    span: node.span,
+                    operator: (lhs.span.1, rhs.span.0),
+                    strict: if *operation == Op::Equal {
+                        "==="
+                    } else {
+                        "!=="
+                    },
+                });
+            }
+        }
+
+        walk_children(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::lint_source, Levels, Warning};
+
+    fn warnings(src: &str) -> Vec<Warning> {
+        lint_source(src, &Levels::default())
+            .into_iter()
+            .map(|f| f.warning)
+            .collect()
+    }
+
+    #[test]
+    fn code_after_return_is_unreachable() {
+        assert_eq!(
+            warnings("fn f(x) {\n    return x;\n    print(x);\n    x\n}\nf(1);"),
+            vec![Warning::UnreachableCode {
+                span: (28, 43),
+                after: (14, 22),
+            }]
+        );
+        assert!(warnings("fn f(x) { print(x); return x; }\nf(1);").is_empty());
+    }
+
+    #[test]
+    fn literal_conditions_are_constant() {
+        assert_eq!(
+            warnings("let x = 1;\nprint(x > 0 and true, 1 < 2, x < 2);"),
+            vec![
+                Warning::ConstantCondition { span: (17, 31) },
+                Warning::ConstantCondition { span: (33, 38) },
+            ]
+        );
+    }
+
+    #[test]
+    fn comparing_to_strings_and_booleans_is_loose() {
+        assert_eq!(
+            warnings("let x = 1;\nprint(x == \"1\", x != true, x == 1, x === \"1\");"),
+            vec![
+                Warning::LooseEquality {
+                    span: (17, 25),
+                    operator: (18, 22),
+                    strict: "===",
+                },
+                Warning::LooseEquality {
+                    span: (27, 36),
+                    operator: (28, 32),
+                    strict: "!==",
+                },
+            ]
+        );
+    }
+}
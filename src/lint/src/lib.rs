@@ -0,0 +1,304 @@
+//! Lints: warnings about code that's valid but probably not what was meant.
+//!
+//! Every lint has a name and a level, which decides whether it's ignored, reported as a warning or
+//! reported as an error. Levels start at each lint's default, then are set in turn by the package
+//! manifest, the command line, and `#[allow(...)]`, `#[warn(...)]` or `#[deny(...)]` attributes in
+//! source, where the attribute nearest the problem wins.
+
+use std::{collections::HashMap, fmt};
+
+use lex::Span;
+use parse::expr::{Node, Spanned};
+
+mod attributes;
+mod exprs;
+mod scopes;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedImport,
+    UnreachableCode,
+    ShadowedBinding,
+    ConstantCondition,
+    LooseEquality,
+    /// a lint attribute naming no lint
+    UnknownLint,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[
+        Lint::UnusedVariable,
+        Lint::UnusedImport,
+        Lint::UnreachableCode,
+        Lint::ShadowedBinding,
+        Lint::ConstantCondition,
+        Lint::LooseEquality,
+        Lint::UnknownLint,
+    ];
+
+    /// the name used to set the lint's level, like `unused_variable`
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnusedImport => "unused_import",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::ShadowedBinding => "shadowed_binding",
+            Lint::ConstantCondition => "constant_condition",
+            Lint::LooseEquality => "loose_equality",
+            Lint::UnknownLint => "unknown_lint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+
+    /// shadowing is common and usually deliberate, so it's only reported when asked for
+    pub fn default_level(self) -> Level {
+        match self {
+            Lint::ShadowedBinding => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    /// `allow`, `warn` or `deny`
+    pub fn parse(level: &str) -> Option<Level> {
+        match level {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warn"),
+            Level::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// the level of every lint, before attributes in source are applied
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Levels {
+    levels: HashMap<Lint, Level>,
+}
+
+impl Levels {
+    pub fn get(&self, lint: Lint) -> Level {
+        self.levels
+            .get(&lint)
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.levels.insert(lint, level);
+    }
+
+    /// sets the lint called `name`, or every lint for `all`, returning false for an unknown name
+    pub fn set_named(&mut self, name: &str, level: Level) -> bool {
+        if name == "all" {
+            Lint::ALL.iter().for_each(|lint| self.set(*lint, level));
+            true
+        } else if let Some(lint) = Lint::from_name(name) {
+            self.set(lint, level);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// what a lint found
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// a `let`, `const` or parameter binding that's never read
+    UnusedVariable { name: String, span: Span },
+    /// a name brought in by an import that's never read
+    UnusedImport { name: String, span: Span },
+    /// statements after a `return` in the same block
+    UnreachableCode { span: Span, after: Span },
+    /// a binding hiding an earlier one of the same name
+    ShadowedBinding {
+        name: String,
+        span: Span,
+        previous: Span,
+    },
+    /// a comparison or `and`/`or` whose outcome is fixed by its literal operands
+    ConstantCondition { span: Span },
+    /// `==` or `!=` against a string or boolean, which convert to numbers when compared; `operator`
+    /// covers the operator and the space around it
+    LooseEquality {
+        span: Span,
+        operator: Span,
+        strict: &'static str,
+    },
+    /// a lint attribute naming no lint
+    UnknownLint { name: String, span: Span },
+}
+
+impl Warning {
+    pub fn lint(&self) -> Lint {
+        match self {
+            Warning::UnusedVariable { .. } => Lint::UnusedVariable,
+            Warning::UnusedImport { .. } => Lint::UnusedImport,
+            Warning::UnreachableCode { .. } => Lint::UnreachableCode,
+            Warning::ShadowedBinding { .. } => Lint::ShadowedBinding,
+            Warning::ConstantCondition { .. } => Lint::ConstantCondition,
+            Warning::LooseEquality { .. } => Lint::LooseEquality,
+            Warning::UnknownLint { .. } => Lint::UnknownLint,
+        }
+    }
+
+    /// where the problem is
+    pub fn span(&self) -> Span {
+        match self {
+            Warning::UnusedVariable { span, .. }
+            | Warning::UnusedImport { span, .. }
+            | Warning::UnreachableCode { span, .. }
+            | Warning::ShadowedBinding { span, .. }
+            | Warning::ConstantCondition { span }
+            | Warning::LooseEquality { span, .. }
+            | Warning::UnknownLint { span, .. } => *span,
+        }
+    }
+}
+
+/// a warning at the level it's reported at, which is never `Allow`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub level: Level,
+    pub warning: Warning,
+}
+
+/// every lint found in `program` at a level other than allow, in source order
+pub fn lint(program: &[Spanned<Node>], levels: &Levels) -> Vec<Finding> {
+    let mut warnings = scopes::check(program);
+    warnings.extend(exprs::check(program));
+
+    let (attributes, unknown) = attributes::collect(program);
+    warnings.extend(unknown);
+
+    let mut findings: Vec<Finding> = warnings
+        .into_iter()
+        .map(|warning| Finding {
+            level: attributes.level(&warning, levels),
+            warning,
+        })
+        .filter(|finding| finding.level != Level::Allow)
+        .collect();
+    findings.sort_by_key(|finding| finding.warning.span());
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+
+    use super::*;
+
+    pub(crate) fn lint_source(src: &str, levels: &Levels) -> Vec<Finding> {
+        lint(&parse::parse(tokenise(src).unwrap()).unwrap(), levels)
+    }
+
+    fn names(findings: &[Finding]) -> Vec<(&'static str, Level)> {
+        findings
+            .iter()
+            .map(|f| (f.warning.lint().name(), f.level))
+            .collect()
+    }
+
+    #[test]
+    fn levels_default_then_can_be_set() {
+        let mut levels = Levels::default();
+        assert_eq!(levels.get(Lint::UnusedVariable), Level::Warn);
+        assert_eq!(levels.get(Lint::ShadowedBinding), Level::Allow);
+
+        assert!(levels.set_named("shadowed_binding", Level::Deny));
+        assert!(!levels.set_named("shadowed", Level::Deny));
+        assert_eq!(levels.get(Lint::ShadowedBinding), Level::Deny);
+
+        levels.set_named("all", Level::Allow);
+        assert!(Lint::ALL.iter().all(|l| levels.get(*l) == Level::Allow));
+    }
+
+    #[test]
+    fn allowed_lints_are_left_out() {
+        let src = "let x = 1;\nlet x = 2;\nprint(x);";
+        assert_eq!(
+            names(&lint_source(src, &Levels::default())),
+            vec![("unused_variable", Level::Warn)]
+        );
+
+        let mut levels = Levels::default();
+        levels.set(Lint::UnusedVariable, Level::Allow);
+        levels.set(Lint::ShadowedBinding, Level::Deny);
+        assert_eq!(
+            names(&lint_source(src, &levels)),
+            vec![("shadowed_binding", Level::Deny)]
+        );
+    }
+
+    #[test]
+    fn attributes_override_levels_nearest_first() {
+        let src = "#![deny(unused_variable)]\nlet a = 1;\n#[allow(unused_variable)]\nlet b = 2;\nfn f() {\n    #![warn(unused_variable)]\n    let c = 3;\n}\nf();";
+        let findings = lint_source(src, &Levels::default());
+
+        assert_eq!(
+            findings
+                .iter()
+                .map(|f| (&f.warning, f.level))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    &Warning::UnusedVariable {
+                        name: String::from("a"),
+                        span: (30, 31),
+                    },
+                    Level::Deny
+                ),
+                (
+                    &Warning::UnusedVariable {
+                        name: String::from("c"),
+                        span: (121, 122),
+                    },
+                    Level::Warn
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_lints_in_attributes_are_reported() {
+        let findings = lint_source("#[allow(unused_varible)]\nprint(1);", &Levels::default());
+        assert_eq!(
+            findings[0].warning,
+            Warning::UnknownLint {
+                name: String::from("unused_varible"),
+                span: (8, 22),
+            }
+        );
+    }
+}
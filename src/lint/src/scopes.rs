@@ -0,0 +1,327 @@
+//! Lints that follow names through scopes: unused variables, unused imports and shadowing.
+//!
+//! A name read before any binding of it is in scope, as a function may do with a global declared
+//! after it, is matched against the bindings of each enclosing scope as that scope ends.
+
+use lex::{trace, Span};
+use parse::{
+    expr::{Node, Param, Spanned},
+    visit::{walk_children, Visitor},
+};
+
+use crate::Warning;
+
+pub(crate) fn check(program: &[Spanned<Node>]) -> Vec<Warning> {
+    let mut scopes = Scopes {
+        scopes: vec![Scope::default()],
+        warnings: Vec::new(),
+    };
+    for node in program {
+        trace::at(node.span);
+        scopes.visit_node(node);
+    }
+    scopes.pop();
+
+    scopes.warnings
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Variable,
+    Import,
+    /// a function or struct, which isn't reported when unused
+    Item,
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+    kind: Kind,
+    used: bool,
+    /// a `pub` binding at the top level, used by other modules
+    exported: bool,
+}
+
+#[derive(Default)]
+struct Scope {
+    bindings: Vec<Binding>,
+    /// names read in this scope or an inner one with no binding in scope at the time
+    unresolved: Vec<String>,
+}
+
+struct Scopes {
+    /// the innermost scope last
+    scopes: Vec<Scope>,
+    warnings: Vec<Warning>,
+}
+
+impl Scopes {
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.iter_mut().rev().find(|b| b.name == name))
+    }
+
+    fn declare(&mut self, name: &str, span: Span, kind: Kind, exported: bool) {
+        if let Some(previous) = self.lookup(name) {
+            let previous = previous.span;
+            self.warnings.push(Warning::ShadowedBinding {
+                name: name.to_string(),
+                span,
+                previous,
+            });
+        }
+
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never left");
+        scope.bindings.push(Binding {
+            name: name.to_string(),
+            span,
+            kind,
+            used: false,
+            exported,
+        });
+    }
+
+    fn read(&mut self, name: &str) {
+        match self.lookup(name) {
+            Some(binding) => binding.used = true,
+            None => {
+                let scope = self
+                    .scopes
+                    .last_mut()
+                    .expect("the global scope is never left");
+                scope.unresolved.push(name.to_string());
+            }
+        }
+    }
+
+    fn push(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    /// ends the innermost scope, reporting what in it was never read
+    fn pop(&mut self) {
+        let mut scope = self.scopes.pop().expect("scopes are balanced");
+
+        for name in scope.unresolved {
+            match scope.bindings.iter_mut().find(|b| b.name == name) {
+                Some(binding) => binding.used = true,
+                None => {
+                    if let Some(parent) = self.scopes.last_mut() {
+                        parent.unresolved.push(name);
+                    }
+                }
+            }
+        }
+
+        for binding in scope.bindings {
+            if binding.used || binding.exported || binding.name.starts_with('_') {
+                continue;
+            }
+
+            let Binding { name, span, .. } = binding;
+            match binding.kind {
+                Kind::Variable => self.warnings.push(Warning::UnusedVariable { name, span }),
+                Kind::Import => self.warnings.push(Warning::UnusedImport { name, span }),
+                Kind::Item => {}
+            }
+        }
+    }
+
+    /// a function body in a new scope holding its parameters
+    fn function(&mut self, params: &[Param], body: &Spanned<Node>) {
+        self.push();
+        for param in params {
+            for (name, span) in param.pattern.bindings() {
+                self.declare(name, span, Kind::Variable, false);
+            }
+        }
+        self.visit_node(body);
+        self.pop();
+    }
+}
+
+impl Visitor for Scopes {
+    fn visit_variable(&mut self, node: &Spanned<Node>) {
+        if let Node::Variable {
+            public,
+            pattern,
+            value,
+            ..
+        } = &node.node
+        {
+            // the value can't see the names it's about to be bound to
+            self.visit_node(value);
+            let exported = *public && self.scopes.len() == 1;
+            for (name, span) in pattern.bindings() {
+                self.declare(name, span, Kind::Variable, exported);
+            }
+        }
+    }
+
+    fn visit_fn(&mut self, node: &Spanned<Node>) {
+        if let Node::Fn {
+            name, args, body, ..
+        } = &node.node
+        {
+            if let Node::Identifier(name) = &name.node {
+                self.declare(name, node.span, Kind::Item, false);
+            }
+            self.function(args, body);
+        }
+    }
+
+    fn visit_closure(&mut self, node: &Spanned<Node>) {
+        if let Node::Closure { args, body, .. } = &node.node {
+            self.function(args, body);
+        }
+    }
+
+    fn visit_struct(&mut self, node: &Spanned<Node>) {
+        // field names aren't reads, so the children aren't walked
+        if let Node::Struct { name, .. } = &node.node {
+            if let Node::Identifier(name) = &name.node {
+                self.declare(name, node.span, Kind::Item, false);
+            }
+        }
+    }
+
+    fn visit_enum(&mut self, node: &Spanned<Node>) {
+        // nor are variant names, and field types are never variables
+        if let Node::Enum { name, .. } = &node.node {
+            if let Node::Identifier(name) = &name.node {
+                self.declare(name, node.span, Kind::Item, false);
+            }
+        }
+    }
+
+    fn visit_match(&mut self, node: &Spanned<Node>) {
+        if let Node::Match { value, arms } = &node.node {
+            self.visit_node(value);
+            // each arm's bindings are only in scope for its value
+            for arm in arms {
+                self.push();
+                for (name, span) in arm.pattern.bindings() {
+                    self.declare(name, span, Kind::Variable, false);
+                }
+                self.visit_node(&arm.value);
+                self.pop();
+            }
+        }
+    }
+
+    fn visit_import(&mut self, node: &Spanned<Node>) {
+        if let Node::Import { alias, items, .. } = &node.node {
+            for imported in alias.as_deref().into_iter().chain(items) {
+                if let Node::Identifier(name) = &imported.node {
+                    self.declare(name, imported.span, Kind::Import, false);
+                }
+            }
+        }
+    }
+
+    fn visit_block(&mut self, node: &Spanned<Node>) {
+        self.push();
+        walk_children(self, node);
+        self.pop();
+    }
+
+    fn visit_assign(&mut self, node: &Spanned<Node>) {
+        match &node.node {
+            // assigning to a name without reading it first doesn't use it
+            Node::Assign {
+                operation: None,
+                target,
+                value,
+            } if matches!(target.node, Node::Identifier(_)) => self.visit_node(value),
+            _ => walk_children(self, node),
+        }
+    }
+
+    fn visit_identifier(&mut self, node: &Spanned<Node>) {
+        if let Node::Identifier(name) = &node.node {
+            self.read(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::lint_source, Level, Levels, Lint, Warning};
+
+    fn warnings(src: &str) -> Vec<Warning> {
+        let mut levels = Levels::default();
+        levels.set(Lint::ShadowedBinding, Level::Warn);
+        lint_source(src, &levels)
+            .into_iter()
+            .map(|f| f.warning)
+            .collect()
+    }
+
+    fn unused(name: &str, span: (usize, usize)) -> Warning {
+        Warning::UnusedVariable {
+            name: name.to_string(),
+            span,
+        }
+    }
+
+    #[test]
+    fn reports_unread_bindings() {
+        assert_eq!(
+            warnings("let (a, b) = (1, 2);\nfn f(x, _y) { let z = 1; z = 2; }\nf(a);"),
+            vec![
+                unused("b", (8, 9)),
+                unused("x", (26, 27)),
+                unused("z", (39, 40)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_can_come_before_the_binding() {
+        assert!(warnings("fn f() { total }\nlet total = 1;\nf();").is_empty());
+        assert!(warnings("let n = 1;\nn += 1;\npub let exported = 2;").is_empty());
+    }
+
+    #[test]
+    fn reports_unused_imports() {
+        assert_eq!(
+            warnings("import std.io { read, write };\nimport std.math as m;\nread();"),
+            vec![
+                Warning::UnusedImport {
+                    name: String::from("write"),
+                    span: (22, 27),
+                },
+                Warning::UnusedImport {
+                    name: String::from("m"),
+                    span: (50, 51),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_shadowing() {
+        assert_eq!(
+            warnings("let x = 1;\nfn f(x) { x }\nf(x);"),
+            vec![Warning::ShadowedBinding {
+                name: String::from("x"),
+                span: (16, 17),
+                previous: (4, 5),
+            }]
+        );
+    }
+
+    #[test]
+    fn arm_bindings_end_with_their_arm() {
+        assert_eq!(
+            warnings("let s = 1;\nmatch s { n => 1, m => m, };"),
+            vec![unused("n", (21, 22))]
+        );
+        assert!(warnings("enum E { A }\nlet e = E::A;\nmatch e { E::A => e, };").is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+//! Branch folding, replacing a `match` on a literal with the value of the arm it always takes.
+//!
+//! Arms are tried in order the way the interpreter tries them. A literal pattern of another kind,
+//! or one equal to a different literal, can never match and is passed over. The first wildcard or
+//! equal literal is the arm taken. An arm whose pattern binds names or takes a value apart stops
+//! the search, as does running out of arms, so the `match` is kept for the interpreter to run.
+
+use std::mem;
+
+use lex::trace;
+use parse::{
+    expr::{Arm, Node, Pattern, Spanned},
+    fold::{fold_children, Fold},
+};
+
+use crate::pass::Pass;
+
+/// the `match-fold` pass
+pub struct BranchFolding;
+
+impl Pass for BranchFolding {
+    fn name(&self) -> &'static str {
+        "match-fold"
+    }
+
+    fn run(&mut self, program: &mut Vec<Spanned<Node>>) -> bool {
+        let mut pruner = Pruner { changed: false };
+        *program = mem::take(program)
+            .into_iter()
+            .map(|node| {
+                trace::at(node.span);
+                pruner.fold_node(node)
+            })
+            .collect();
+
+        pruner.changed
+    }
+}
+
+struct Pruner {
+    changed: bool,
+}
+
+impl Fold for Pruner {
+    fn fold_match(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        let node = fold_children(self, node);
+        let (value, mut arms) = match node.node {
+            Node::Match { value, arms } => (value, arms),
+            other => return Spanned::new(other, node.span),
+        };
+
+        match taken(&value.node, &arms) {
+            Some(idx) => {
+                self.changed = true;
+                arms.swap_remove(idx).value
+            }
+            None => Spanned::new(Node::Match { value, arms }, node.span),
+        }
+    }
+}
+
+/// the index of the arm a match on `value` takes, if that's known before the program runs
+fn taken(value: &Node, arms: &[Arm]) -> Option<usize> {
+    for (idx, arm) in arms.iter().enumerate() {
+        let matches = match (&arm.pattern.node, value) {
+            (Pattern::Wildcard, Node::Number(_) | Node::String(_) | Node::Boolean(_)) => true,
+            (Pattern::Number(n), Node::Number(value)) => n == value,
+            (Pattern::String(s), Node::String(value)) => s == value,
+            (Pattern::Boolean(b), Node::Boolean(value)) => b == value,
+            (Pattern::Number(_) | Pattern::String(_) | Pattern::Boolean(_), _) => false,
+            _ => return None,
+        };
+
+        if matches {
+            return Some(idx);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use parse::{dump::to_sexp, parse};
+
+    use super::*;
+
+    fn folded(src: &str) -> (String, bool) {
+        let mut ast = parse(tokenise(src).unwrap()).unwrap();
+        let changed = BranchFolding.run(&mut ast);
+        (to_sexp(&ast), changed)
+    }
+
+    #[test]
+    fn takes_the_arm_a_literal_matches() {
+        assert_eq!(
+            folded("let a = match 2 { \"2\" => 0, 1 => 1, 2 => 2, _ => 3, };"),
+            (
+                String::from(
+                    "(variable let 0..53
+  (identifier a 4..5)
+  (number 2 41..42))
+"
+                ),
+                true
+            )
+        );
+        assert_eq!(
+            folded("let a = match true { false => 0, _ => 1, };").0,
+            "(variable let 0..42
+  (identifier a 4..5)
+  (number 1 38..39))
+"
+        );
+    }
+
+    #[test]
+    fn keeps_matches_it_cant_decide() {
+        for src in [
+            "let a = match x { 1 => 0, _ => 1, };",
+            "let a = match 2 { n => n, };",
+            "let a = match 2 { 1 => 0, };",
+        ] {
+            assert!(!folded(src).1, "{}", src);
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! Constant folding, evaluating expressions whose operands are all literals before the program runs.
+//!
+//! An expression is only folded when evaluating it can't fail, so mistakes like `1 + "a"` are left
+//! for the interpreter to report. Numbers are folded with the same `f64` operations the
+//! interpreter uses, so NaN, infinities and negative zero come out exactly as they would when the
+//! program runs. A `const` binding whose value folds to a literal is substituted into every read
+//! of it after its declaration.
+
+use std::collections::HashMap;
+
+use lex::{trace, Span, TokenType};
+use parse::{
+    expr::{Node, Op, Pattern, Spanned, Var},
+    fold::{fold_children, Fold},
+};
+use rt::Value;
+use sema::{DeclId, DeclKind, Resolution};
+
+use crate::pass::Pass;
+
+/// the `const-fold` pass
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "const-fold"
+    }
+
+    fn run(&mut self, program: &mut Vec<Spanned<Node>>) -> bool {
+        let (folded, changed) = fold(std::mem::take(program));
+        *program = folded;
+        changed
+    }
+}
+
+/// `program` with every constant expression replaced by its value
+pub fn fold_constants(program: Vec<Spanned<Node>>) -> Vec<Spanned<Node>> {
+    fold(program).0
+}
+
+/// the folded program, and whether anything was folded
+fn fold(program: Vec<Spanned<Node>>) -> (Vec<Spanned<Node>>, bool) {
+    let resolution = sema::resolve(&program);
+    let mut folder = ConstantFolder {
+        resolution: &resolution,
+        constants: HashMap::new(),
+        changed: false,
+    };
+
+    let program = program
+        .into_iter()
+        .map(|node| {
+            trace::at(node.span);
+            folder.fold_node(node)
+        })
+        .collect();
+    (program, folder.changed)
+}
+
+struct ConstantFolder<'a> {
+    resolution: &'a Resolution,
+    /// the literal each `const` declared so far folded to
+    constants: HashMap<DeclId, Node>,
+    changed: bool,
+}
+
+impl ConstantFolder<'_> {
+    /// the `const` declared by the identifier pattern at `span`
+    fn constant(&self, span: Span) -> Option<DeclId> {
+        self.resolution
+            .declarations
+            .iter()
+            .position(|decl| decl.kind == DeclKind::Const && decl.span == span)
+            .map(DeclId)
+    }
+}
+
+impl Fold for ConstantFolder<'_> {
+    fn fold_variable(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        let node = fold_children(self, node);
+        if let Node::Variable {
+            variable_type: Var::Const,
+            pattern,
+            value,
+            ..
+        } = &node.node
+        {
+            if let (Pattern::Identifier(_), true) = (&pattern.node, literal(&value.node)) {
+                if let Some(id) = self.constant(pattern.span) {
+                    self.constants.insert(id, value.node.clone());
+                }
+            }
+        }
+
+        node
+    }
+
+    fn fold_binary_expr(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        let node = fold_children(self, node);
+        let folded = match &node.node {
+            Node::BinaryExpr {
+                operation,
+                lhs,
+                rhs,
+            } => binary(operation, &lhs.node, &rhs.node),
+            _ => None,
+        };
+
+        match folded {
+            Some(value) => {
+                self.changed = true;
+                Spanned::new(value, node.span)
+            }
+            None => node,
+        }
+    }
+
+    fn fold_unary_expr(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        let node = fold_children(self, node);
+        let folded = match &node.node {
+            Node::UnaryExpr { operation, body } => match (operation, &body.node) {
+                (TokenType::Minus, Node::Number(n)) => Some(Node::Number(-n)),
+                (TokenType::Not, Node::Boolean(b)) => Some(Node::Boolean(!b)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match folded {
+            Some(value) => {
+                self.changed = true;
+                Spanned::new(value, node.span)
+            }
+            None => node,
+        }
+    }
+
+    /// a variable being assigned to stays a name, even if it's a constant
+    fn fold_assign(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        match node.node {
+            Node::Assign {
+                operation,
+                target,
+                value,
+            } if matches!(target.node, Node::Identifier(_)) => Spanned::new(
+                Node::Assign {
+                    operation,
+                    target,
+                    value: Box::new(self.fold_node(*value)),
+                },
+                node.span,
+            ),
+            _ => fold_children(self, node),
+        }
+    }
+
+    fn fold_identifier(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        let constant = self
+            .resolution
+            .uses
+            .get(&node.span)
+            .and_then(|id| self.constants.get(id))
+            .cloned();
+
+        match constant {
+            Some(value) => {
+                self.changed = true;
+                Spanned::new(value, node.span)
+            }
+            None => node,
+        }
+    }
+}
+
+fn literal(node: &Node) -> bool {
+    matches!(node, Node::Number(_) | Node::String(_) | Node::Boolean(_))
+}
+
+/// the value a literal evaluates to
+fn value(node: &Node) -> Option<Value> {
+    match node {
+        Node::Number(n) => Some(Value::Number(*n)),
+        Node::String(s) => Some(Value::String(s.clone())),
+        Node::Boolean(b) => Some(Value::Boolean(*b)),
+        _ => None,
+    }
+}
+
+/// the literal `lhs operation rhs` evaluates to, if it can be known without running the program
+fn binary(operation: &Op, lhs: &Node, rhs: &Node) -> Option<Node> {
+    let folded = match (operation, lhs, rhs) {
+        // the right operand isn't evaluated once the left one decides the result
+        (Op::And, Node::Boolean(false), _) => Node::Boolean(false),
+        (Op::Or, Node::Boolean(true), _) => Node::Boolean(true),
+        (Op::And | Op::Or, Node::Boolean(_), Node::Boolean(rhs)) => Node::Boolean(*rhs),
+        (Op::And | Op::Or, ..) => return None,
+        (Op::Equal | Op::NotEqual | Op::StrictEqual | Op::NotStrictEqual, lhs, rhs) => {
+            let (lhs, rhs) = (value(lhs)?, value(rhs)?);
+            let equal = match operation {
+                Op::Equal | Op::NotEqual => lhs.loose_eq(&rhs),
+                _ => lhs.strict_eq(&rhs),
+            };
+            Node::Boolean(equal == matches!(operation, Op::Equal | Op::StrictEqual))
+        }
+        (Op::Add, Node::String(lhs), Node::String(rhs)) => Node::String(format!("{}{}", lhs, rhs)),
+        (Op::Less, Node::String(lhs), Node::String(rhs)) => Node::Boolean(lhs < rhs),
+        (Op::LessEqual, Node::String(lhs), Node::String(rhs)) => Node::Boolean(lhs <= rhs),
+        (Op::Greater, Node::String(lhs), Node::String(rhs)) => Node::Boolean(lhs > rhs),
+        (Op::GreaterEqual, Node::String(lhs), Node::String(rhs)) => Node::Boolean(lhs >= rhs),
+        (operation, Node::Number(lhs), Node::Number(rhs)) => match operation {
+            Op::Add => Node::Number(lhs + rhs),
+            Op::Subtract => Node::Number(lhs - rhs),
+            Op::Multiply => Node::Number(lhs * rhs),
+            Op::Divide => Node::Number(lhs / rhs),
+            Op::Less => Node::Boolean(lhs < rhs),
+            Op::LessEqual => Node::Boolean(lhs <= rhs),
+            Op::Greater => Node::Boolean(lhs > rhs),
+            Op::GreaterEqual => Node::Boolean(lhs >= rhs),
+            _ => unreachable!("equality and logical operators are handled above"),
+        },
+        _ => return None,
+    };
+
+    Some(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use parse::{dump::to_sexp, parse};
+
+    use super::*;
+
+    /// checks the tree of `src` is `before`, and that it's `after` once folded
+    fn golden(src: &str, before: &str, after: &str) {
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+        assert_eq!(to_sexp(&ast), before);
+        assert_eq!(to_sexp(&fold_constants(ast)), after);
+    }
+
+    #[test]
+    fn folds_arithmetic() {
+        golden(
+            "let a = 1 + 2 * 3 - -4;",
+            "(variable let 0..22
+  (identifier a 4..5)
+  (binary-expr subtract 8..22
+    (binary-expr add 8..17
+      (number 1 8..9)
+      (binary-expr multiply 12..17
+        (number 2 12..13)
+        (number 3 16..17)))
+    (unary-expr minus 20..22
+      (number 4 21..22))))
+",
+            "(variable let 0..22
+  (identifier a 4..5)
+  (number 11 8..22))
+",
+        );
+    }
+
+    #[test]
+    fn keeps_float_semantics() {
+        golden(
+            "let nan = 0 / 0; let inf = -1 / 0; let z = 0 * -1; let same = 0 / 0 == 0 / 0;",
+            "(variable let 0..15
+  (identifier nan 4..7)
+  (binary-expr divide 10..15
+    (number 0 10..11)
+    (number 0 14..15)))
+(variable let 17..33
+  (identifier inf 21..24)
+  (binary-expr divide 27..33
+    (unary-expr minus 27..29
+      (number 1 28..29))
+    (number 0 32..33)))
+(variable let 35..49
+  (identifier z 39..40)
+  (binary-expr multiply 43..49
+    (number 0 43..44)
+    (unary-expr minus 47..49
+      (number 1 48..49))))
+(variable let 51..76
+  (identifier same 55..59)
+  (binary-expr equal 62..76
+    (binary-expr divide 62..67
+      (number 0 62..63)
+      (number 0 66..67))
+    (binary-expr divide 71..76
+      (number 0 71..72)
+      (number 0 75..76))))
+",
+            "(variable let 0..15
+  (identifier nan 4..7)
+  (number NaN 10..15))
+(variable let 17..33
+  (identifier inf 21..24)
+  (number -inf 27..33))
+(variable let 35..49
+  (identifier z 39..40)
+  (number -0 43..49))
+(variable let 51..76
+  (identifier same 55..59)
+  (boolean false 62..76))
+",
+        );
+    }
+
+    #[test]
+    fn folds_strings_and_comparisons() {
+        golden(
+            "let s = \"con\" + \"cat\"; let b = \"a\" < \"b\"; let e = \"1\" == 1;",
+            "(variable let 0..21
+  (identifier s 4..5)
+  (binary-expr add 8..21
+    (string \"con\" 8..13)
+    (string \"cat\" 16..21)))
+(variable let 23..40
+  (identifier b 27..28)
+  (binary-expr less 31..40
+    (string \"a\" 31..34)
+    (string \"b\" 37..40)))
+(variable let 42..58
+  (identifier e 46..47)
+  (binary-expr equal 50..58
+    (string \"1\" 50..53)
+    (number 1 57..58)))
+",
+            "(variable let 0..21
+  (identifier s 4..5)
+  (string \"concat\" 8..21))
+(variable let 23..40
+  (identifier b 27..28)
+  (boolean true 31..40))
+(variable let 42..58
+  (identifier e 46..47)
+  (boolean true 50..58))
+",
+        );
+    }
+
+    #[test]
+    fn folds_boolean_logic() {
+        golden(
+            "let b = !true or false and f(); let c = true and x;",
+            "(variable let 0..30
+  (identifier b 4..5)
+  (binary-expr or 8..30
+    (unary-expr not 8..13
+      (boolean true 9..13))
+    (binary-expr and 17..30
+      (boolean false 17..22)
+      (fn-call 27..30
+        (identifier f 27..28)))))
+(variable let 32..50
+  (identifier c 36..37)
+  (binary-expr and 40..50
+    (boolean true 40..44)
+    (identifier x 49..50)))
+",
+            "(variable let 0..30
+  (identifier b 4..5)
+  (boolean false 8..30))
+(variable let 32..50
+  (identifier c 36..37)
+  (binary-expr and 40..50
+    (boolean true 40..44)
+    (identifier x 49..50)))
+",
+        );
+    }
+
+    #[test]
+    fn propagates_constants() {
+        golden(
+            "const k = 2 * 3; fn f(x) { x * k } fn g(k) { k }",
+            "(variable const 0..15
+  (identifier k 6..7)
+  (binary-expr multiply 10..15
+    (number 2 10..11)
+    (number 3 14..15)))
+(fn 17..34
+  (identifier f 20..21)
+  (identifier x 22..23)
+  (block 25..34
+    :value (binary-expr multiply 27..32
+      (identifier x 27..28)
+      (identifier k 31..32))))
+(fn 35..48
+  (identifier g 38..39)
+  (identifier k 40..41)
+  (block 43..48
+    :value (identifier k 45..46)))
+",
+            "(variable const 0..15
+  (identifier k 6..7)
+  (number 6 10..15))
+(fn 17..34
+  (identifier f 20..21)
+  (identifier x 22..23)
+  (block 25..34
+    :value (binary-expr multiply 27..32
+      (identifier x 27..28)
+      (number 6 31..32))))
+(fn 35..48
+  (identifier g 38..39)
+  (identifier k 40..41)
+  (block 43..48
+    :value (identifier k 45..46)))
+",
+        );
+    }
+
+    #[test]
+    fn leaves_errors_for_the_interpreter() {
+        golden(
+            "let e = 1 + \"a\"; let n = -true; let c = 1 and true;",
+            "(variable let 0..15
+  (identifier e 4..5)
+  (binary-expr add 8..15
+    (number 1 8..9)
+    (string \"a\" 12..15)))
+(variable let 17..30
+  (identifier n 21..22)
+  (unary-expr minus 25..30
+    (boolean true 26..30)))
+(variable let 32..50
+  (identifier c 36..37)
+  (binary-expr and 40..50
+    (number 1 40..41)
+    (boolean true 46..50)))
+",
+            "(variable let 0..15
+  (identifier e 4..5)
+  (binary-expr add 8..15
+    (number 1 8..9)
+    (string \"a\" 12..15)))
+(variable let 17..30
+  (identifier n 21..22)
+  (unary-expr minus 25..30
+    (boolean true 26..30)))
+(variable let 32..50
+  (identifier c 36..37)
+  (binary-expr and 40..50
+    (number 1 40..41)
+    (boolean true 46..50)))
+",
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Dead code removal, dropping statements from blocks that can never run or do nothing when they do.
+//!
+//! Everything after a `return` in a block is dropped, along with the block's value. Statements that
+//! are just a literal, like the `3;` left behind when `1 + 2;` is folded, are dropped too. Anything
+//! else may fail or have an effect when it runs, so it's kept.
+
+use std::mem;
+
+use lex::trace;
+use parse::{
+    expr::{Node, Spanned},
+    fold::{fold_children, Fold},
+};
+
+use crate::pass::Pass;
+
+/// the `dead-code` pass
+pub struct DeadCode;
+
+impl Pass for DeadCode {
+    fn name(&self) -> &'static str {
+        "dead-code"
+    }
+
+    fn run(&mut self, program: &mut Vec<Spanned<Node>>) -> bool {
+        let mut remover = Remover { changed: false };
+        *program = mem::take(program)
+            .into_iter()
+            .map(|node| {
+                trace::at(node.span);
+                remover.fold_node(node)
+            })
+            .collect();
+
+        remover.changed
+    }
+}
+
+struct Remover {
+    changed: bool,
+}
+
+impl Fold for Remover {
+    fn fold_block(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        let node = fold_children(self, node);
+        let (mut body, mut value) = match node.node {
+            Node::Block { body, value } => (body, value),
+            other => return Spanned::new(other, node.span),
+        };
+
+        let statements = body.len();
+        body.retain(|statement| {
+            !matches!(
+                statement.node,
+                Node::Number(_) | Node::String(_) | Node::Boolean(_)
+            )
+        });
+        if let Some(idx) = body.iter().position(|s| matches!(s.node, Node::Return(_))) {
+            body.truncate(idx + 1);
+            self.changed |= value.take().is_some();
+        }
+        self.changed |= body.len() != statements;
+
+        Spanned::new(Node::Block { body, value }, node.span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use parse::{dump::to_sexp, parse};
+
+    use super::*;
+
+    #[test]
+    fn drops_unreachable_and_unused_statements() {
+        let mut ast = parse(tokenise("fn f(x) { 1; g(x); return x; h(); x }").unwrap()).unwrap();
+
+        assert!(DeadCode.run(&mut ast));
+        assert_eq!(
+            to_sexp(&ast),
+            "(fn 0..37
+  (identifier f 3..4)
+  (identifier x 5..6)
+  (block 8..37
+    (fn-call 13..17
+      (identifier g 13..14)
+      (identifier x 15..16))
+    (return 19..27
+      (identifier x 26..27))))
+"
+        );
+        assert!(!DeadCode.run(&mut ast));
+    }
+}
@@ -1,7 +1,9 @@
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+//! Rewrites of a parsed program that keep what it does but do less work when it runs.
+
+pub mod branch;
+pub mod constant;
+pub mod dead;
+pub mod pass;
+
+pub use constant::fold_constants;
+pub use pass::{pass, OptLevel, Pass, PassManager, Timing, LIST_ITERATIONS, PASSES};
@@ -0,0 +1,260 @@
+//! Running optimisation passes over a program in order, until none of them changes anything.
+
+use std::time::{Duration, Instant};
+
+use parse::expr::{Node, Spanned};
+
+use crate::{branch::BranchFolding, constant::ConstantFolding, dead::DeadCode};
+
+/// a rewrite of the whole program
+pub trait Pass {
+    /// the name passes are chosen by with `--passes` and `--print-after`
+    fn name(&self) -> &'static str;
+
+    /// rewrites `program` in place, returning true if anything changed
+    fn run(&mut self, program: &mut Vec<Spanned<Node>>) -> bool;
+}
+
+/// the name of every pass, in the order `-O3` runs them
+pub const PASSES: &[&str] = &["const-fold", "dead-code", "match-fold"];
+
+/// the most times a pipeline chosen with `--passes` is run, the same as `-O3`, as naming the passes
+/// says nothing about how hard to try
+pub const LIST_ITERATIONS: usize = 16;
+
+/// the pass called `name`
+pub fn pass(name: &str) -> Option<Box<dyn Pass>> {
+    match name {
+        "const-fold" => Some(Box::new(ConstantFolding)),
+        "dead-code" => Some(Box::new(DeadCode)),
+        "match-fold" => Some(Box::new(BranchFolding)),
+        _ => None,
+    }
+}
+
+/// how hard to optimise, from `-O0` to `-O3`, which `chrn run` and `chrn build` take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// no passes, running the program as written
+    O0,
+    /// constant folding, once
+    O1,
+    /// constant folding and dead code removal, up to 4 times
+    O2,
+    /// everything in `-O2` and folding matches on literals, up to 16 times
+    O3,
+}
+
+impl OptLevel {
+    /// the level for the digit after `-O`
+    pub fn parse(level: &str) -> Option<OptLevel> {
+        match level {
+            "0" => Some(OptLevel::O0),
+            "1" => Some(OptLevel::O1),
+            "2" => Some(OptLevel::O2),
+            "3" => Some(OptLevel::O3),
+            _ => None,
+        }
+    }
+
+    /// the passes run at this level, in order
+    pub fn passes(self) -> &'static [&'static str] {
+        match self {
+            OptLevel::O0 => &[],
+            OptLevel::O1 => &PASSES[..1],
+            OptLevel::O2 => &PASSES[..2],
+            OptLevel::O3 => PASSES,
+        }
+    }
+
+    /// the most times the passes are run before stopping short of a fixed point
+    pub fn max_iterations(self) -> usize {
+        match self {
+            OptLevel::O0 | OptLevel::O1 => 1,
+            OptLevel::O2 => 4,
+            OptLevel::O3 => LIST_ITERATIONS,
+        }
+    }
+}
+
+/// how long a pass took, summed over every time it ran
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub pass: &'static str,
+    pub runs: usize,
+    pub elapsed: Duration,
+}
+
+/// an ordered pipeline of passes
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    max_iterations: usize,
+    /// one per pass, in pipeline order
+    timings: Vec<Timing>,
+}
+
+impl PassManager {
+    pub fn new(max_iterations: usize) -> PassManager {
+        PassManager {
+            passes: Vec::new(),
+            max_iterations,
+            timings: Vec::new(),
+        }
+    }
+
+    /// the pipeline for `level`
+    pub fn for_level(level: OptLevel) -> PassManager {
+        PassManager::with_passes(level.passes(), level.max_iterations())
+            .expect("levels only name known passes")
+    }
+
+    /// a pipeline of the passes called `names`, in order, or the first name that isn't a pass
+    pub fn with_passes<S: AsRef<str>>(
+        names: &[S],
+        max_iterations: usize,
+    ) -> Result<PassManager, String> {
+        let mut manager = PassManager::new(max_iterations);
+        for name in names {
+            let name = name.as_ref();
+            manager.add(pass(name).ok_or_else(|| name.to_string())?);
+        }
+
+        Ok(manager)
+    }
+
+    pub fn add(&mut self, pass: Box<dyn Pass>) {
+        self.timings.push(Timing {
+            pass: pass.name(),
+            runs: 0,
+            elapsed: Duration::default(),
+        });
+        self.passes.push(pass);
+    }
+
+    /// runs the passes in order until none of them changes the program, or they've been run
+    /// `max_iterations` times
+    pub fn run(&mut self, program: Vec<Spanned<Node>>) -> Vec<Spanned<Node>> {
+        self.run_inspecting(program, |_, _| {})
+    }
+
+    /// like `run`, calling `inspect` with each pass's name and the program after each time it runs
+    pub fn run_inspecting(
+        &mut self,
+        mut program: Vec<Spanned<Node>>,
+        mut inspect: impl FnMut(&str, &[Spanned<Node>]),
+    ) -> Vec<Spanned<Node>> {
+        for _ in 0..self.max_iterations {
+            let mut changed = false;
+            for (pass, timing) in self.passes.iter_mut().zip(&mut self.timings) {
+                let start = Instant::now();
+                changed |= pass.run(&mut program);
+                timing.elapsed += start.elapsed();
+                timing.runs += 1;
+
+                inspect(timing.pass, &program);
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        program
+    }
+
+    /// how long each pass has taken so far, in pipeline order
+    pub fn timings(&self) -> &[Timing] {
+        &self.timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use parse::parse;
+
+    use super::*;
+
+    /// changes the program the first `left` times it runs
+    struct Countdown {
+        left: usize,
+    }
+
+    impl Pass for Countdown {
+        fn name(&self) -> &'static str {
+            "countdown"
+        }
+
+        fn run(&mut self, _program: &mut Vec<Spanned<Node>>) -> bool {
+            let changed = self.left > 0;
+            self.left = self.left.saturating_sub(1);
+            changed
+        }
+    }
+
+    fn runs(left: usize, max_iterations: usize) -> usize {
+        let mut manager = PassManager::new(max_iterations);
+        manager.add(Box::new(Countdown { left }));
+        manager.run(Vec::new());
+        manager.timings()[0].runs
+    }
+
+    #[test]
+    fn runs_to_a_fixed_point_or_the_limit() {
+        assert_eq!(runs(0, 8), 1);
+        assert_eq!(runs(3, 8), 4);
+        assert_eq!(runs(10, 8), 8);
+    }
+
+    #[test]
+    fn passes_feed_each_other() {
+        let ast = parse(tokenise("fn f() { 1 + 2; g() }").unwrap()).unwrap();
+        let mut after = Vec::new();
+        let program = PassManager::for_level(OptLevel::O2)
+            .run_inspecting(ast, |pass, _| after.push(pass.to_string()));
+
+        if let Node::Fn { body, .. } = &program[0].node {
+            if let Node::Block { body, value } = &body.node {
+                assert!(body.is_empty());
+                assert!(value.is_some());
+            } else {
+                panic!("expected a block");
+            }
+        } else {
+            panic!("expected a function");
+        }
+        // the second time round changes nothing
+        assert_eq!(
+            after,
+            vec!["const-fold", "dead-code", "const-fold", "dead-code"]
+        );
+    }
+
+    #[test]
+    fn levels_name_known_passes() {
+        assert!(PassManager::for_level(OptLevel::O0).timings().is_empty());
+        assert!(!OptLevel::O2.passes().contains(&"match-fold"));
+        assert_eq!(OptLevel::O3.passes(), PASSES);
+        assert_eq!(OptLevel::parse("3"), Some(OptLevel::O3));
+        assert_eq!(
+            PassManager::with_passes(&["dead-code", "inline"], 1).err(),
+            Some(String::from("inline"))
+        );
+    }
+
+    #[test]
+    fn only_o3_folds_matches() {
+        let src = "let a = match 1 + 1 { 1 => \"one\", 2 => \"two\", _ => \"many\", };";
+        let folded = |level| {
+            let ast = parse(tokenise(src).unwrap()).unwrap();
+            let program = PassManager::for_level(level).run(ast);
+            match &program[0].node {
+                Node::Variable { value, .. } => value.node.clone(),
+                _ => panic!("expected a variable"),
+            }
+        };
+
+        assert!(matches!(folded(OptLevel::O2), Node::Match { .. }));
+        assert_eq!(folded(OptLevel::O3), Node::String(String::from("two")));
+    }
+}
@@ -0,0 +1,544 @@
+//! Stable JSON and S-expression dumps of tokens and the AST, for debugging and external tooling.
+//!
+//! In the S-expression form every node is written as `(kind attributes start..end children)`,
+//! with each child on its own line, indented under its parent.
+
+use lex::{Span, Token, TokenType};
+
+use crate::expr::{FieldPattern, ModulePath, Node, Pattern, Spanned, TypeExpr};
+
+/// pretty-printed JSON for a parsed program
+pub fn to_json(nodes: &[Spanned<Node>]) -> String {
+    serde_json::to_string_pretty(nodes).expect("the AST always serialises")
+}
+
+/// pretty-printed JSON for a token stream
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    serde_json::to_string_pretty(tokens).expect("tokens always serialise")
+}
+
+/// indented S-expressions for a parsed program, one top-level node per line
+pub fn to_sexp(nodes: &[Spanned<Node>]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        write_node(&mut out, node, 0);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// S-expressions for a token stream, one token per line
+pub fn tokens_to_sexp(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        let head = match &token.token_type {
+            TokenType::String(s) => format!("string {:?}", s),
+            TokenType::Number(n) => format!("number {}", n),
+            TokenType::Identifier(i) => format!("identifier {}", i),
+            TokenType::DocComment(d) => format!("doc-comment {:?}", d),
+            other => kebab_case(&format!("{:?}", other)),
+        };
+
+        out.push_str(&format!("({} {})\n", head, span(token.span)));
+    }
+
+    out
+}
+
+fn span(span: Span) -> String {
+    format!("{}..{}", span.0, span.1)
+}
+
+/// `NotStrictEqual` to `not-strict-equal`, keeping runs of capitals together
+fn kebab_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lowercase {
+            out.push('-');
+        }
+
+        previous_lowercase = c.is_lowercase();
+        out.extend(c.to_lowercase());
+    }
+
+    out
+}
+
+/// a child in the S-expression form: a node, a type annotation or a pattern
+enum Child<'a> {
+    Node(&'a Spanned<Node>),
+    Type(&'a Spanned<TypeExpr>),
+    Pattern(&'a Spanned<Pattern>),
+}
+
+fn write_type(out: &mut String, ty: &Spanned<TypeExpr>, depth: usize) {
+    let mut children = Vec::new();
+    let head = match &ty.node {
+        TypeExpr::Named { name, generics } => {
+            children.extend(generics.iter().map(|g| (None, Child::Type(g))));
+            format!("type {}", name)
+        }
+        TypeExpr::Fn { params, ret } => {
+            children.extend(params.iter().map(|p| (None, Child::Type(p))));
+            if let Some(ret) = ret {
+                children.push((Some(":returns"), Child::Type(ret)));
+            }
+            String::from("fn-type")
+        }
+    };
+
+    write_sexp(out, head, ty.span, children, depth);
+}
+
+fn write_pattern(out: &mut String, pattern: &Spanned<Pattern>, depth: usize) {
+    let mut children = Vec::new();
+    let head = match &pattern.node {
+        Pattern::Identifier(name) => format!("identifier {}", name),
+        Pattern::Wildcard => String::from("wildcard"),
+        Pattern::Number(n) => format!("number {}", n),
+        Pattern::String(s) => format!("string {:?}", s),
+        Pattern::Boolean(b) => format!("boolean {}", b),
+        Pattern::Tuple(items) => {
+            children.extend(items.iter().map(|i| (None, Child::Pattern(i))));
+            String::from("tuple-pattern")
+        }
+        Pattern::List { items, rest } => {
+            children.extend(items.iter().map(|i| (None, Child::Pattern(i))));
+            if let Some(rest) = rest {
+                children.push((Some(":rest"), Child::Pattern(rest)));
+            }
+            String::from("list-pattern")
+        }
+        Pattern::Struct { name, fields } => {
+            // field names go in the head, leaving only their patterns as children
+            children.extend(fields.iter().map(|f| (None, Child::Pattern(&f.pattern))));
+            match name {
+                Some(name) => format!("struct-pattern {} {}", name, field_names(fields)),
+                None => format!("struct-pattern {}", field_names(fields)),
+            }
+        }
+        Pattern::Variant {
+            name,
+            variant,
+            fields,
+        } => {
+            children.extend(fields.iter().map(|f| (None, Child::Pattern(f))));
+            format!("variant-pattern {}::{}", name, variant)
+        }
+    };
+
+    write_sexp(out, head, pattern.span, children, depth);
+}
+
+/// `[a b]`, the fields a struct pattern names, in order
+fn field_names(fields: &[FieldPattern]) -> String {
+    let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+    format!("[{}]", names.join(" "))
+}
+
+fn write_node(out: &mut String, node: &Spanned<Node>, depth: usize) {
+    // children are paired with an optional keyword, used where position alone is ambiguous
+    let mut children: Vec<(Option<&str>, Child)> = Vec::new();
+
+    let head = match &node.node {
+        Node::Variable {
+            public,
+            variable_type,
+            pattern,
+            ty,
+            value,
+        } => {
+            children.push((None, Child::Pattern(pattern)));
+            if let Some(ty) = ty {
+                children.push((Some(":type"), Child::Type(ty)));
+            }
+            children.push((None, Child::Node(value)));
+            format!(
+                "{}variable {}",
+                visibility(*public),
+                kebab_case(&format!("{:?}", variable_type))
+            )
+        }
+        Node::BinaryExpr {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            children.push((None, Child::Node(lhs)));
+            children.push((None, Child::Node(rhs)));
+            format!("binary-expr {}", kebab_case(&format!("{:?}", operation)))
+        }
+        Node::UnaryExpr { operation, body } => {
+            children.push((None, Child::Node(body)));
+            format!("unary-expr {}", kebab_case(&format!("{:?}", operation)))
+        }
+        Node::Fn {
+            public,
+            name,
+            args,
+            ret,
+            body,
+        } => {
+            children.push((None, Child::Node(name)));
+            for arg in args {
+                children.push((None, Child::Pattern(&arg.pattern)));
+                if let Some(ty) = &arg.ty {
+                    children.push((Some(":type"), Child::Type(ty)));
+                }
+            }
+            if let Some(ret) = ret {
+                children.push((Some(":returns"), Child::Type(ret)));
+            }
+            children.push((None, Child::Node(body)));
+            format!("{}fn", visibility(*public))
+        }
+        Node::Closure { args, ret, body } => {
+            for arg in args {
+                children.push((None, Child::Pattern(&arg.pattern)));
+                if let Some(ty) = &arg.ty {
+                    children.push((Some(":type"), Child::Type(ty)));
+                }
+            }
+            if let Some(ret) = ret {
+                children.push((Some(":returns"), Child::Type(ret)));
+            }
+            children.push((Some(":body"), Child::Node(body)));
+            String::from("closure")
+        }
+        Node::FnCall { function, args } => {
+            children.push((None, Child::Node(function)));
+            children.extend(args.iter().map(|a| (None, Child::Node(a))));
+            String::from("fn-call")
+        }
+        Node::Block { body, value } => {
+            children.extend(body.iter().map(|s| (None, Child::Node(s))));
+            if let Some(value) = value {
+                children.push((Some(":value"), Child::Node(value)));
+            }
+            String::from("block")
+        }
+        Node::Return(value) => {
+            if let Some(value) = value {
+                children.push((None, Child::Node(value)));
+            }
+            String::from("return")
+        }
+        Node::Struct {
+            public,
+            name,
+            fields,
+        } => {
+            children.push((None, Child::Node(name)));
+            for field in fields {
+                children.push((None, Child::Node(&field.name)));
+                if let Some(ty) = &field.ty {
+                    children.push((Some(":type"), Child::Type(ty)));
+                }
+            }
+            format!("{}struct", visibility(*public))
+        }
+        Node::Enum {
+            public,
+            name,
+            variants,
+        } => {
+            children.push((None, Child::Node(name)));
+            for variant in variants {
+                children.push((None, Child::Node(&variant.name)));
+                children.extend(
+                    variant
+                        .fields
+                        .iter()
+                        .map(|f| (Some(":type"), Child::Type(f))),
+                );
+            }
+            format!("{}enum", visibility(*public))
+        }
+        Node::Path { name, variant } => {
+            children.push((None, Child::Node(name)));
+            format!("path {}", variant.node)
+        }
+        Node::Match { value, arms } => {
+            children.push((None, Child::Node(value)));
+            for arm in arms {
+                children.push((Some(":arm"), Child::Pattern(&arm.pattern)));
+                children.push((None, Child::Node(&arm.value)));
+            }
+            String::from("match")
+        }
+        Node::Import { path, alias, items } => {
+            if let Some(alias) = alias {
+                children.push((Some(":as"), Child::Node(alias)));
+            }
+            children.extend(items.iter().map(|i| (None, Child::Node(i))));
+
+            let path = match &path.node {
+                ModulePath::File(file) => format!("{:?}", file),
+                ModulePath::Dotted(segments) => segments.join("."),
+            };
+            format!("import {}", path)
+        }
+        Node::Attribute { inner, name, args } => {
+            let args: Vec<&str> = args.iter().map(|a| a.node.as_str()).collect();
+            let kind = if *inner {
+                "inner-attribute"
+            } else {
+                "attribute"
+            };
+            if args.is_empty() {
+                format!("{} {}", kind, name.node)
+            } else {
+                format!("{} {} {}", kind, name.node, args.join(" "))
+            }
+        }
+        Node::Assign {
+            operation,
+            target,
+            value,
+        } => {
+            children.push((None, Child::Node(target)));
+            children.push((None, Child::Node(value)));
+            match operation {
+                Some(operation) => format!("assign {}", kebab_case(&format!("{:?}", operation))),
+                None => String::from("assign"),
+            }
+        }
+        Node::List(items) => {
+            children.extend(items.iter().map(|i| (None, Child::Node(i))));
+            String::from("list")
+        }
+        Node::Tuple(items) => {
+            children.extend(items.iter().map(|i| (None, Child::Node(i))));
+            String::from("tuple")
+        }
+        Node::Map(entries) => {
+            for entry in entries {
+                children.push((Some(":key"), Child::Node(&entry.key)));
+                children.push((Some(":value"), Child::Node(&entry.value)));
+            }
+            String::from("map")
+        }
+        Node::Index { target, index } => {
+            children.push((None, Child::Node(target)));
+            children.push((None, Child::Node(index)));
+            String::from("index")
+        }
+        Node::Slice { target, start, end } => {
+            children.push((None, Child::Node(target)));
+            if let Some(start) = start {
+                children.push((Some(":start"), Child::Node(start)));
+            }
+            if let Some(end) = end {
+                children.push((Some(":end"), Child::Node(end)));
+            }
+            String::from("slice")
+        }
+        Node::Number(n) => format!("number {}", n),
+        Node::String(s) => format!("string {:?}", s),
+        Node::Boolean(b) => format!("boolean {}", b),
+        Node::Identifier(i) => format!("identifier {}", i),
+    };
+
+    write_sexp(out, head, node.span, children, depth);
+}
+
+/// prefix marking `pub` items
+fn visibility(public: bool) -> &'static str {
+    if public {
+        "pub-"
+    } else {
+        ""
+    }
+}
+
+fn write_sexp(
+    out: &mut String,
+    head: String,
+    node_span: Span,
+    children: Vec<(Option<&str>, Child)>,
+    depth: usize,
+) {
+    out.push_str(&format!("({} {}", head, span(node_span)));
+    for (keyword, child) in children {
+        out.push('\n');
+        out.push_str(&"  ".repeat(depth + 1));
+        if let Some(keyword) = keyword {
+            out.push_str(keyword);
+            out.push(' ');
+        }
+
+        match child {
+            Child::Node(node) => write_node(out, node, depth + 1),
+            Child::Type(ty) => write_type(out, ty, depth + 1),
+            Child::Pattern(pattern) => write_pattern(out, pattern, depth + 1),
+        }
+    }
+    out.push(')');
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn sexp_includes_spans() {
+        let ast = parse(tokenise("let t = -2 + x;").unwrap()).unwrap();
+
+        assert_eq!(
+            to_sexp(&ast),
+            "(variable let 0..14
+  (identifier t 4..5)
+  (binary-expr add 8..14
+    (unary-expr minus 8..10
+      (number 2 9..10))
+    (identifier x 13..14)))
+"
+        );
+    }
+
+    #[test]
+    fn sexp_marks_block_values() {
+        let ast = parse(tokenise("fn f() { g(); 1 }").unwrap()).unwrap();
+
+        assert_eq!(
+            to_sexp(&ast),
+            "(fn 0..17
+  (identifier f 3..4)
+  (block 7..17
+    (fn-call 9..12
+      (identifier g 9..10))
+    :value (number 1 14..15)))
+"
+        );
+    }
+
+    #[test]
+    fn sexp_types() {
+        let ast = parse(tokenise("fn f(a: List<T>) -> fn(T) {}").unwrap()).unwrap();
+
+        assert_eq!(
+            to_sexp(&ast),
+            "(fn 0..28
+  (identifier f 3..4)
+  (identifier a 5..6)
+  :type (type List 8..15
+    (type T 13..14))
+  :returns (fn-type 20..25
+    (type T 23..24))
+  (block 26..28))
+"
+        );
+    }
+
+    #[test]
+    fn sexp_imports() {
+        let ast = parse(tokenise("import std.math as m; pub fn f() {}").unwrap()).unwrap();
+
+        assert_eq!(
+            to_sexp(&ast),
+            "(import std.math 0..20
+  :as (identifier m 19..20))
+(pub-fn 22..35
+  (identifier f 29..30)
+  (block 33..35))
+"
+        );
+    }
+
+    #[test]
+    fn sexp_collections() {
+        let ast = parse(tokenise("#{ 1: [x] }[1][..2]").unwrap()).unwrap();
+
+        assert_eq!(
+            to_sexp(&ast),
+            "(slice 0..19
+  (index 0..14
+    (map 0..11
+      :key (number 1 3..4)
+      :value (list 6..9
+        (identifier x 7..8)))
+    (number 1 12..13))
+  :end (number 2 17..18))
+"
+        );
+    }
+
+    #[test]
+    fn sexp_patterns() {
+        let ast = parse(tokenise("let P { a, b: [_, ..c] } = (1,);").unwrap()).unwrap();
+
+        assert_eq!(
+            to_sexp(&ast),
+            "(variable let 0..31
+  (struct-pattern P [a b] 4..24
+    (identifier a 8..9)
+    (list-pattern 14..22
+      (wildcard 15..16)
+      :rest (identifier c 20..21)))
+  (tuple 27..31
+    (number 1 28..29)))
+"
+        );
+    }
+
+    #[test]
+    fn json_is_stable() {
+        let ast = parse(tokenise("f(\"a\")").unwrap()).unwrap();
+
+        let expected = r#"[
+  {
+    "node": {
+      "FnCall": {
+        "function": {
+          "node": {
+            "Identifier": "f"
+          },
+          "span": [
+            0,
+            1
+          ]
+        },
+        "args": [
+          {
+            "node": {
+              "String": "a"
+            },
+            "span": [
+              2,
+              5
+            ]
+          }
+        ]
+      }
+    },
+    "span": [
+      0,
+      6
+    ]
+  }
+]"#;
+        assert_eq!(to_json(&ast), expected);
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = tokenise("a !== 1").unwrap();
+
+        assert_eq!(
+            tokens_to_sexp(&tokens),
+            "(identifier a 0..1)
+(whitespace 1..2)
+(not-strict-equal 2..5)
+(whitespace 5..6)
+(number 1 6..7)
+(eoi 7..7)
+"
+        );
+        assert!(tokens_to_json(&tokens).contains("\"NotStrictEqual\""));
+    }
+}
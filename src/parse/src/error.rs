@@ -1,7 +1,23 @@
-#[derive(Debug, Clone)]
+use lex::Span;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
-    UnexpectedTermination,
-    NoToken,
-    InvalidType,
-    UnexpectedToken,
+    UnexpectedTermination(Span),
+    InvalidType(Span),
+    UnexpectedToken(Span),
+    /// the left of an `=` that can't be assigned to
+    InvalidAssignment(Span),
+    /// a pattern that can fail to match, where it must always match
+    RefutablePattern(Span),
+    /// a statement not ended by `;`, with a zero-width span where it belongs
+    MissingSemicolon {
+        span: Span,
+        /// whether the next token starts a new line, making a `;` certain to be what's missing
+        line_ends: bool,
+    },
+    /// a name starting a statement that looks like a mistyped keyword, like `retrun`
+    MisspelledKeyword {
+        span: Span,
+        keyword: &'static str,
+    },
 }
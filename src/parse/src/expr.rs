@@ -1,44 +1,310 @@
-use lex::TokenType;
+use lex::{Span, TokenType};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
 pub enum Op {
     Add,
     Subtract,
     Multiply,
     Divide,
+    Equal,
+    NotEqual,
+    StrictEqual,
+    NotStrictEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    And,
+    Or,
 }
 
-#[derive(Debug, Clone)]
+impl Op {
+    /// binding power of the operator, higher binds tighter
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Op::Or => 1,
+            Op::And => 2,
+            Op::Equal | Op::NotEqual | Op::StrictEqual | Op::NotStrictEqual => 3,
+            Op::Greater | Op::GreaterEqual | Op::Less | Op::LessEqual => 4,
+            Op::Add | Op::Subtract => 5,
+            Op::Multiply | Op::Divide => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
 pub enum Var {
     Let,
     Const,
 }
 
+/// a value paired with the source range it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct Spanned<T> {
+    pub node: T,
+    /// byte range from the start of the first token to the end of the last
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node, span }
+    }
+}
+
+/// a type annotation
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub enum TypeExpr {
+    /// `Number`, or a generic type such as `List<String>`
+    Named {
+        name: String,
+        generics: Vec<Spanned<TypeExpr>>,
+    },
+    /// `fn(Number, Number) -> Number`
+    Fn {
+        params: Vec<Spanned<TypeExpr>>,
+        ret: Option<Box<Spanned<TypeExpr>>>,
+    },
+}
+
+/// a function parameter, `pattern` or `pattern: Type`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct Param {
+    pub pattern: Spanned<Pattern>,
+    pub ty: Option<Spanned<TypeExpr>>,
+}
+
+/// the shape of a value to take apart, binding names to its pieces
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub enum Pattern {
+    /// `name`, binding the whole value
+    Identifier(String),
+    /// `_`, matching anything and binding nothing
+    Wildcard,
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    /// `(a, b)`
+    Tuple(Vec<Spanned<Pattern>>),
+    /// `[a, b]`, or `[a, b, ..rest]` to match at least as many items
+    List {
+        items: Vec<Spanned<Pattern>>,
+        rest: Option<Box<Spanned<Pattern>>>,
+    },
+    /// `{ x, y: pattern }`, or `Name { x, y }` to also match the struct's name
+    Struct {
+        name: Option<String>,
+        fields: Vec<FieldPattern>,
+    },
+    /// `Enum::Variant`, or `Enum::Variant(a, b)` to also match the variant's values
+    Variant {
+        name: String,
+        variant: String,
+        fields: Vec<Spanned<Pattern>>,
+    },
+}
+
+/// `field` or `field: pattern` in a struct pattern
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct FieldPattern {
+    pub name: String,
+    /// for the shorthand `field`, an identifier pattern binding the field's name
+    pub pattern: Spanned<Pattern>,
+}
+
+impl Spanned<Pattern> {
+    /// the first part of the pattern that can fail to match a value of the right shape
+    pub fn refutable(&self) -> Option<Span> {
+        match &self.node {
+            Pattern::Identifier(_) | Pattern::Wildcard => None,
+            Pattern::Number(_) | Pattern::String(_) | Pattern::Boolean(_) => Some(self.span),
+            Pattern::Tuple(items) => items.iter().find_map(|i| i.refutable()),
+            Pattern::List { items, rest } => items
+                .iter()
+                .chain(rest.as_deref())
+                .find_map(|i| i.refutable()),
+            Pattern::Struct { fields, .. } => fields.iter().find_map(|f| f.pattern.refutable()),
+            Pattern::Variant { .. } => Some(self.span),
+        }
+    }
+
+    /// every name the pattern binds, in source order
+    pub fn bindings(&self) -> Vec<(&str, Span)> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<(&'a str, Span)>) {
+        match &self.node {
+            Pattern::Identifier(name) => names.push((name, self.span)),
+            Pattern::Tuple(items) => items.iter().for_each(|i| i.collect_bindings(names)),
+            Pattern::List { items, rest } => {
+                items.iter().for_each(|i| i.collect_bindings(names));
+                if let Some(rest) = rest {
+                    rest.collect_bindings(names);
+                }
+            }
+            Pattern::Struct { fields, .. } => fields
+                .iter()
+                .for_each(|f| f.pattern.collect_bindings(names)),
+            Pattern::Variant { fields, .. } => {
+                fields.iter().for_each(|f| f.collect_bindings(names))
+            }
+            Pattern::Wildcard | Pattern::Number(_) | Pattern::String(_) | Pattern::Boolean(_) => {}
+        }
+    }
+}
+
+/// a struct field, `name` or `name: Type`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct Field {
+    pub name: Spanned<Node>,
+    pub ty: Option<Spanned<TypeExpr>>,
+}
+
+/// an enum variant, `Name` or `Name(Type, Type)`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct Variant {
+    pub name: Spanned<Node>,
+    pub fields: Vec<Spanned<TypeExpr>>,
+}
+
+/// `pattern => value` in a `match`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct Arm {
+    pub pattern: Spanned<Pattern>,
+    pub value: Spanned<Node>,
+}
+
+/// a `key: value` pair in a map literal
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub struct MapEntry {
+    pub key: Spanned<Node>,
+    pub value: Spanned<Node>,
+}
+
+/// the module named by an import
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
+pub enum ModulePath {
+    /// `"path/to/mod"`, relative to the package source directory
+    File(String),
+    /// `std.math`
+    Dotted(Vec<String>),
+}
+
 /// AST Node
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "dump", derive(serde::Serialize))]
 pub enum Node {
     Variable {
+        /// `pub`, exported from the module
+        public: bool,
         variable_type: Var,
-        identifier: Box<Node>,
-        value: Box<Node>,
+        pattern: Spanned<Pattern>,
+        ty: Option<Spanned<TypeExpr>>,
+        value: Box<Spanned<Node>>,
     },
     BinaryExpr {
         operation: Op,
-        lhs: Box<Node>,
-        rhs: Box<Node>,
+        lhs: Box<Spanned<Node>>,
+        rhs: Box<Spanned<Node>>,
     },
     UnaryExpr {
         operation: TokenType,
-        body: Box<Node>,
+        body: Box<Spanned<Node>>,
     },
     Fn {
-        name: Box<Node>,
-        args: Box<Node>,
-        body: Box<Node>,
+        public: bool,
+        name: Box<Spanned<Node>>,
+        args: Vec<Param>,
+        ret: Option<Spanned<TypeExpr>>,
+        body: Box<Spanned<Node>>,
+    },
+    /// an anonymous function, `fn(args) { body }` or `|args| body`
+    Closure {
+        args: Vec<Param>,
+        ret: Option<Spanned<TypeExpr>>,
+        body: Box<Spanned<Node>>,
     },
     FnCall {
-        function: Box<Node>,
-        args: Vec<Node>,
+        function: Box<Spanned<Node>>,
+        args: Vec<Spanned<Node>>,
+    },
+    /// `{ ... }`, evaluating to `value` when the last expression has no trailing semicolon
+    Block {
+        body: Vec<Spanned<Node>>,
+        value: Option<Box<Spanned<Node>>>,
+    },
+    Return(Option<Box<Spanned<Node>>>),
+    Struct {
+        public: bool,
+        name: Box<Spanned<Node>>,
+        fields: Vec<Field>,
+    },
+    /// `enum Name { A, B(Type) }`
+    Enum {
+        public: bool,
+        name: Box<Spanned<Node>>,
+        variants: Vec<Variant>,
+    },
+    /// `Enum::Variant`, a variant without values or the function making one with them
+    Path {
+        name: Box<Spanned<Node>>,
+        variant: Spanned<String>,
+    },
+    /// `match value { pattern => value, ... }`, evaluating to the first arm whose pattern matches
+    Match {
+        value: Box<Spanned<Node>>,
+        arms: Vec<Arm>,
+    },
+    /// `import path`, `import path as alias` or `import path { a, b }`
+    Import {
+        path: Spanned<ModulePath>,
+        alias: Option<Box<Spanned<Node>>>,
+        items: Vec<Spanned<Node>>,
+    },
+    /// `#[name(args)]`, applying to the statement after it, or `#![name(args)]`, applying to the
+    /// block or file it's in
+    Attribute {
+        inner: bool,
+        name: Spanned<String>,
+        args: Vec<Spanned<String>>,
+    },
+    /// `target = value`, or `target op= value` with the operator
+    Assign {
+        operation: Option<Op>,
+        target: Box<Spanned<Node>>,
+        value: Box<Spanned<Node>>,
+    },
+    /// `[a, b, c]`
+    List(Vec<Spanned<Node>>),
+    /// `(a, b)`, or `(a,)` with a single item
+    Tuple(Vec<Spanned<Node>>),
+    /// `#{ key: value, ... }`
+    Map(Vec<MapEntry>),
+    /// `target[index]`
+    Index {
+        target: Box<Spanned<Node>>,
+        index: Box<Spanned<Node>>,
+    },
+    /// `target[start..end]`, where either bound may be left off
+    Slice {
+        target: Box<Spanned<Node>>,
+        start: Option<Box<Spanned<Node>>>,
+        end: Option<Box<Spanned<Node>>>,
     },
     Number(f64),
     String(String),
@@ -0,0 +1,362 @@
+//! Traversal of the AST by value, rebuilding it from the bottom up.
+//!
+//! Like the visitors, `fold_node` dispatches to a hook per variant and every hook folds the node's
+//! children by default. An overridden hook usually calls `fold_children` first, then inspects the
+//! already folded node. Type annotations are carried over as they are.
+
+use crate::expr::{Arm, Field, MapEntry, Node, Param, Pattern, Spanned, Variant};
+
+pub trait Fold {
+    fn fold_node(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        walk_fold(self, node)
+    }
+
+    fn fold_variable(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_binary_expr(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_unary_expr(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_fn(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_closure(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_fn_call(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_block(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_return(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_struct(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_enum(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_path(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_match(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_import(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_attribute(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        node
+    }
+
+    fn fold_assign(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_list(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_tuple(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_map(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_index(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_slice(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        fold_children(self, node)
+    }
+
+    fn fold_number(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        node
+    }
+
+    fn fold_string(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        node
+    }
+
+    fn fold_boolean(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        node
+    }
+
+    fn fold_identifier(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+        node
+    }
+
+    /// called for every pattern bound by a `let`, a parameter or a `match` arm
+    fn fold_pattern(&mut self, pattern: Spanned<Pattern>) -> Spanned<Pattern> {
+        pattern
+    }
+}
+
+/// calls the folder's hook for the variant of `node`
+pub fn walk_fold<F: Fold + ?Sized>(folder: &mut F, node: Spanned<Node>) -> Spanned<Node> {
+    match &node.node {
+        Node::Variable { .. } => folder.fold_variable(node),
+        Node::BinaryExpr { .. } => folder.fold_binary_expr(node),
+        Node::UnaryExpr { .. } => folder.fold_unary_expr(node),
+        Node::Fn { .. } => folder.fold_fn(node),
+        Node::Closure { .. } => folder.fold_closure(node),
+        Node::FnCall { .. } => folder.fold_fn_call(node),
+        Node::Block { .. } => folder.fold_block(node),
+        Node::Return(_) => folder.fold_return(node),
+        Node::Struct { .. } => folder.fold_struct(node),
+        Node::Enum { .. } => folder.fold_enum(node),
+        Node::Path { .. } => folder.fold_path(node),
+        Node::Match { .. } => folder.fold_match(node),
+        Node::Import { .. } => folder.fold_import(node),
+        Node::Attribute { .. } => folder.fold_attribute(node),
+        Node::Assign { .. } => folder.fold_assign(node),
+        Node::List(_) => folder.fold_list(node),
+        Node::Tuple(_) => folder.fold_tuple(node),
+        Node::Map(_) => folder.fold_map(node),
+        Node::Index { .. } => folder.fold_index(node),
+        Node::Slice { .. } => folder.fold_slice(node),
+        Node::Number(_) => folder.fold_number(node),
+        Node::String(_) => folder.fold_string(node),
+        Node::Boolean(_) => folder.fold_boolean(node),
+        Node::Identifier(_) => folder.fold_identifier(node),
+    }
+}
+
+/// folds each direct child of `node` in source order, keeping the node's own span
+pub fn fold_children<F: Fold + ?Sized>(folder: &mut F, node: Spanned<Node>) -> Spanned<Node> {
+    let folded = match node.node {
+        Node::Variable {
+            public,
+            variable_type,
+            pattern,
+            ty,
+            value,
+        } => Node::Variable {
+            public,
+            variable_type,
+            pattern: folder.fold_pattern(pattern),
+            ty,
+            value: fold_boxed(folder, *value),
+        },
+        Node::BinaryExpr {
+            operation,
+            lhs,
+            rhs,
+        } => Node::BinaryExpr {
+            operation,
+            lhs: fold_boxed(folder, *lhs),
+            rhs: fold_boxed(folder, *rhs),
+        },
+        Node::UnaryExpr { operation, body } => Node::UnaryExpr {
+            operation,
+            body: fold_boxed(folder, *body),
+        },
+        Node::Fn {
+            public,
+            name,
+            args,
+            ret,
+            body,
+        } => Node::Fn {
+            public,
+            name: fold_boxed(folder, *name),
+            args: fold_params(folder, args),
+            ret,
+            body: fold_boxed(folder, *body),
+        },
+        Node::Closure { args, ret, body } => Node::Closure {
+            args: fold_params(folder, args),
+            ret,
+            body: fold_boxed(folder, *body),
+        },
+        Node::FnCall { function, args } => Node::FnCall {
+            function: fold_boxed(folder, *function),
+            args: fold_all(folder, args),
+        },
+        Node::Block { body, value } => Node::Block {
+            body: fold_all(folder, body),
+            value: value.map(|v| fold_boxed(folder, *v)),
+        },
+        Node::Return(value) => Node::Return(value.map(|v| fold_boxed(folder, *v))),
+        Node::Struct {
+            public,
+            name,
+            fields,
+        } => Node::Struct {
+            public,
+            name: fold_boxed(folder, *name),
+            fields: fields
+                .into_iter()
+                .map(|f| Field {
+                    name: folder.fold_node(f.name),
+                    ty: f.ty,
+                })
+                .collect(),
+        },
+        Node::Enum {
+            public,
+            name,
+            variants,
+        } => Node::Enum {
+            public,
+            name: fold_boxed(folder, *name),
+            variants: variants
+                .into_iter()
+                .map(|v| Variant {
+                    name: folder.fold_node(v.name),
+                    fields: v.fields,
+                })
+                .collect(),
+        },
+        Node::Path { name, variant } => Node::Path {
+            name: fold_boxed(folder, *name),
+            variant,
+        },
+        Node::Match { value, arms } => Node::Match {
+            value: fold_boxed(folder, *value),
+            arms: arms
+                .into_iter()
+                .map(|a| Arm {
+                    pattern: folder.fold_pattern(a.pattern),
+                    value: folder.fold_node(a.value),
+                })
+                .collect(),
+        },
+        Node::Import { path, alias, items } => Node::Import {
+            path,
+            alias: alias.map(|a| fold_boxed(folder, *a)),
+            items: fold_all(folder, items),
+        },
+        Node::Assign {
+            operation,
+            target,
+            value,
+        } => Node::Assign {
+            operation,
+            target: fold_boxed(folder, *target),
+            value: fold_boxed(folder, *value),
+        },
+        Node::List(items) => Node::List(fold_all(folder, items)),
+        Node::Tuple(items) => Node::Tuple(fold_all(folder, items)),
+        Node::Map(entries) => Node::Map(
+            entries
+                .into_iter()
+                .map(|e| MapEntry {
+                    key: folder.fold_node(e.key),
+                    value: folder.fold_node(e.value),
+                })
+                .collect(),
+        ),
+        Node::Index { target, index } => Node::Index {
+            target: fold_boxed(folder, *target),
+            index: fold_boxed(folder, *index),
+        },
+        Node::Slice { target, start, end } => Node::Slice {
+            target: fold_boxed(folder, *target),
+            start: start.map(|s| fold_boxed(folder, *s)),
+            end: end.map(|e| fold_boxed(folder, *e)),
+        },
+        leaf @ (Node::Attribute { .. }
+        | Node::Number(_)
+        | Node::String(_)
+        | Node::Boolean(_)
+        | Node::Identifier(_)) => leaf,
+    };
+
+    Spanned::new(folded, node.span)
+}
+
+fn fold_boxed<F: Fold + ?Sized>(folder: &mut F, node: Spanned<Node>) -> Box<Spanned<Node>> {
+    Box::new(folder.fold_node(node))
+}
+
+fn fold_params<F: Fold + ?Sized>(folder: &mut F, params: Vec<Param>) -> Vec<Param> {
+    params
+        .into_iter()
+        .map(|p| Param {
+            pattern: folder.fold_pattern(p.pattern),
+            ty: p.ty,
+        })
+        .collect()
+}
+
+fn fold_all<F: Fold + ?Sized>(folder: &mut F, nodes: Vec<Spanned<Node>>) -> Vec<Spanned<Node>> {
+    nodes.into_iter().map(|n| folder.fold_node(n)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+
+    use super::*;
+    use crate::{expr::Op, parse};
+
+    /// renames every identifier, leaving the rest of the tree as is
+    struct Rename;
+
+    impl Fold for Rename {
+        fn fold_identifier(&mut self, node: Spanned<Node>) -> Spanned<Node> {
+            match node.node {
+                Node::Identifier(i) => Spanned::new(Node::Identifier(i.to_uppercase()), node.span),
+                other => Spanned::new(other, node.span),
+            }
+        }
+
+        fn fold_pattern(&mut self, pattern: Spanned<Pattern>) -> Spanned<Pattern> {
+            match pattern.node {
+                Pattern::Identifier(i) => {
+                    Spanned::new(Pattern::Identifier(i.to_uppercase()), pattern.span)
+                }
+                other => Spanned::new(other, pattern.span),
+            }
+        }
+    }
+
+    #[test]
+    fn rebuilds_with_folded_children() {
+        let ast = parse(tokenise("let a = b * c;").unwrap()).unwrap();
+        let folded = Rename.fold_node(ast[0].clone());
+
+        assert_eq!(folded.span, ast[0].span);
+        if let Node::Variable { pattern, value, .. } = folded.node {
+            assert_eq!(pattern.node, Pattern::Identifier(String::from("A")));
+
+            if let Node::BinaryExpr {
+                operation,
+                lhs,
+                rhs,
+            } = value.node
+            {
+                assert_eq!(operation, Op::Multiply);
+                assert_eq!(lhs.node, Node::Identifier(String::from("B")));
+                assert_eq!(rhs.node, Node::Identifier(String::from("C")));
+                assert_eq!(rhs.span, (12, 13));
+            } else {
+                panic!("expected a binary expression");
+            }
+        } else {
+            panic!("expected a variable");
+        }
+    }
+}
@@ -1,19 +1,26 @@
-use expr::{Node, Op, Var};
-use lex::{Token, TokenType};
+use expr::{
+    Arm, Field, FieldPattern, MapEntry, ModulePath, Node, Op, Param, Pattern, Spanned, TypeExpr,
+    Var, Variant,
+};
+use lex::{suggest::closest_keyword, trace, Span, Token, TokenType};
 
+#[cfg(feature = "dump")]
+pub mod dump;
 pub mod expr;
+pub mod fold;
+pub mod visit;
 
 pub mod error;
 use error::ParseError;
 
 pub struct Parser<'a> {
     tokens: Vec<Token<'a>>,
-    nodes: Vec<Node>,
+    nodes: Vec<Spanned<Node>>,
     index: usize,
 }
 
 impl<'a> Parser<'a> {
-    fn new(tok: Vec<Token>) -> Parser {
+    fn new(tok: Vec<Token<'_>>) -> Parser<'_> {
         Parser {
             tokens: tok,
             nodes: Vec::new(),
@@ -22,224 +29,1091 @@ impl<'a> Parser<'a> {
     }
 
     /// returns the next token, advancing the parser
-    fn next(&mut self) -> Option<&Token> {
-        if let Ok(is_ended) = self.is_ended() {
-            self.index += 1;
-        } else {
-            return None;
-        }
+    fn next(&mut self) -> Result<Token<'a>, ParseError> {
+        self.is_ended()?;
+        self.index += 1;
+        trace::at(self.tokens[self.index - 1].span);
 
-        self.peek_previous()
+        Ok(self.tokens[self.index - 1].clone())
     }
 
     /// Looks at the next token without advancing the parser
-    fn peek(&self) -> Option<&Token> {
+    fn peek(&self) -> Option<&Token<'a>> {
         self.tokens.get(self.index)
     }
 
-    // returns the nth character after index without advancing parser
-    fn peek_nth(&self, idx: usize) -> Option<&Token> {
-        self.tokens.get(self.index + idx)
-    }
-
-    fn peek_end_of_statement(&self) -> Vec<Token> {
-        let mut idx = 0;
-        while self.peek().unwrap().token_type != TokenType::Semicolon {
-            idx += 1;
-        }
-
-        self.tokens[self.index..idx].to_vec()
-    }
-
     /// looks at the previous token without changing the parser's place
-    fn peek_previous(&self) -> Option<&Token> {
-        self.tokens.get(self.index - 1)
+    fn peek_previous(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.index.checked_sub(1)?)
     }
 
-    fn peek_previous_nth(&self, idx: usize) -> Option<&Token> {
-        self.tokens.get(self.index - idx)
-    }
-
-    /// returns Ok(true) if there's nothing left to parse (EOI), Ok(false) if there is, and Err(ParseError) if something went wrong peeking the next token
+    /// returns Err(ParseError) if there's nothing left to parse
     fn is_ended(&self) -> Result<(), ParseError> {
         if self.index >= self.tokens.len() {
-            Err(ParseError::UnexpectedTermination)
+            Err(ParseError::UnexpectedTermination(self.end_span()))
         } else {
             Ok(())
         }
     }
 
-    fn parse(&mut self) -> Result<Vec<Node>, ParseError> {
-        while self.index < self.tokens.len() {
-            let n = self.expr()?;
-            self.nodes.push(n);
+    /// true once only the end of input remains
+    fn at_end(&self) -> bool {
+        match self.peek() {
+            Some(t) => t.token_type == TokenType::EOI,
+            None => true,
+        }
+    }
+
+    /// zero-width span just past the last token
+    fn end_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(t) => (t.span.1, t.span.1),
+            None => (0, 0),
         }
+    }
 
-        Ok(self.nodes.clone())
+    /// span of the next token, or of the end of input
+    fn peek_span(&self) -> Span {
+        match self.peek() {
+            Some(t) => t.span,
+            None => self.end_span(),
+        }
     }
 
-    fn until_rparan(&self) -> Option<Vec<Token>> {
-        for (idx, i) in self.tokens.clone().into_iter().enumerate() {
-            if i.token_type == TokenType::RParen {
-                return Some(self.tokens[0..idx].to_vec());
-            }
+    /// span of the last consumed token
+    fn previous_span(&self) -> Span {
+        match self.peek_previous() {
+            Some(t) => t.span,
+            None => (0, 0),
         }
+    }
 
-        None
+    fn check(&self, token_type: &TokenType) -> bool {
+        match self.peek() {
+            Some(t) => &t.token_type == token_type,
+            None => false,
+        }
     }
 
-    fn next_rparan_idx(&self) -> Option<usize> {
-        for (idx, i) in self.tokens.clone().into_iter().enumerate() {
-            if i.token_type == TokenType::RParen {
-                return Some(idx);
-            }
+    /// consumes the next token if it is of `token_type`, erroring otherwise
+    fn expect(&mut self, token_type: TokenType) -> Result<Token<'a>, ParseError> {
+        if self.check(&token_type) {
+            self.next()
+        } else {
+            Err(self.unexpected())
         }
+    }
 
-        None
+    /// error for the token at the current position
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(t) if t.token_type != TokenType::EOI => ParseError::UnexpectedToken(t.span),
+            _ => ParseError::UnexpectedTermination(self.peek_span()),
+        }
     }
 
-    fn is_op(tok: Token) -> bool {
-        match tok.token_type {
-            TokenType::Plus | TokenType::Minus | TokenType::Asterisk | TokenType::Slash => true,
-            // TokenType::RParen
-            _ => false,
+    fn parse(&mut self) -> Result<Vec<Spanned<Node>>, ParseError> {
+        while !self.at_end() {
+            let n = self.statement()?;
+            self.nodes.push(n);
         }
+
+        Ok(std::mem::take(&mut self.nodes))
     }
 
-    fn tok_to_op(tok: Token) -> Option<Op> {
+    fn tok_to_op(tok: &Token) -> Option<Op> {
         match tok.token_type {
             TokenType::Plus => Some(Op::Add),
             TokenType::Minus => Some(Op::Subtract),
             TokenType::Asterisk => Some(Op::Multiply),
             TokenType::Slash => Some(Op::Divide),
+            TokenType::ComparisonEqual => Some(Op::Equal),
+            TokenType::NotEqual => Some(Op::NotEqual),
+            TokenType::StrictComparisonEqual => Some(Op::StrictEqual),
+            TokenType::NotStrictEqual => Some(Op::NotStrictEqual),
+            TokenType::Greater => Some(Op::Greater),
+            TokenType::GreaterThanEqualTo => Some(Op::GreaterEqual),
+            TokenType::Less => Some(Op::Less),
+            TokenType::LessThanEqualTo => Some(Op::LessEqual),
+            TokenType::And => Some(Op::And),
+            TokenType::Or => Some(Op::Or),
             _ => None,
         }
     }
 
-    fn expr_op(toks: Vec<Token>) -> Result<Node, ParseError> {
-        for (idx, i) in toks.clone().into_iter().enumerate() {
-            if Parser::is_op(i.clone()) {
-                let lhs = toks.get(idx - 1).unwrap().to_owned();
-                let rhs = toks.get(idx + 1).unwrap().to_owned();
+    /// consumes the `;` ending a statement, which may be left off before `}` or the end of input
+    fn end_of_statement(&mut self) -> Result<(), ParseError> {
+        if self.check(&TokenType::Semicolon) {
+            self.next()?;
+        } else if !self.check(&TokenType::RBrace) && !self.at_end() {
+            return Err(self.missing_semicolon());
+        }
 
-                // if Parser::is_op(lhs.to_owned()) == false
-                //     || Parser::is_op(rhs.to_owned()) == false
-                // {
-                //     let next_rparen = self.until_rparan().unwrap().len();
-                //     Parser::op(toks[idx..next_rparen].to_vec());
-                // }
-                let l = match lhs.token_type {
-                    TokenType::Number(n) => Node::Number(n),
-                    TokenType::Identifier(i) => Node::Identifier(i),
-                    _ => return Err(ParseError::UnexpectedToken),
-                };
+        Ok(())
+    }
+
+    /// error for a `;` that should come straight after the last consumed token
+    fn missing_semicolon(&self) -> ParseError {
+        let end = self.previous_span().1;
+        let line_ends = match self.peek() {
+            Some(next) => next.source[end..next.span.0].contains('\n'),
+            None => true,
+        };
+
+        ParseError::MissingSemicolon {
+            span: (end, end),
+            line_ends,
+        }
+    }
 
-                let r = match rhs.token_type {
-                    TokenType::Number(n) => Node::Number(n),
-                    TokenType::Identifier(i) => Node::Identifier(i),
-                    _ => return Err(ParseError::UnexpectedToken),
+    fn statement(&mut self) -> Result<Spanned<Node>, ParseError> {
+        self.is_ended()?;
+
+        match self.tokens[self.index].token_type {
+            TokenType::Let | TokenType::Const => {
+                let variable = self.variable()?;
+                self.end_of_statement()?;
+                Ok(variable)
+            }
+            TokenType::Fn if self.is_declaration() => self.function(),
+            TokenType::Struct => self.structure(),
+            TokenType::Enum => self.enumeration(),
+            TokenType::Hash if self.is_attribute() => self.attribute(),
+            TokenType::Pub => match self.tokens.get(self.index + 1).map(|t| &t.token_type) {
+                Some(TokenType::Let) | Some(TokenType::Const) => {
+                    let variable = self.variable()?;
+                    self.end_of_statement()?;
+                    Ok(variable)
+                }
+                Some(TokenType::Fn) => self.function(),
+                Some(TokenType::Struct) => self.structure(),
+                Some(TokenType::Enum) => self.enumeration(),
+                _ => {
+                    self.next()?;
+                    Err(self.unexpected())
+                }
+            },
+            TokenType::Import => {
+                let import = self.import()?;
+                self.end_of_statement()?;
+                Ok(import)
+            }
+            TokenType::Return => {
+                let start = self.next()?.span;
+                let value = if self.check(&TokenType::Semicolon)
+                    || self.check(&TokenType::RBrace)
+                    || self.at_end()
+                {
+                    None
+                } else {
+                    Some(Box::new(self.expr()?))
                 };
 
-                return Ok(Node::BinaryExpr {
-                    operation: Parser::tok_to_op(i).unwrap(),
-                    lhs: Box::new(l),
-                    rhs: Box::new(r),
-                });
+                let span = (start.0, self.previous_span().1);
+                self.end_of_statement()?;
+                Ok(Spanned::new(Node::Return(value), span))
+            }
+            _ => {
+                let expr = self.expr()?;
+                self.end_of_statement()
+                    .map_err(|err| Parser::misspelled_keyword(&expr).unwrap_or(err))?;
+                Ok(expr)
+            }
+        }
+    }
+
+    /// an error for a statement that's only a name, followed by more on the same statement, when
+    /// that name is close to a keyword, as in `retrun x;`
+    fn misspelled_keyword(expr: &Spanned<Node>) -> Option<ParseError> {
+        match &expr.node {
+            Node::Identifier(name) => Some(ParseError::MisspelledKeyword {
+                span: expr.span,
+                keyword: closest_keyword(name)?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// consumes an optional `pub`, returning whether it was there
+    fn visibility(&mut self) -> Result<bool, ParseError> {
+        if self.check(&TokenType::Pub) {
+            self.next()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// `let pattern = value` or `const pattern: Type = value`
+    fn variable(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.peek_span();
+        let public = self.visibility()?;
+        let keyword = self.next()?;
+        let variable_type = match keyword.token_type {
+            TokenType::Const => Var::Const,
+            _ => Var::Let,
+        };
+
+        let pattern = self.irrefutable_pattern()?;
+        let ty = self.annotation()?;
+        self.expect(TokenType::Equal)?;
+        let value = self.expr()?;
+
+        let span = (start.0, value.span.1);
+        Ok(Spanned::new(
+            Node::Variable {
+                public,
+                variable_type,
+                pattern,
+                ty,
+                value: Box::new(value),
+            },
+            span,
+        ))
+    }
+
+    /// `fn name(args) { body }`, where each argument and the return type may be annotated
+    fn function(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.peek_span();
+        let public = self.visibility()?;
+        self.expect(TokenType::Fn)?;
+        let name = self.identifier()?;
+
+        self.expect(TokenType::LParen)?;
+        let args = self.params(TokenType::RParen)?;
+        let ret = self.return_type()?;
+        let body = self.block()?;
+
+        let span = (start.0, body.span.1);
+        Ok(Spanned::new(
+            Node::Fn {
+                public,
+                name: Box::new(name),
+                args,
+                ret,
+                body: Box::new(body),
+            },
+            span,
+        ))
+    }
+
+    /// true if the `fn` at the current position names a function rather than starting a closure
+    fn is_declaration(&self) -> bool {
+        matches!(
+            self.tokens.get(self.index + 1).map(|t| &t.token_type),
+            Some(TokenType::Identifier(_))
+        )
+    }
+
+    /// true if the `#` at the current position starts an attribute rather than a map
+    fn is_attribute(&self) -> bool {
+        matches!(
+            self.tokens.get(self.index + 1).map(|t| &t.token_type),
+            Some(TokenType::LBracket) | Some(TokenType::Not)
+        )
+    }
+
+    /// `#[name]` or `#[name(arg, ...)]`, with a `!` after the `#` for an inner attribute
+    fn attribute(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.expect(TokenType::Hash)?.span;
+        let inner = self.check(&TokenType::Not);
+        if inner {
+            self.next()?;
+        }
+        self.expect(TokenType::LBracket)?;
+        let name = self.word()?;
+
+        let mut args = Vec::new();
+        if self.check(&TokenType::LParen) {
+            self.next()?;
+            while !self.check(&TokenType::RParen) {
+                args.push(self.word()?);
+                if !self.check(&TokenType::RParen) {
+                    self.expect(TokenType::Comma)?;
+                }
             }
+            self.next()?;
         }
 
-        Err(ParseError::UnexpectedToken)
+        let end = self.expect(TokenType::RBracket)?.span;
+        Ok(Spanned::new(
+            Node::Attribute { inner, name, args },
+            (start.0, end.1),
+        ))
     }
 
-    fn op(&self) -> Result<Node, ParseError> {
-        let toks = self.tokens[self.index..self.next_rparan_idx().unwrap()].to_vec();
-        for (idx, i) in toks.into_iter().enumerate() {
-            match i.token_type {
-                TokenType::LParen => {
-                    Parser::expr_op(self.until_rparan().unwrap());
+    /// an identifier as a plain name, for places that don't refer to a binding
+    fn word(&mut self) -> Result<Spanned<String>, ParseError> {
+        let name = self.identifier()?;
+        match name.node {
+            Node::Identifier(word) => Ok(Spanned::new(word, name.span)),
+            _ => unreachable!("identifier() only returns identifiers"),
+        }
+    }
+
+    /// parameters up to and including `close`, each optionally annotated
+    fn params(&mut self, close: TokenType) -> Result<Vec<Param>, ParseError> {
+        let mut params = Vec::new();
+        while !self.check(&close) {
+            let pattern = self.irrefutable_pattern()?;
+            let ty = self.annotation()?;
+            params.push(Param { pattern, ty });
+
+            if !self.check(&close) {
+                self.expect(TokenType::Comma)?;
+            }
+        }
+        self.expect(close)?;
+
+        Ok(params)
+    }
+
+    /// a pattern that always matches, as bound by `let` and parameters
+    fn irrefutable_pattern(&mut self) -> Result<Spanned<Pattern>, ParseError> {
+        let pattern = self.pattern()?;
+        match pattern.refutable() {
+            Some(span) => Err(ParseError::RefutablePattern(span)),
+            None => Ok(pattern),
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Spanned<Pattern>, ParseError> {
+        if self.at_end() {
+            return Err(self.unexpected());
+        }
+        if self.check(&TokenType::LBrace) {
+            let start = self.peek_span();
+            return self.struct_pattern(None, start);
+        }
+
+        let token = self.next()?;
+        let node = match token.token_type {
+            TokenType::Identifier(name) if name == "_" => Pattern::Wildcard,
+            TokenType::Identifier(name) if self.check(&TokenType::LBrace) => {
+                return self.struct_pattern(Some(name), token.span)
+            }
+            TokenType::Identifier(name) if self.check(&TokenType::PathSeparator) => {
+                return self.variant_pattern(name, token.span)
+            }
+            TokenType::Identifier(name) => Pattern::Identifier(name),
+            TokenType::Number(n) => Pattern::Number(n),
+            TokenType::Minus => match self.peek().map(|t| &t.token_type) {
+                Some(TokenType::Number(n)) => {
+                    let n = *n;
+                    let end = self.next()?.span;
+                    return Ok(Spanned::new(Pattern::Number(-n), (token.span.0, end.1)));
+                }
+                _ => return Err(self.unexpected()),
+            },
+            TokenType::String(s) => Pattern::String(s),
+            TokenType::True => Pattern::Boolean(true),
+            TokenType::False => Pattern::Boolean(false),
+            TokenType::LParen => {
+                let mut items = Vec::new();
+                let mut trailing_comma = false;
+                while !self.check(&TokenType::RParen) {
+                    items.push(self.pattern()?);
+
+                    trailing_comma = self.check(&TokenType::Comma);
+                    if !self.check(&TokenType::RParen) {
+                        self.expect(TokenType::Comma)?;
+                    }
+                }
+                let end = self.expect(TokenType::RParen)?.span;
+
+                // without a comma, parentheses only group
+                if items.len() == 1 && !trailing_comma {
+                    let inner = items.pop().unwrap();
+                    return Ok(Spanned::new(inner.node, (token.span.0, end.1)));
                 }
-                _ => return Err(ParseError::UnexpectedToken),
+
+                return Ok(Spanned::new(Pattern::Tuple(items), (token.span.0, end.1)));
+            }
+            TokenType::LBracket => {
+                let mut items = Vec::new();
+                let mut rest = None;
+                while !self.check(&TokenType::RBracket) {
+                    if self.check(&TokenType::Range) {
+                        let dots = self.next()?.span;
+                        let pattern =
+                            if self.check(&TokenType::RBracket) || self.check(&TokenType::Comma) {
+                                Spanned::new(Pattern::Wildcard, dots)
+                            } else {
+                                self.pattern()?
+                            };
+                        rest = Some(Box::new(pattern));
+
+                        // the rest of the list can only come last
+                        if self.check(&TokenType::Comma) {
+                            self.next()?;
+                        }
+                        break;
+                    }
+
+                    items.push(self.pattern()?);
+                    if !self.check(&TokenType::RBracket) {
+                        self.expect(TokenType::Comma)?;
+                    }
+                }
+                let end = self.expect(TokenType::RBracket)?.span;
+
+                return Ok(Spanned::new(
+                    Pattern::List { items, rest },
+                    (token.span.0, end.1),
+                ));
+            }
+            _ => return Err(ParseError::UnexpectedToken(token.span)),
+        };
+
+        Ok(Spanned::new(node, token.span))
+    }
+
+    /// `{ field, field: pattern }`, after any struct name
+    fn struct_pattern(
+        &mut self,
+        name: Option<String>,
+        start: Span,
+    ) -> Result<Spanned<Pattern>, ParseError> {
+        self.expect(TokenType::LBrace)?;
+
+        let mut fields = Vec::new();
+        while !self.check(&TokenType::RBrace) {
+            let field = self.identifier()?;
+            let name = match field.node {
+                Node::Identifier(name) => name,
+                _ => unreachable!("identifier() only returns identifiers"),
+            };
+
+            let pattern = if self.check(&TokenType::Colon) {
+                self.next()?;
+                self.pattern()?
+            } else {
+                Spanned::new(Pattern::Identifier(name.clone()), field.span)
+            };
+            fields.push(FieldPattern { name, pattern });
+
+            if !self.check(&TokenType::RBrace) {
+                self.expect(TokenType::Comma)?;
             }
         }
+        let end = self.expect(TokenType::RBrace)?.span;
 
-        Err(ParseError::UnexpectedToken)
+        Ok(Spanned::new(
+            Pattern::Struct { name, fields },
+            (start.0, end.1),
+        ))
     }
 
-    fn expr(&mut self) -> Result<Node, ParseError> {
-        self.is_ended()?;
+    /// `::Variant` or `::Variant(pattern, ...)`, after the enum's name
+    fn variant_pattern(
+        &mut self,
+        name: String,
+        start: Span,
+    ) -> Result<Spanned<Pattern>, ParseError> {
+        self.expect(TokenType::PathSeparator)?;
+        let variant = self.word()?;
+
+        let mut fields = Vec::new();
+        if self.check(&TokenType::LParen) {
+            self.next()?;
+            while !self.check(&TokenType::RParen) {
+                fields.push(self.pattern()?);
+                if !self.check(&TokenType::RParen) {
+                    self.expect(TokenType::Comma)?;
+                }
+            }
+            self.next()?;
+        }
+
+        Ok(Spanned::new(
+            Pattern::Variant {
+                name,
+                variant: variant.node,
+                fields,
+            },
+            (start.0, self.previous_span().1),
+        ))
+    }
 
+    /// an optional `-> Type`
+    fn return_type(&mut self) -> Result<Option<Spanned<TypeExpr>>, ParseError> {
+        if self.check(&TokenType::Arrow) {
+            self.next()?;
+            Ok(Some(self.type_expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// `fn(args) -> Type { body }`, or `|args| body` where the body is a block or an expression,
+    /// after the opening `fn` or `|`
+    fn closure(&mut self, start: Span, arrow: bool) -> Result<Spanned<Node>, ParseError> {
+        let (args, ret, body) = if arrow {
+            let args = self.params(TokenType::OrPipe)?;
+            let body = if self.check(&TokenType::LBrace) {
+                self.block()?
+            } else {
+                self.expr()?
+            };
+            (args, None, body)
+        } else {
+            self.expect(TokenType::LParen)?;
+            let args = self.params(TokenType::RParen)?;
+            (args, self.return_type()?, self.block()?)
+        };
+
+        let span = (start.0, body.span.1);
+        Ok(Spanned::new(
+            Node::Closure {
+                args,
+                ret,
+                body: Box::new(body),
+            },
+            span,
+        ))
+    }
+
+    /// `struct Name { field: Type, ... }`
+    fn structure(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.peek_span();
+        let public = self.visibility()?;
+        self.expect(TokenType::Struct)?;
+        let name = self.identifier()?;
+
+        self.expect(TokenType::LBrace)?;
+        let mut fields = Vec::new();
+        while !self.check(&TokenType::RBrace) {
+            let name = self.identifier()?;
+            let ty = self.annotation()?;
+            fields.push(Field { name, ty });
+
+            if !self.check(&TokenType::RBrace) {
+                self.expect(TokenType::Comma)?;
+            }
+        }
+        let end = self.expect(TokenType::RBrace)?.span;
+
+        Ok(Spanned::new(
+            Node::Struct {
+                public,
+                name: Box::new(name),
+                fields,
+            },
+            (start.0, end.1),
+        ))
+    }
+
+    /// `enum Name { Variant, Variant(Type, ...), ... }`
+    fn enumeration(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.peek_span();
+        let public = self.visibility()?;
+        self.expect(TokenType::Enum)?;
+        let name = self.identifier()?;
+
+        self.expect(TokenType::LBrace)?;
+        let mut variants = Vec::new();
+        while !self.check(&TokenType::RBrace) {
+            let name = self.identifier()?;
+            let mut fields = Vec::new();
+            if self.check(&TokenType::LParen) {
+                self.next()?;
+                while !self.check(&TokenType::RParen) {
+                    fields.push(self.type_expr()?);
+                    if !self.check(&TokenType::RParen) {
+                        self.expect(TokenType::Comma)?;
+                    }
+                }
+                self.next()?;
+            }
+            variants.push(Variant { name, fields });
+
+            if !self.check(&TokenType::RBrace) {
+                self.expect(TokenType::Comma)?;
+            }
+        }
+        let end = self.expect(TokenType::RBrace)?.span;
+
+        Ok(Spanned::new(
+            Node::Enum {
+                public,
+                name: Box::new(name),
+                variants,
+            },
+            (start.0, end.1),
+        ))
+    }
+
+    /// `import "path/to/mod"` or `import std.math`, followed by `as alias` or `{ a, b }`
+    fn import(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.expect(TokenType::Import)?.span;
+
+        let path = match self.peek().map(|t| (&t.token_type, t.span)) {
+            Some((TokenType::String(file), span)) => {
+                let path = Spanned::new(ModulePath::File(file.clone()), span);
+                self.next()?;
+                path
+            }
+            Some((TokenType::Identifier(_), span)) => {
+                let mut segments = Vec::new();
+                let end = loop {
+                    let segment = self.identifier()?;
+                    if let Node::Identifier(name) = segment.node {
+                        segments.push(name);
+                    }
+
+                    if !self.check(&TokenType::Period) {
+                        break segment.span;
+                    }
+                    self.next()?;
+                };
+
+                Spanned::new(ModulePath::Dotted(segments), (span.0, end.1))
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        let mut alias = None;
+        let mut items = Vec::new();
+        if self.check(&TokenType::As) {
+            self.next()?;
+            alias = Some(Box::new(self.identifier()?));
+        } else if self.check(&TokenType::LBrace) {
+            self.next()?;
+            while !self.check(&TokenType::RBrace) {
+                items.push(self.identifier()?);
+
+                if !self.check(&TokenType::RBrace) {
+                    self.expect(TokenType::Comma)?;
+                }
+            }
+            self.expect(TokenType::RBrace)?;
+        }
+
+        Ok(Spanned::new(
+            Node::Import { path, alias, items },
+            (start.0, self.previous_span().1),
+        ))
+    }
+
+    /// an optional `: Type`
+    fn annotation(&mut self) -> Result<Option<Spanned<TypeExpr>>, ParseError> {
+        if self.check(&TokenType::Colon) {
+            self.next()?;
+            Ok(Some(self.type_expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// splits a `>=` token into `>` and `=`, as in `let x: List<Number>= xs`
+    fn split_greater_equal(&mut self) {
         let token = self.tokens[self.index].clone();
-        self.index += 1;
+        let (start, end) = token.span;
+
+        self.tokens[self.index] = Token {
+            token_type: TokenType::Equal,
+            span: (start + 1, end),
+            ..token.clone()
+        };
+        self.tokens.insert(
+            self.index,
+            Token {
+                token_type: TokenType::Greater,
+                span: (start, start + 1),
+                ..token
+            },
+        );
+    }
+
+    /// `Name`, `Name<Type, ...>` or `fn(Type, ...) -> Type`
+    fn type_expr(&mut self) -> Result<Spanned<TypeExpr>, ParseError> {
+        if self.check(&TokenType::Fn) {
+            let start = self.next()?.span;
+
+            self.expect(TokenType::LParen)?;
+            let mut params = Vec::new();
+            while !self.check(&TokenType::RParen) {
+                params.push(self.type_expr()?);
+
+                if !self.check(&TokenType::RParen) {
+                    self.expect(TokenType::Comma)?;
+                }
+            }
+            let mut end = self.expect(TokenType::RParen)?.span;
+
+            let ret = if self.check(&TokenType::Arrow) {
+                self.next()?;
+                let ret = self.type_expr()?;
+                end = ret.span;
+                Some(Box::new(ret))
+            } else {
+                None
+            };
+
+            return Ok(Spanned::new(TypeExpr::Fn { params, ret }, (start.0, end.1)));
+        }
+
+        let (name, start) = match self.peek() {
+            Some(Token {
+                token_type: TokenType::Identifier(name),
+                span,
+                ..
+            }) => (name.clone(), *span),
+            _ => return Err(ParseError::InvalidType(self.peek_span())),
+        };
+        self.next()?;
+
+        let mut generics = Vec::new();
+        let mut end = start;
+        if self.check(&TokenType::Less) {
+            self.next()?;
+            while !self.check(&TokenType::Greater) {
+                if self.check(&TokenType::GreaterThanEqualTo) {
+                    self.split_greater_equal();
+                    continue;
+                }
+
+                generics.push(self.type_expr()?);
+
+                if !self.check(&TokenType::Greater) && !self.check(&TokenType::GreaterThanEqualTo) {
+                    self.expect(TokenType::Comma)?;
+                }
+            }
+            end = self.expect(TokenType::Greater)?.span;
+        }
+
+        Ok(Spanned::new(
+            TypeExpr::Named { name, generics },
+            (start.0, end.1),
+        ))
+    }
+
+    fn block(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let start = self.expect(TokenType::LBrace)?.span;
+
+        let mut body = Vec::new();
+        let mut value = None;
+        while !self.check(&TokenType::RBrace) {
+            if self.at_end() {
+                return Err(self.unexpected());
+            }
+
+            match self.tokens[self.index].token_type {
+                TokenType::Fn if self.is_declaration() => body.push(self.statement()?),
+                TokenType::Hash if self.is_attribute() => body.push(self.attribute()?),
+                TokenType::Let
+                | TokenType::Const
+                | TokenType::Struct
+                | TokenType::Enum
+                | TokenType::Return => body.push(self.statement()?),
+                _ => {
+                    let expr = self.expr()?;
+                    if self.check(&TokenType::RBrace) {
+                        value = Some(Box::new(expr));
+                    } else if self.check(&TokenType::Semicolon) {
+                        self.next()?;
+                        body.push(expr);
+                    } else if self.at_end() {
+                        return Err(self.unexpected());
+                    } else {
+                        let err = self.missing_semicolon();
+                        return Err(Parser::misspelled_keyword(&expr).unwrap_or(err));
+                    }
+                }
+            }
+        }
+
+        let end = self.expect(TokenType::RBrace)?.span;
+        Ok(Spanned::new(Node::Block { body, value }, (start.0, end.1)))
+    }
+
+    fn identifier(&mut self) -> Result<Spanned<Node>, ParseError> {
+        if let Some(Token {
+            token_type: TokenType::Identifier(i),
+            span,
+            ..
+        }) = self.peek()
+        {
+            let node = Spanned::new(Node::Identifier(i.clone()), *span);
+            self.next()?;
+            Ok(node)
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expr(&mut self) -> Result<Spanned<Node>, ParseError> {
+        self.assignment()
+    }
+
+    /// `target = value` or `target += value`, right associative, where the target is a name or
+    /// an index
+    fn assignment(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let target = self.binary(0)?;
+        let operation = match self.peek().map(|t| &t.token_type) {
+            Some(TokenType::Equal) => None,
+            Some(TokenType::PlusEqual) => Some(Op::Add),
+            Some(TokenType::MinusEqual) => Some(Op::Subtract),
+            Some(TokenType::AsteriskEqual) => Some(Op::Multiply),
+            Some(TokenType::SlashEqual) => Some(Op::Divide),
+            _ => return Ok(target),
+        };
+
+        if !matches!(target.node, Node::Identifier(_) | Node::Index { .. }) {
+            return Err(ParseError::InvalidAssignment(target.span));
+        }
+
+        self.next()?;
+        let value = self.assignment()?;
+
+        let span = (target.span.0, value.span.1);
+        Ok(Spanned::new(
+            Node::Assign {
+                operation,
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+            span,
+        ))
+    }
+
+    /// parses operators binding at least as tightly as `precedence`
+    fn binary(&mut self, precedence: u8) -> Result<Spanned<Node>, ParseError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(operation) = self.peek().and_then(Parser::tok_to_op) {
+            let op_precedence = operation.precedence();
+            if op_precedence < precedence {
+                break;
+            }
+
+            self.next()?;
+            let rhs = self.binary(op_precedence + 1)?;
+
+            let span = (lhs.span.0, rhs.span.1);
+            lhs = Spanned::new(
+                Node::BinaryExpr {
+                    operation,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Spanned<Node>, ParseError> {
+        if self.check(&TokenType::Not) || self.check(&TokenType::Minus) {
+            let operation = self.next()?;
+            let body = self.unary()?;
+
+            let span = (operation.span.0, body.span.1);
+            return Ok(Spanned::new(
+                Node::UnaryExpr {
+                    operation: operation.token_type,
+                    body: Box::new(body),
+                },
+                span,
+            ));
+        }
+
+        self.call()
+    }
+
+    /// calls, indexing and slicing, in any order
+    fn call(&mut self) -> Result<Spanned<Node>, ParseError> {
+        let mut target = self.primary()?;
+
+        loop {
+            if self.check(&TokenType::LParen) {
+                self.next()?;
+                let args = self.items(TokenType::RParen)?;
+
+                let span = (target.span.0, self.previous_span().1);
+                target = Spanned::new(
+                    Node::FnCall {
+                        function: Box::new(target),
+                        args,
+                    },
+                    span,
+                );
+            } else if self.check(&TokenType::LBracket) {
+                self.next()?;
+                target = self.index(target)?;
+            } else {
+                return Ok(target);
+            }
+        }
+    }
+
+    /// `[index]` or `[start..end]`, after the opening bracket
+    fn index(&mut self, target: Spanned<Node>) -> Result<Spanned<Node>, ParseError> {
+        let target_start = target.span.0;
+        let start = if self.check(&TokenType::Range) {
+            None
+        } else {
+            Some(Box::new(self.expr()?))
+        };
+
+        let node = match start {
+            Some(index) if !self.check(&TokenType::Range) => Node::Index {
+                target: Box::new(target),
+                index,
+            },
+            start => {
+                self.expect(TokenType::Range)?;
+                let end = if self.check(&TokenType::RBracket) {
+                    None
+                } else {
+                    Some(Box::new(self.expr()?))
+                };
+
+                Node::Slice {
+                    target: Box::new(target),
+                    start,
+                    end,
+                }
+            }
+        };
+        let end = self.expect(TokenType::RBracket)?.span;
 
-        match token.token_type.clone() {
-            TokenType::LParen => {}
-            TokenType::LBrace => {}
-            TokenType::Comma => {}
-            TokenType::Period => {}
-            TokenType::Plus => {}
-            TokenType::Minus => {}
-            TokenType::Asterisk => {}
-            TokenType::Slash => {}
-            TokenType::Semicolon => {}
-            TokenType::NotEqual => {}
-            TokenType::NotStrictEqual => {}
-            TokenType::ComparisonEqual => {}
-            TokenType::Equal => {}
-            TokenType::StrictComparisonEqual => {}
-            TokenType::Greater
-            | TokenType::GreaterThanEqualTo
-            | TokenType::Less
-            | TokenType::LessThanEqualTo => {
-                // let lhs = self.peek_previous().unwrap().to_owned();
-                // let expr = token;
-                // let rhs = self.peek().unwrap().to_owned();
-            }
-            TokenType::Not => {}
-            TokenType::String(s) => return Ok(Node::String(s)),
-            TokenType::Name => {}
-            TokenType::Number(n) => return Ok(Node::Number(n)),
-            TokenType::Identifier(_) => {}
-            TokenType::And => {}
-            TokenType::AndAmpersand => {}
-            TokenType::Or => {}
-            TokenType::OrPipe => {}
-            TokenType::Match => {}
-            TokenType::Enum => {}
-            TokenType::Struct => {}
-            TokenType::Let => {
-                let declaration = self.peek_end_of_statement();
-                let identifier = declaration.get(0).unwrap().source;
-                let value = declaration[2..].to_vec();
-
-                return Ok(Node::Variable {
-                    variable_type: Var::Let,
-                    identifier: Box::new(Node::Identifier(identifier.to_string())),
-                    value: Box::new(self.op().unwrap()),
-                });
-            }
-            TokenType::Const => {}
-            TokenType::True => return Ok(Node::Boolean(true)),
-            TokenType::False => return Ok(Node::Boolean(false)),
-            TokenType::Fn => {}
-            TokenType::Return => {}
-            TokenType::EOI => {}
-            _ => return Err(ParseError::UnexpectedToken),
-        }
-
-        Err(ParseError::UnexpectedTermination)
+        Ok(Spanned::new(node, (target_start, end.1)))
+    }
+
+    /// comma separated expressions up to and including `close`
+    fn items(&mut self, close: TokenType) -> Result<Vec<Spanned<Node>>, ParseError> {
+        let mut items = Vec::new();
+        while !self.check(&close) {
+            items.push(self.expr()?);
+
+            if !self.check(&close) {
+                self.expect(TokenType::Comma)?;
+            }
+        }
+        self.expect(close)?;
+
+        Ok(items)
+    }
+
+    /// `#{ key: value, ... }`, after the `#`
+    fn map(&mut self, start: Span) -> Result<Spanned<Node>, ParseError> {
+        self.expect(TokenType::LBrace)?;
+
+        let mut entries = Vec::new();
+        while !self.check(&TokenType::RBrace) {
+            let key = self.expr()?;
+            self.expect(TokenType::Colon)?;
+            let value = self.expr()?;
+            entries.push(MapEntry { key, value });
+
+            if !self.check(&TokenType::RBrace) {
+                self.expect(TokenType::Comma)?;
+            }
+        }
+        let end = self.expect(TokenType::RBrace)?.span;
+
+        Ok(Spanned::new(Node::Map(entries), (start.0, end.1)))
+    }
+
+    /// `match value { pattern => value, ... }`, after the `match`
+    fn matching(&mut self, start: Span) -> Result<Spanned<Node>, ParseError> {
+        let value = self.expr()?;
+
+        self.expect(TokenType::LBrace)?;
+        let mut arms = Vec::new();
+        while !self.check(&TokenType::RBrace) {
+            let pattern = self.pattern()?;
+            self.expect(TokenType::FatArrow)?;
+            let value = self.expr()?;
+            arms.push(Arm { pattern, value });
+
+            if !self.check(&TokenType::RBrace) {
+                self.expect(TokenType::Comma)?;
+            }
+        }
+        let end = self.expect(TokenType::RBrace)?.span;
+
+        Ok(Spanned::new(
+            Node::Match {
+                value: Box::new(value),
+                arms,
+            },
+            (start.0, end.1),
+        ))
+    }
+
+    fn primary(&mut self) -> Result<Spanned<Node>, ParseError> {
+        if self.at_end() {
+            return Err(self.unexpected());
+        }
+
+        let token = self.next()?;
+        let node = match token.token_type {
+            TokenType::Number(n) => Node::Number(n),
+            TokenType::String(s) => Node::String(s),
+            TokenType::True => Node::Boolean(true),
+            TokenType::False => Node::Boolean(false),
+            TokenType::Identifier(i) if self.check(&TokenType::PathSeparator) => {
+                self.next()?;
+                let variant = self.word()?;
+                let span = (token.span.0, variant.span.1);
+                return Ok(Spanned::new(
+                    Node::Path {
+                        name: Box::new(Spanned::new(Node::Identifier(i), token.span)),
+                        variant,
+                    },
+                    span,
+                ));
+            }
+            TokenType::Identifier(i) => Node::Identifier(i),
+            TokenType::Match => return self.matching(token.span),
+            TokenType::Fn => return self.closure(token.span, false),
+            TokenType::OrPipe => return self.closure(token.span, true),
+            TokenType::LBracket => {
+                let items = self.items(TokenType::RBracket)?;
+                return Ok(Spanned::new(
+                    Node::List(items),
+                    (token.span.0, self.previous_span().1),
+                ));
+            }
+            TokenType::Hash => return self.map(token.span),
+            TokenType::LParen => {
+                if self.check(&TokenType::RParen) {
+                    let end = self.next()?.span;
+                    return Ok(Spanned::new(Node::Tuple(Vec::new()), (token.span.0, end.1)));
+                }
+
+                let inner = self.expr()?;
+                if self.check(&TokenType::Comma) {
+                    self.next()?;
+                    let mut items = vec![inner];
+                    items.extend(self.items(TokenType::RParen)?);
+
+                    let span = (token.span.0, self.previous_span().1);
+                    return Ok(Spanned::new(Node::Tuple(items), span));
+                }
+                let end = self.expect(TokenType::RParen)?.span;
+
+                // parentheses only group, but the span covers them
+                return Ok(Spanned::new(inner.node, (token.span.0, end.1)));
+            }
+            _ => return Err(ParseError::UnexpectedToken(token.span)),
+        };
+
+        Ok(Spanned::new(node, token.span))
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Vec<Node>, ParseError> {
+pub fn parse(tokens: Vec<Token<'_>>) -> Result<Vec<Spanned<Node>>, ParseError> {
     let filtered: Vec<Token> = tokens
-        .to_owned()
         .into_iter()
-        .filter(|x| match x.token_type {
-            TokenType::Comment
-            | TokenType::DocComment(_)
-            | TokenType::Whitespace
-            | TokenType::Whitespace => false,
-            _ => true,
+        .filter(|x| {
+            !matches!(
+                x.token_type,
+                TokenType::Comment | TokenType::DocComment(_) | TokenType::Whitespace
+            )
         })
         .collect();
 
@@ -255,7 +1129,579 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(unused_variables)]
     fn let_binding_generates_proper_ast() {
         let ast = parse(tokenise("let t = 2+2").unwrap()).unwrap();
     }
+
+    #[test]
+    fn let_binding_spans() {
+        let ast = parse(tokenise("let t = 2+2").unwrap()).unwrap();
+
+        assert_eq!(ast.len(), 1);
+        assert_eq!(ast[0].span, (0, 11));
+
+        if let Node::Variable {
+            variable_type,
+            pattern,
+            value,
+            ..
+        } = &ast[0].node
+        {
+            assert_eq!(*variable_type, Var::Let);
+            assert_eq!(pattern.node, Pattern::Identifier(String::from("t")));
+            assert_eq!(pattern.span, (4, 5));
+            assert_eq!(value.span, (8, 11));
+        } else {
+            panic!("expected a variable");
+        }
+    }
+
+    #[test]
+    fn binary_precedence() {
+        let ast = parse(tokenise("1 + 2 * 3;").unwrap()).unwrap();
+
+        if let Node::BinaryExpr { operation, rhs, .. } = &ast[0].node {
+            assert_eq!(*operation, Op::Add);
+            assert_eq!(rhs.span, (4, 9));
+            assert!(matches!(
+                rhs.node,
+                Node::BinaryExpr {
+                    operation: Op::Multiply,
+                    ..
+                }
+            ));
+        } else {
+            panic!("expected a binary expression");
+        }
+    }
+
+    #[test]
+    fn grouping_spans_include_parentheses() {
+        let ast = parse(tokenise("(1 + 2) * 3").unwrap()).unwrap();
+
+        if let Node::BinaryExpr { lhs, .. } = &ast[0].node {
+            assert_eq!(lhs.span, (0, 7));
+        } else {
+            panic!("expected a binary expression");
+        }
+    }
+
+    #[test]
+    fn function_spans() {
+        let src = "fn add(a, b) {\n    return a + b;\n}\nadd(1, 2);";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        assert_eq!(ast.len(), 2);
+        assert_eq!(ast[0].span, (0, 34));
+
+        if let Node::Fn { args, body, .. } = &ast[0].node {
+            assert_eq!(args[1].pattern.span, (10, 11));
+
+            if let Node::Block { body, value } = &body.node {
+                assert_eq!(body[0].span, (19, 31));
+                assert!(value.is_none());
+            } else {
+                panic!("expected a block");
+            }
+        } else {
+            panic!("expected a function");
+        }
+
+        assert_eq!(ast[1].span, (35, 44));
+    }
+
+    #[test]
+    fn type_annotations() {
+        let src = "let x: List<Number> = 1;\nfn add(a: Number, b) -> fn(Number) -> Number { a }";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        if let Node::Variable { ty: Some(ty), .. } = &ast[0].node {
+            assert_eq!(ty.span, (7, 19));
+            if let TypeExpr::Named { name, generics } = &ty.node {
+                assert_eq!(name, "List");
+                assert_eq!(
+                    generics[0].node,
+                    TypeExpr::Named {
+                        name: String::from("Number"),
+                        generics: Vec::new()
+                    }
+                );
+            } else {
+                panic!("expected a named type");
+            }
+        } else {
+            panic!("expected an annotated variable");
+        }
+
+        if let Node::Fn { args, ret, .. } = &ast[1].node {
+            assert_eq!(args[0].ty.as_ref().unwrap().span, (35, 41));
+            assert!(args[1].ty.is_none());
+
+            let ret = ret.as_ref().unwrap();
+            assert_eq!(ret.span, (49, 69));
+            assert!(
+                matches!(&ret.node, TypeExpr::Fn { params, ret: Some(_) } if params.len() == 1)
+            );
+        } else {
+            panic!("expected a function");
+        }
+    }
+
+    #[test]
+    fn struct_fields() {
+        let ast = parse(tokenise("struct Point { x: Number, y: Number, label }").unwrap()).unwrap();
+
+        assert_eq!(ast[0].span, (0, 44));
+        if let Node::Struct { name, fields, .. } = &ast[0].node {
+            assert_eq!(name.node, Node::Identifier(String::from("Point")));
+            assert_eq!(fields.len(), 3);
+            assert_eq!(fields[1].ty.as_ref().unwrap().span, (29, 35));
+            assert!(fields[2].ty.is_none());
+        } else {
+            panic!("expected a struct");
+        }
+    }
+
+    #[test]
+    fn generic_followed_by_assignment() {
+        let ast = parse(tokenise("let x: List<Number>= 1;").unwrap()).unwrap();
+
+        if let Node::Variable { ty, value, .. } = &ast[0].node {
+            assert_eq!(ty.as_ref().unwrap().span, (7, 19));
+            assert_eq!(value.node, Node::Number(1.0));
+        } else {
+            panic!("expected a variable");
+        }
+    }
+
+    #[test]
+    fn imports() {
+        let src = "import \"util/strings\";\nimport std.math as m;\nimport std.io { read, write }";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        assert_eq!(ast.len(), 3);
+        if let Node::Import { path, alias, items } = &ast[0].node {
+            assert_eq!(path.node, ModulePath::File(String::from("util/strings")));
+            assert_eq!(path.span, (7, 21));
+            assert!(alias.is_none() && items.is_empty());
+        } else {
+            panic!("expected an import");
+        }
+
+        if let Node::Import { path, alias, .. } = &ast[1].node {
+            assert_eq!(
+                path.node,
+                ModulePath::Dotted(vec![String::from("std"), String::from("math")])
+            );
+            assert_eq!(path.span, (30, 38));
+            assert_eq!(alias.as_ref().unwrap().span, (42, 43));
+        } else {
+            panic!("expected an import");
+        }
+
+        assert_eq!(ast[2].span, (45, 74));
+        if let Node::Import { items, .. } = &ast[2].node {
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[1].node, Node::Identifier(String::from("write")));
+        } else {
+            panic!("expected an import");
+        }
+    }
+
+    #[test]
+    fn public_items() {
+        let ast = parse(tokenise("pub fn f() {}\npub const x = 1;\nstruct S {}").unwrap()).unwrap();
+
+        assert_eq!(ast[0].span, (0, 13));
+        assert!(matches!(ast[0].node, Node::Fn { public: true, .. }));
+        assert!(matches!(ast[1].node, Node::Variable { public: true, .. }));
+        assert!(matches!(ast[2].node, Node::Struct { public: false, .. }));
+
+        let err = parse(tokenise("pub 1;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::UnexpectedToken((4, 5)));
+        let err = parse(tokenise("fn f() { pub let x = 1; }").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::UnexpectedToken((9, 12)));
+    }
+
+    #[test]
+    fn closures() {
+        let src = "let double = fn(x: Number) -> Number { x * 2 };\nmap(xs, |x, y| x + y);\n|| 1;";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        if let Node::Variable { value, .. } = &ast[0].node {
+            assert_eq!(value.span, (13, 46));
+            assert!(matches!(
+                &value.node,
+                Node::Closure { args, ret: Some(_), body } if args.len() == 1 && matches!(body.node, Node::Block { .. })
+            ));
+        } else {
+            panic!("expected a variable");
+        }
+
+        if let Node::FnCall { args, .. } = &ast[1].node {
+            assert_eq!(args[1].span, (56, 68));
+            assert!(matches!(
+                &args[1].node,
+                Node::Closure { args, ret: None, body } if args.len() == 2 && matches!(body.node, Node::BinaryExpr { .. })
+            ));
+        } else {
+            panic!("expected a call");
+        }
+
+        assert!(matches!(&ast[2].node, Node::Closure { args, .. } if args.is_empty()));
+    }
+
+    #[test]
+    fn closures_with_block_bodies() {
+        let src = "map(xs, |x| { x + 1 });
+let inc = || { c += 1; c };";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        if let Node::FnCall { args, .. } = &ast[0].node {
+            assert_eq!(args[1].span, (8, 21));
+            assert!(matches!(
+                &args[1].node,
+                Node::Closure { args, body, .. } if args.len() == 1 && matches!(body.node, Node::Block { .. })
+            ));
+        } else {
+            panic!("expected a call");
+        }
+
+        if let Node::Variable { value, .. } = &ast[1].node {
+            assert_eq!(value.span, (34, 50));
+            match &value.node {
+                Node::Closure { args, body, .. } => {
+                    assert!(args.is_empty());
+                    assert!(matches!(
+                        &body.node,
+                        Node::Block { body, value: Some(_) } if body.len() == 1
+                    ));
+                }
+                _ => panic!("expected a closure"),
+            }
+        } else {
+            panic!("expected a variable");
+        }
+    }
+
+    #[test]
+    fn immediately_called_closure() {
+        let ast = parse(tokenise("fn f() { fn(x) { x }(1) }").unwrap()).unwrap();
+
+        if let Node::Fn { body, .. } = &ast[0].node {
+            if let Node::Block {
+                value: Some(value), ..
+            } = &body.node
+            {
+                assert_eq!(value.span, (9, 23));
+                assert!(matches!(value.node, Node::FnCall { .. }));
+                return;
+            }
+        }
+        panic!("expected a call as the block's value");
+    }
+
+    #[test]
+    fn collections() {
+        let ast = parse(tokenise("let m = #{ \"a\": [1, 2,], 3: #{} };\n[]").unwrap()).unwrap();
+
+        if let Node::Variable { value, .. } = &ast[0].node {
+            assert_eq!(value.span, (8, 33));
+            if let Node::Map(entries) = &value.node {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries[0].key.node, Node::String(String::from("a")));
+                assert_eq!(entries[0].value.span, (16, 23));
+                assert!(matches!(&entries[0].value.node, Node::List(items) if items.len() == 2));
+                assert_eq!(entries[1].value.node, Node::Map(Vec::new()));
+            } else {
+                panic!("expected a map");
+            }
+        } else {
+            panic!("expected a variable");
+        }
+
+        assert_eq!(ast[1].node, Node::List(Vec::new()));
+    }
+
+    #[test]
+    fn indexing_and_slicing() {
+        let ast =
+            parse(tokenise("xs[0][1..n]; xs[..2]; f()[1..]; xs[i] = ys[j] = 1").unwrap()).unwrap();
+
+        if let Node::Slice { target, start, end } = &ast[0].node {
+            assert_eq!(ast[0].span, (0, 11));
+            assert_eq!(target.span, (0, 5));
+            assert!(matches!(target.node, Node::Index { .. }));
+            assert_eq!(start.as_ref().unwrap().node, Node::Number(1.0));
+            assert_eq!(end.as_ref().unwrap().span, (9, 10));
+        } else {
+            panic!("expected a slice");
+        }
+
+        assert!(matches!(
+            &ast[1].node,
+            Node::Slice {
+                start: None,
+                end: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &ast[2].node,
+            Node::Slice {
+                start: Some(_),
+                end: None,
+                ..
+            }
+        ));
+
+        if let Node::Assign { target, value, .. } = &ast[3].node {
+            assert_eq!(target.span, (32, 37));
+            assert!(matches!(value.node, Node::Assign { .. }));
+        } else {
+            panic!("expected an assignment");
+        }
+    }
+
+    #[test]
+    fn assignments() {
+        let ast = parse(tokenise("x = y = 1; x -= 2; xs[0] /= 3;").unwrap()).unwrap();
+
+        assert!(matches!(
+            &ast[0].node,
+            Node::Assign { operation: None, value, .. }
+                if matches!(value.node, Node::Assign { operation: None, .. })
+        ));
+        assert!(matches!(
+            &ast[1].node,
+            Node::Assign {
+                operation: Some(Op::Subtract),
+                ..
+            }
+        ));
+        if let Node::Assign {
+            operation, target, ..
+        } = &ast[2].node
+        {
+            assert_eq!(*operation, Some(Op::Divide));
+            assert_eq!(target.span, (19, 24));
+        } else {
+            panic!("expected an assignment");
+        }
+    }
+
+    #[test]
+    fn only_names_and_indexes_are_assignable() {
+        let err = parse(tokenise("f() = 1;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::InvalidAssignment((0, 3)));
+
+        let err = parse(tokenise("a + b += 1;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::InvalidAssignment((0, 5)));
+    }
+
+    #[test]
+    fn tuples() {
+        let ast = parse(tokenise("(1, 2); (1,); (); (1);").unwrap()).unwrap();
+
+        assert!(matches!(&ast[0].node, Node::Tuple(items) if items.len() == 2));
+        assert!(matches!(&ast[1].node, Node::Tuple(items) if items.len() == 1));
+        assert_eq!(ast[1].span, (8, 12));
+        assert!(matches!(&ast[2].node, Node::Tuple(items) if items.is_empty()));
+        assert!(matches!(&ast[3].node, Node::Number(_)));
+    }
+
+    #[test]
+    fn destructuring_patterns() {
+        let src = "let { x, y: [a, ..rest] } = p;
+fn f((a, _), Point { x }) {}";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        if let Node::Variable { pattern, .. } = &ast[0].node {
+            assert_eq!(pattern.span, (4, 25));
+            assert_eq!(
+                pattern.bindings(),
+                vec![("x", (6, 7)), ("a", (13, 14)), ("rest", (18, 22))]
+            );
+
+            if let Pattern::Struct { name, fields } = &pattern.node {
+                assert_eq!(*name, None);
+                assert!(matches!(
+                    &fields[1].pattern.node,
+                    Pattern::List { items, rest: Some(_) } if items.len() == 1
+                ));
+            } else {
+                panic!("expected a struct pattern");
+            }
+        } else {
+            panic!("expected a variable");
+        }
+
+        if let Node::Fn { args, .. } = &ast[1].node {
+            assert!(matches!(&args[0].pattern.node, Pattern::Tuple(items) if items.len() == 2));
+            assert!(matches!(
+                &args[1].pattern.node,
+                Pattern::Struct { name: Some(name), .. } if name == "Point"
+            ));
+        } else {
+            panic!("expected a function");
+        }
+    }
+
+    #[test]
+    fn enums_and_matches() {
+        let src = "enum Shape { Circle(Number), Square(Number, Number), Empty }
+fn area(s) {
+    match s {
+        Shape::Circle(r) => 3 * r * r,
+        Shape::Empty => 0,
+        _ => Shape::Empty,
+    }
+}";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        if let Node::Enum { variants, .. } = &ast[0].node {
+            let fields: Vec<usize> = variants.iter().map(|v| v.fields.len()).collect();
+            assert_eq!(fields, vec![1, 2, 0]);
+            assert_eq!(variants[2].name.span, (53, 58));
+        } else {
+            panic!("expected an enum");
+        }
+
+        if let Node::Fn { body, .. } = &ast[1].node {
+            let Node::Block {
+                value: Some(value), ..
+            } = &body.node
+            else {
+                panic!("expected a block with a value");
+            };
+            if let Node::Match { arms, .. } = &value.node {
+                assert_eq!(arms.len(), 3);
+                assert_eq!(arms[0].pattern.span, (96, 112));
+                assert_eq!(arms[0].pattern.bindings(), vec![("r", (110, 111))]);
+                assert!(matches!(
+                    &arms[1].pattern.node,
+                    Pattern::Variant { variant, fields, .. } if variant == "Empty" && fields.is_empty()
+                ));
+                assert!(matches!(
+                    &arms[2].value.node,
+                    Node::Path { variant, .. } if variant.node == "Empty"
+                ));
+            } else {
+                panic!("expected a match, found {:?}", value.node);
+            }
+        } else {
+            panic!("expected a function");
+        }
+
+        let err = parse(tokenise("let Shape::Empty = s;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::RefutablePattern((4, 16)));
+    }
+
+    #[test]
+    fn refutable_patterns_are_rejected() {
+        let err = parse(tokenise("let [x, -1] = xs;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::RefutablePattern((8, 10)));
+
+        let err = parse(tokenise("fn f({ a: \"a\" }) {}").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::RefutablePattern((10, 13)));
+    }
+
+    #[test]
+    fn rest_pattern_comes_last() {
+        let err = parse(tokenise("let [..rest, x] = xs;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::UnexpectedToken((13, 14)));
+    }
+
+    #[test]
+    fn missing_semicolon_points_past_the_statement() {
+        let err = parse(tokenise("let x = 1\nlet y = 2;").unwrap()).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::MissingSemicolon {
+                span: (9, 9),
+                line_ends: true
+            }
+        );
+        let err = parse(tokenise("fn f() { g() h() }").unwrap()).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::MissingSemicolon {
+                span: (12, 12),
+                line_ends: false
+            }
+        );
+    }
+
+    #[test]
+    fn misspelled_keywords() {
+        let err = parse(tokenise("retrun x;").unwrap()).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::MisspelledKeyword {
+                span: (0, 6),
+                keyword: "return"
+            }
+        );
+        let err = parse(tokenise("fn f(x) { retrun x; }").unwrap()).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::MisspelledKeyword { span: (10, 16), .. }
+        ));
+        let err = parse(tokenise("cosnt x = 1;").unwrap()).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::MisspelledKeyword {
+                keyword: "const",
+                ..
+            }
+        ));
+        let err = parse(tokenise("fucntion add(a, b) { a + b }").unwrap()).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::MisspelledKeyword {
+                span: (0, 8),
+                keyword: "fn"
+            }
+        );
+        let err = parse(tokenise("value x;").unwrap()).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::MissingSemicolon { span: (5, 5), .. }
+        ));
+    }
+
+    #[test]
+    fn attributes() {
+        let src = "#![deny(loose_equality)]\n#[allow(unused_variable, shadowed_binding)]\nlet x = #{};\nfn f() { #[inline] g() }";
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+
+        assert_eq!(ast.len(), 4);
+        assert_eq!(ast[0].span, (0, 24));
+        if let Node::Attribute { inner, name, args } = &ast[1].node {
+            assert!(!inner);
+            assert_eq!(name.node, "allow");
+            let args: Vec<_> = args.iter().map(|a| a.node.as_str()).collect();
+            assert_eq!(args, vec!["unused_variable", "shadowed_binding"]);
+        } else {
+            panic!("expected an attribute, found {:?}", ast[1].node);
+        }
+        assert!(matches!(ast[2].node, Node::Variable { .. }));
+        if let Node::Fn { body, .. } = &ast[3].node {
+            assert!(matches!(&body.node, Node::Block { body, value: Some(_) }
+                if matches!(body[0].node, Node::Attribute { inner: false, .. })));
+        }
+    }
+
+    #[test]
+    fn invalid_type() {
+        let err = parse(tokenise("let x: 1 = 1;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::InvalidType((7, 8)));
+    }
+
+    #[test]
+    fn unexpected_token_span() {
+        let err = parse(tokenise("let = 2;").unwrap()).unwrap_err();
+        assert_eq!(err, ParseError::UnexpectedToken((4, 5)));
+    }
 }
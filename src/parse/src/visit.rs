@@ -0,0 +1,623 @@
+//! Traversal of the AST by reference.
+//!
+//! `visit_node` dispatches each node to the hook for its variant, and every hook walks the node's
+//! children by default, so a pass only overrides the hooks for the nodes it cares about. An
+//! overridden hook calls `walk_children` (or `walk_children_mut`) to keep descending.
+
+use crate::expr::{Node, Pattern, Spanned, TypeExpr};
+
+pub trait Visitor {
+    fn visit_node(&mut self, node: &Spanned<Node>) {
+        walk_node(self, node)
+    }
+
+    fn visit_variable(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_binary_expr(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_unary_expr(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_fn(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_closure(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_fn_call(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_block(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_return(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_struct(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_enum(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_path(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_match(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_import(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_attribute(&mut self, _node: &Spanned<Node>) {}
+
+    fn visit_assign(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_list(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_tuple(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_map(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_index(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_slice(&mut self, node: &Spanned<Node>) {
+        walk_children(self, node)
+    }
+
+    fn visit_number(&mut self, _node: &Spanned<Node>) {}
+
+    fn visit_string(&mut self, _node: &Spanned<Node>) {}
+
+    fn visit_boolean(&mut self, _node: &Spanned<Node>) {}
+
+    fn visit_identifier(&mut self, _node: &Spanned<Node>) {}
+
+    /// called for every pattern bound by a `let`, a parameter or a `match` arm
+    fn visit_pattern(&mut self, _pattern: &Spanned<Pattern>) {}
+
+    /// called for every type annotation
+    fn visit_type_expr(&mut self, _ty: &Spanned<TypeExpr>) {}
+}
+
+/// calls the visitor's hook for the variant of `node`
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &Spanned<Node>) {
+    match &node.node {
+        Node::Variable { .. } => visitor.visit_variable(node),
+        Node::BinaryExpr { .. } => visitor.visit_binary_expr(node),
+        Node::UnaryExpr { .. } => visitor.visit_unary_expr(node),
+        Node::Fn { .. } => visitor.visit_fn(node),
+        Node::Closure { .. } => visitor.visit_closure(node),
+        Node::FnCall { .. } => visitor.visit_fn_call(node),
+        Node::Block { .. } => visitor.visit_block(node),
+        Node::Return(_) => visitor.visit_return(node),
+        Node::Struct { .. } => visitor.visit_struct(node),
+        Node::Enum { .. } => visitor.visit_enum(node),
+        Node::Path { .. } => visitor.visit_path(node),
+        Node::Match { .. } => visitor.visit_match(node),
+        Node::Import { .. } => visitor.visit_import(node),
+        Node::Attribute { .. } => visitor.visit_attribute(node),
+        Node::Assign { .. } => visitor.visit_assign(node),
+        Node::List(_) => visitor.visit_list(node),
+        Node::Tuple(_) => visitor.visit_tuple(node),
+        Node::Map(_) => visitor.visit_map(node),
+        Node::Index { .. } => visitor.visit_index(node),
+        Node::Slice { .. } => visitor.visit_slice(node),
+        Node::Number(_) => visitor.visit_number(node),
+        Node::String(_) => visitor.visit_string(node),
+        Node::Boolean(_) => visitor.visit_boolean(node),
+        Node::Identifier(_) => visitor.visit_identifier(node),
+    }
+}
+
+/// visits each direct child of `node`, in source order
+pub fn walk_children<V: Visitor + ?Sized>(visitor: &mut V, node: &Spanned<Node>) {
+    match &node.node {
+        Node::Variable {
+            pattern, ty, value, ..
+        } => {
+            visitor.visit_pattern(pattern);
+            if let Some(ty) = ty {
+                visitor.visit_type_expr(ty);
+            }
+            visitor.visit_node(value);
+        }
+        Node::BinaryExpr { lhs, rhs, .. } => {
+            visitor.visit_node(lhs);
+            visitor.visit_node(rhs);
+        }
+        Node::UnaryExpr { body, .. } => visitor.visit_node(body),
+        Node::Fn {
+            name,
+            args,
+            ret,
+            body,
+            ..
+        } => {
+            visitor.visit_node(name);
+            for arg in args {
+                visitor.visit_pattern(&arg.pattern);
+                if let Some(ty) = &arg.ty {
+                    visitor.visit_type_expr(ty);
+                }
+            }
+            if let Some(ret) = ret {
+                visitor.visit_type_expr(ret);
+            }
+            visitor.visit_node(body);
+        }
+        Node::Closure { args, ret, body } => {
+            for arg in args {
+                visitor.visit_pattern(&arg.pattern);
+                if let Some(ty) = &arg.ty {
+                    visitor.visit_type_expr(ty);
+                }
+            }
+            if let Some(ret) = ret {
+                visitor.visit_type_expr(ret);
+            }
+            visitor.visit_node(body);
+        }
+        Node::FnCall { function, args } => {
+            visitor.visit_node(function);
+            for arg in args {
+                visitor.visit_node(arg);
+            }
+        }
+        Node::Block { body, value } => {
+            for statement in body {
+                visitor.visit_node(statement);
+            }
+            if let Some(value) = value {
+                visitor.visit_node(value);
+            }
+        }
+        Node::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_node(value);
+            }
+        }
+        Node::Struct { name, fields, .. } => {
+            visitor.visit_node(name);
+            for field in fields {
+                visitor.visit_node(&field.name);
+                if let Some(ty) = &field.ty {
+                    visitor.visit_type_expr(ty);
+                }
+            }
+        }
+        Node::Enum { name, variants, .. } => {
+            visitor.visit_node(name);
+            for variant in variants {
+                visitor.visit_node(&variant.name);
+                for field in &variant.fields {
+                    visitor.visit_type_expr(field);
+                }
+            }
+        }
+        Node::Path { name, .. } => visitor.visit_node(name),
+        Node::Match { value, arms } => {
+            visitor.visit_node(value);
+            for arm in arms {
+                visitor.visit_pattern(&arm.pattern);
+                visitor.visit_node(&arm.value);
+            }
+        }
+        Node::Import { alias, items, .. } => {
+            if let Some(alias) = alias {
+                visitor.visit_node(alias);
+            }
+            for item in items {
+                visitor.visit_node(item);
+            }
+        }
+        Node::Assign { target, value, .. } => {
+            visitor.visit_node(target);
+            visitor.visit_node(value);
+        }
+        Node::List(items) | Node::Tuple(items) => {
+            for item in items {
+                visitor.visit_node(item);
+            }
+        }
+        Node::Map(entries) => {
+            for entry in entries {
+                visitor.visit_node(&entry.key);
+                visitor.visit_node(&entry.value);
+            }
+        }
+        Node::Index { target, index } => {
+            visitor.visit_node(target);
+            visitor.visit_node(index);
+        }
+        Node::Slice { target, start, end } => {
+            visitor.visit_node(target);
+            if let Some(start) = start {
+                visitor.visit_node(start);
+            }
+            if let Some(end) = end {
+                visitor.visit_node(end);
+            }
+        }
+        Node::Attribute { .. }
+        | Node::Number(_)
+        | Node::String(_)
+        | Node::Boolean(_)
+        | Node::Identifier(_) => {}
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_node_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_node_mut(self, node)
+    }
+
+    fn visit_variable_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_binary_expr_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_unary_expr_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_fn_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_closure_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_fn_call_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_block_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_return_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_struct_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_enum_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_path_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_match_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_import_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_attribute_mut(&mut self, _node: &mut Spanned<Node>) {}
+
+    fn visit_assign_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_list_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_tuple_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_map_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_index_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_slice_mut(&mut self, node: &mut Spanned<Node>) {
+        walk_children_mut(self, node)
+    }
+
+    fn visit_number_mut(&mut self, _node: &mut Spanned<Node>) {}
+
+    fn visit_string_mut(&mut self, _node: &mut Spanned<Node>) {}
+
+    fn visit_boolean_mut(&mut self, _node: &mut Spanned<Node>) {}
+
+    fn visit_identifier_mut(&mut self, _node: &mut Spanned<Node>) {}
+
+    /// called for every pattern bound by a `let`, a parameter or a `match` arm
+    fn visit_pattern_mut(&mut self, _pattern: &mut Spanned<Pattern>) {}
+
+    /// called for every type annotation
+    fn visit_type_expr_mut(&mut self, _ty: &mut Spanned<TypeExpr>) {}
+}
+
+/// calls the visitor's hook for the variant of `node`
+pub fn walk_node_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Spanned<Node>) {
+    match &node.node {
+        Node::Variable { .. } => visitor.visit_variable_mut(node),
+        Node::BinaryExpr { .. } => visitor.visit_binary_expr_mut(node),
+        Node::UnaryExpr { .. } => visitor.visit_unary_expr_mut(node),
+        Node::Fn { .. } => visitor.visit_fn_mut(node),
+        Node::Closure { .. } => visitor.visit_closure_mut(node),
+        Node::FnCall { .. } => visitor.visit_fn_call_mut(node),
+        Node::Block { .. } => visitor.visit_block_mut(node),
+        Node::Return(_) => visitor.visit_return_mut(node),
+        Node::Struct { .. } => visitor.visit_struct_mut(node),
+        Node::Enum { .. } => visitor.visit_enum_mut(node),
+        Node::Path { .. } => visitor.visit_path_mut(node),
+        Node::Match { .. } => visitor.visit_match_mut(node),
+        Node::Import { .. } => visitor.visit_import_mut(node),
+        Node::Attribute { .. } => visitor.visit_attribute_mut(node),
+        Node::Assign { .. } => visitor.visit_assign_mut(node),
+        Node::List(_) => visitor.visit_list_mut(node),
+        Node::Tuple(_) => visitor.visit_tuple_mut(node),
+        Node::Map(_) => visitor.visit_map_mut(node),
+        Node::Index { .. } => visitor.visit_index_mut(node),
+        Node::Slice { .. } => visitor.visit_slice_mut(node),
+        Node::Number(_) => visitor.visit_number_mut(node),
+        Node::String(_) => visitor.visit_string_mut(node),
+        Node::Boolean(_) => visitor.visit_boolean_mut(node),
+        Node::Identifier(_) => visitor.visit_identifier_mut(node),
+    }
+}
+
+/// visits each direct child of `node`, in source order
+pub fn walk_children_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Spanned<Node>) {
+    match &mut node.node {
+        Node::Variable {
+            pattern, ty, value, ..
+        } => {
+            visitor.visit_pattern_mut(pattern);
+            if let Some(ty) = ty {
+                visitor.visit_type_expr_mut(ty);
+            }
+            visitor.visit_node_mut(value);
+        }
+        Node::BinaryExpr { lhs, rhs, .. } => {
+            visitor.visit_node_mut(lhs);
+            visitor.visit_node_mut(rhs);
+        }
+        Node::UnaryExpr { body, .. } => visitor.visit_node_mut(body),
+        Node::Fn {
+            name,
+            args,
+            ret,
+            body,
+            ..
+        } => {
+            visitor.visit_node_mut(name);
+            for arg in args {
+                visitor.visit_pattern_mut(&mut arg.pattern);
+                if let Some(ty) = &mut arg.ty {
+                    visitor.visit_type_expr_mut(ty);
+                }
+            }
+            if let Some(ret) = ret {
+                visitor.visit_type_expr_mut(ret);
+            }
+            visitor.visit_node_mut(body);
+        }
+        Node::Closure { args, ret, body } => {
+            for arg in args {
+                visitor.visit_pattern_mut(&mut arg.pattern);
+                if let Some(ty) = &mut arg.ty {
+                    visitor.visit_type_expr_mut(ty);
+                }
+            }
+            if let Some(ret) = ret {
+                visitor.visit_type_expr_mut(ret);
+            }
+            visitor.visit_node_mut(body);
+        }
+        Node::FnCall { function, args } => {
+            visitor.visit_node_mut(function);
+            for arg in args {
+                visitor.visit_node_mut(arg);
+            }
+        }
+        Node::Block { body, value } => {
+            for statement in body {
+                visitor.visit_node_mut(statement);
+            }
+            if let Some(value) = value {
+                visitor.visit_node_mut(value);
+            }
+        }
+        Node::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_node_mut(value);
+            }
+        }
+        Node::Struct { name, fields, .. } => {
+            visitor.visit_node_mut(name);
+            for field in fields {
+                visitor.visit_node_mut(&mut field.name);
+                if let Some(ty) = &mut field.ty {
+                    visitor.visit_type_expr_mut(ty);
+                }
+            }
+        }
+        Node::Enum { name, variants, .. } => {
+            visitor.visit_node_mut(name);
+            for variant in variants {
+                visitor.visit_node_mut(&mut variant.name);
+                for field in &mut variant.fields {
+                    visitor.visit_type_expr_mut(field);
+                }
+            }
+        }
+        Node::Path { name, .. } => visitor.visit_node_mut(name),
+        Node::Match { value, arms } => {
+            visitor.visit_node_mut(value);
+            for arm in arms {
+                visitor.visit_pattern_mut(&mut arm.pattern);
+                visitor.visit_node_mut(&mut arm.value);
+            }
+        }
+        Node::Import { alias, items, .. } => {
+            if let Some(alias) = alias {
+                visitor.visit_node_mut(alias);
+            }
+            for item in items {
+                visitor.visit_node_mut(item);
+            }
+        }
+        Node::Assign { target, value, .. } => {
+            visitor.visit_node_mut(target);
+            visitor.visit_node_mut(value);
+        }
+        Node::List(items) | Node::Tuple(items) => {
+            for item in items {
+                visitor.visit_node_mut(item);
+            }
+        }
+        Node::Map(entries) => {
+            for entry in entries {
+                visitor.visit_node_mut(&mut entry.key);
+                visitor.visit_node_mut(&mut entry.value);
+            }
+        }
+        Node::Index { target, index } => {
+            visitor.visit_node_mut(target);
+            visitor.visit_node_mut(index);
+        }
+        Node::Slice { target, start, end } => {
+            visitor.visit_node_mut(target);
+            if let Some(start) = start {
+                visitor.visit_node_mut(start);
+            }
+            if let Some(end) = end {
+                visitor.visit_node_mut(end);
+            }
+        }
+        Node::Attribute { .. }
+        | Node::Number(_)
+        | Node::String(_)
+        | Node::Boolean(_)
+        | Node::Identifier(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+
+    use super::*;
+    use crate::parse;
+
+    #[derive(Default)]
+    struct Identifiers(Vec<String>);
+
+    impl Visitor for Identifiers {
+        fn visit_identifier(&mut self, node: &Spanned<Node>) {
+            if let Node::Identifier(i) = &node.node {
+                self.0.push(i.clone());
+            }
+        }
+
+        fn visit_pattern(&mut self, pattern: &Spanned<Pattern>) {
+            for (name, _) in pattern.bindings() {
+                self.0.push(name.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn visits_every_identifier_in_order() {
+        let ast = parse(tokenise("fn f(a) { let b = a; g(b, -c) }").unwrap()).unwrap();
+
+        let mut identifiers = Identifiers::default();
+        for node in &ast {
+            identifiers.visit_node(node);
+        }
+
+        assert_eq!(identifiers.0, vec!["f", "a", "b", "a", "g", "b", "c"]);
+    }
+
+    struct Negate;
+
+    impl VisitorMut for Negate {
+        fn visit_assign_mut(&mut self, node: &mut Spanned<Node>) {
+            walk_children_mut(self, node)
+        }
+
+        fn visit_list_mut(&mut self, node: &mut Spanned<Node>) {
+            walk_children_mut(self, node)
+        }
+
+        fn visit_map_mut(&mut self, node: &mut Spanned<Node>) {
+            walk_children_mut(self, node)
+        }
+
+        fn visit_index_mut(&mut self, node: &mut Spanned<Node>) {
+            walk_children_mut(self, node)
+        }
+
+        fn visit_slice_mut(&mut self, node: &mut Spanned<Node>) {
+            walk_children_mut(self, node)
+        }
+
+        fn visit_number_mut(&mut self, node: &mut Spanned<Node>) {
+            if let Node::Number(n) = &mut node.node {
+                *n = -*n;
+            }
+        }
+    }
+
+    #[test]
+    fn rewrites_in_place() {
+        let mut ast = parse(tokenise("f(1, 2 + 3)").unwrap()).unwrap();
+        Negate.visit_node_mut(&mut ast[0]);
+
+        if let Node::FnCall { args, .. } = &ast[0].node {
+            assert_eq!(args[0].node, Node::Number(-1.0));
+
+            if let Node::BinaryExpr { lhs, rhs, .. } = &args[1].node {
+                assert_eq!(lhs.node, Node::Number(-2.0));
+                assert_eq!(rhs.node, Node::Number(-3.0));
+            } else {
+                panic!("expected a binary expression");
+            }
+        } else {
+            panic!("expected a call");
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use lex::{error::LexError, Span};
+use parse::error::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestError {
+    /// a line that isn't `key "value"`, spanning the line
+    InvalidLine(Span),
+    /// a key no manifest has, spanning the key
+    UnknownKey(Span, String),
+    /// an `allow`, `warn` or `deny` line naming no lint, spanning the quoted name
+    UnknownLint(Span, String),
+}
+
+/// a problem loading a module graph, located in the module `file`
+#[derive(Debug)]
+pub enum ResolveError {
+    Io {
+        file: PathBuf,
+        error: io::Error,
+    },
+    Lex {
+        file: PathBuf,
+        error: LexError,
+    },
+    Parse {
+        file: PathBuf,
+        error: ParseError,
+    },
+    /// an import naming a module with no file at `module`
+    Unresolved {
+        file: PathBuf,
+        span: Span,
+        module: PathBuf,
+    },
+    /// an import that leads back to the module itself, through the modules in `cycle`
+    Cycle {
+        file: PathBuf,
+        span: Span,
+        cycle: Vec<PathBuf>,
+    },
+    /// a selective import of a name the module doesn't define
+    MissingItem {
+        file: PathBuf,
+        span: Span,
+        module: PathBuf,
+        name: String,
+    },
+    /// a selective import of an item that isn't `pub`
+    Private {
+        file: PathBuf,
+        span: Span,
+        module: PathBuf,
+        name: String,
+    },
+}
+
+impl ResolveError {
+    /// the module the problem is in, which any span points into
+    pub fn file(&self) -> &Path {
+        match self {
+            ResolveError::Io { file, .. }
+            | ResolveError::Lex { file, .. }
+            | ResolveError::Parse { file, .. }
+            | ResolveError::Unresolved { file, .. }
+            | ResolveError::Cycle { file, .. }
+            | ResolveError::MissingItem { file, .. }
+            | ResolveError::Private { file, .. } => file,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Packages: the `pkg.plto` manifest and the modules that make up a program.
+
+pub mod error;
+pub mod manifest;
+pub mod resolve;
+
+pub use manifest::{Manifest, MANIFEST};
+pub use resolve::{FsLoader, Loader, Module, ModuleGraph, Resolver};
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use lint::{Level, Lint};
+
+use crate::error::ManifestError;
+
+/// file name of a package manifest
+pub const MANIFEST: &str = "pkg.plto";
+
+/// the contents of a `pkg.plto`, one `key "value"` pair per line
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Manifest {
+    pub name: String,
+    pub description: String,
+    pub version: String,
+    pub license: String,
+    /// directory holding the package's modules, relative to the manifest
+    pub source: PathBuf,
+    /// lint levels set with `allow`, `warn` or `deny` lines, in order, by lint name or `all`
+    pub lints: Vec<(String, Level)>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Manifest, ManifestError> {
+        let mut manifest = Manifest {
+            source: PathBuf::from("."),
+            ..Manifest::default()
+        };
+
+        let mut next_line = 0;
+        for raw in text.split_inclusive('\n') {
+            let line_start = next_line;
+            next_line += raw.len();
+            let line = raw.trim();
+            if line.is_empty() {
+                continue;
+            }
+            // where the line's text starts, past any indentation
+            let start = line_start + raw.len() - raw.trim_start().len();
+
+            let (key, value, value_start) = match line.find(char::is_whitespace) {
+                Some(split) => {
+                    let rest = &line[split..];
+                    let skipped = rest.len() - rest.trim_start().len();
+                    (&line[..split], rest.trim(), start + split + skipped)
+                }
+                None => return Err(ManifestError::InvalidLine((start, start + line.len()))),
+            };
+
+            if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+                return Err(ManifestError::InvalidLine((start, start + line.len())));
+            }
+            let value_span = (value_start, value_start + value.len());
+            let value = value[1..value.len() - 1].to_string();
+
+            match key {
+                "name" => manifest.name = value,
+                "description" => manifest.description = value,
+                "version" => manifest.version = value,
+                "license" => manifest.license = value,
+                "source" => manifest.source = PathBuf::from(value),
+                "allow" | "warn" | "deny" => {
+                    if value != "all" && Lint::from_name(&value).is_none() {
+                        return Err(ManifestError::UnknownLint(value_span, value));
+                    }
+                    let level = Level::parse(key).expect("matched a level");
+                    manifest.lints.push((value, level));
+                }
+                _ => {
+                    let key_span = (start, start + key.len());
+                    return Err(ManifestError::UnknownKey(key_span, key.to_string()));
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_key() {
+        let manifest = Manifest::parse(
+            "name \"demo\"\ndescription \"a demo\"\n\nversion \"0.1.0\"\nlicense \"MIT\"\nsource \"src\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name, "demo");
+        assert_eq!(manifest.version, "0.1.0");
+        assert_eq!(manifest.source, PathBuf::from("src"));
+    }
+
+    #[test]
+    fn source_defaults_to_the_package_root() {
+        let manifest = Manifest::parse("name \"demo\"").unwrap();
+        assert_eq!(manifest.source, PathBuf::from("."));
+    }
+
+    #[test]
+    fn reads_lint_levels_in_order() {
+        let manifest =
+            Manifest::parse("deny \"all\"\nallow \"unused_variable\"\nwarn \"shadowed_binding\"")
+                .unwrap();
+
+        assert_eq!(
+            manifest.lints,
+            vec![
+                (String::from("all"), Level::Deny),
+                (String::from("unused_variable"), Level::Allow),
+                (String::from("shadowed_binding"), Level::Warn),
+            ]
+        );
+        assert_eq!(
+            Manifest::parse("allow \"unused\""),
+            Err(ManifestError::UnknownLint((6, 14), String::from("unused")))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(
+            Manifest::parse("name \"demo\"\n  source src"),
+            Err(ManifestError::InvalidLine((14, 24)))
+        );
+        assert_eq!(
+            Manifest::parse("entry \"main\""),
+            Err(ManifestError::UnknownKey((0, 5), String::from("entry")))
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! Loading a program's modules from disk.
+//!
+//! Starting at the entry module, every `import` is mapped to a `.chr` file under the package's
+//! source directory, which is loaded and resolved in turn. Modules are loaded once however often
+//! they're imported, and an import that leads back to a module still being resolved is a cycle.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use lex::tokenise;
+use parse::{
+    expr::{ModulePath, Node, Spanned},
+    parse,
+};
+
+use crate::error::ResolveError;
+
+/// where the resolver reads module sources from
+pub trait Loader {
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// reads modules from the file system
+pub struct FsLoader;
+
+impl Loader for FsLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// modules held in memory, keyed by their path
+impl Loader for HashMap<PathBuf, String> {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such module"))
+    }
+}
+
+#[derive(Debug)]
+pub struct Module {
+    pub file: PathBuf,
+    pub source: String,
+    pub ast: Vec<Spanned<Node>>,
+    /// the modules named by each of this module's imports, as indices into the graph
+    pub imports: Vec<usize>,
+}
+
+impl Module {
+    /// the name of every top-level item, and whether it's `pub`
+    pub fn items(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.ast.iter().flat_map(|node| match &node.node {
+            Node::Variable {
+                public, pattern, ..
+            } => pattern
+                .bindings()
+                .into_iter()
+                .map(|(name, _)| (name, *public))
+                .collect(),
+            Node::Fn {
+                public,
+                name: identifier,
+                ..
+            }
+            | Node::Struct {
+                public,
+                name: identifier,
+                ..
+            }
+            | Node::Enum {
+                public,
+                name: identifier,
+                ..
+            } => match &identifier.node {
+                Node::Identifier(name) => vec![(name.as_str(), *public)],
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        })
+    }
+}
+
+/// every module reachable from an entry module
+#[derive(Debug)]
+pub struct ModuleGraph {
+    /// each module comes after the modules it imports, so the entry module is last
+    pub modules: Vec<Module>,
+}
+
+impl ModuleGraph {
+    pub fn entry(&self) -> &Module {
+        self.modules
+            .last()
+            .expect("a module graph holds at least its entry")
+    }
+}
+
+pub struct Resolver<L> {
+    /// the package's source directory
+    root: PathBuf,
+    loader: L,
+}
+
+/// state carried through a depth-first walk of the imports
+#[derive(Default)]
+struct Walk {
+    modules: Vec<Module>,
+    loaded: HashMap<PathBuf, usize>,
+    /// modules that failed to lex or parse, so they're only reported once
+    failed: HashSet<PathBuf>,
+    /// modules whose imports are still being resolved, outermost first
+    stack: Vec<PathBuf>,
+    errors: Vec<ResolveError>,
+}
+
+impl<L: Loader> Resolver<L> {
+    pub fn new(root: impl Into<PathBuf>, loader: L) -> Resolver<L> {
+        Resolver {
+            root: root.into(),
+            loader,
+        }
+    }
+
+    /// the file an import refers to, `"a/b"` and `a.b` both naming `a/b.chr` under the root
+    pub fn module_file(&self, path: &ModulePath) -> PathBuf {
+        let mut file = match path {
+            ModulePath::File(file) => self.root.join(file),
+            ModulePath::Dotted(segments) => self.root.join(segments.join("/")),
+        };
+        file.set_extension("chr");
+
+        file
+    }
+
+    /// loads the entry module and everything it imports, reporting every problem found
+    pub fn resolve(&self, entry: &Path) -> Result<ModuleGraph, Vec<ResolveError>> {
+        let mut walk = Walk::default();
+
+        match self.loader.load(entry) {
+            Ok(source) => {
+                self.module(&mut walk, entry.to_path_buf(), source);
+            }
+            Err(error) => walk.errors.push(ResolveError::Io {
+                file: entry.to_path_buf(),
+                error,
+            }),
+        }
+
+        if walk.errors.is_empty() {
+            Ok(ModuleGraph {
+                modules: walk.modules,
+            })
+        } else {
+            Err(walk.errors)
+        }
+    }
+
+    /// parses a module and resolves its imports, returning its index in the graph
+    fn module(&self, walk: &mut Walk, file: PathBuf, source: String) -> Option<usize> {
+        let ast = match tokenise(&source) {
+            Ok(tokens) => parse(tokens).map_err(|error| ResolveError::Parse {
+                file: file.clone(),
+                error,
+            }),
+            Err(error) => Err(ResolveError::Lex {
+                file: file.clone(),
+                error,
+            }),
+        };
+        let ast = match ast {
+            Ok(ast) => ast,
+            Err(err) => {
+                walk.errors.push(err);
+                walk.failed.insert(file);
+                return None;
+            }
+        };
+
+        walk.stack.push(file.clone());
+        let mut imports = Vec::new();
+        for node in &ast {
+            if let Node::Import { path, items, .. } = &node.node {
+                if let Some(idx) = self.import(walk, &file, path) {
+                    check_items(walk, &file, idx, items);
+                    imports.push(idx);
+                }
+            }
+        }
+        walk.stack.pop();
+
+        let idx = walk.modules.len();
+        walk.loaded.insert(file.clone(), idx);
+        walk.modules.push(Module {
+            file,
+            source,
+            ast,
+            imports,
+        });
+
+        Some(idx)
+    }
+
+    /// resolves a single import made by `file`
+    fn import(&self, walk: &mut Walk, file: &Path, path: &Spanned<ModulePath>) -> Option<usize> {
+        let target = self.module_file(&path.node);
+
+        if let Some(start) = walk.stack.iter().position(|f| *f == target) {
+            let mut cycle = walk.stack[start..].to_vec();
+            cycle.push(target);
+            walk.errors.push(ResolveError::Cycle {
+                file: file.to_path_buf(),
+                span: path.span,
+                cycle,
+            });
+            return None;
+        }
+
+        if let Some(&idx) = walk.loaded.get(&target) {
+            return Some(idx);
+        }
+        if walk.failed.contains(&target) {
+            return None;
+        }
+
+        match self.loader.load(&target) {
+            Ok(source) => self.module(walk, target, source),
+            Err(error) => {
+                walk.errors
+                    .push(if error.kind() == io::ErrorKind::NotFound {
+                        ResolveError::Unresolved {
+                            file: file.to_path_buf(),
+                            span: path.span,
+                            module: target.clone(),
+                        }
+                    } else {
+                        ResolveError::Io {
+                            file: target.clone(),
+                            error,
+                        }
+                    });
+                walk.failed.insert(target);
+                None
+            }
+        }
+    }
+}
+
+/// checks that each selectively imported name is a `pub` item of the module at `idx`
+fn check_items(walk: &mut Walk, file: &Path, idx: usize, items: &[Spanned<Node>]) {
+    for item in items {
+        let name = match &item.node {
+            Node::Identifier(name) => name,
+            _ => continue,
+        };
+
+        let module = &walk.modules[idx];
+        let error = match module.items().find(|(item, _)| item == name) {
+            Some((_, true)) => continue,
+            Some((_, false)) => ResolveError::Private {
+                file: file.to_path_buf(),
+                span: item.span,
+                module: module.file.clone(),
+                name: name.clone(),
+            },
+            None => ResolveError::MissingItem {
+                file: file.to_path_buf(),
+                span: item.span,
+                module: module.file.clone(),
+                name: name.clone(),
+            },
+        };
+        walk.errors.push(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(files: &[(&str, &str)]) -> Resolver<HashMap<PathBuf, String>> {
+        let files = files
+            .iter()
+            .map(|(path, source)| (Path::new("src").join(path), source.to_string()))
+            .collect();
+
+        Resolver::new("src", files)
+    }
+
+    fn file(path: &str) -> PathBuf {
+        Path::new("src").join(path)
+    }
+
+    #[test]
+    fn maps_module_paths_to_files() {
+        let resolver = resolver(&[]);
+
+        assert_eq!(
+            resolver.module_file(&ModulePath::File(String::from("util/strings"))),
+            file("util/strings.chr")
+        );
+        assert_eq!(
+            resolver.module_file(&ModulePath::Dotted(vec![
+                String::from("std"),
+                String::from("math")
+            ])),
+            file("std/math.chr")
+        );
+    }
+
+    #[test]
+    fn loads_imports_before_importers() {
+        let resolver = resolver(&[
+            (
+                "main.chr",
+                "import std.math as m; import \"util\" { pad }; import std.math;",
+            ),
+            ("std/math.chr", "import \"util\"; pub fn sin(x) { x }"),
+            ("util.chr", "pub fn pad(s) { s }"),
+        ]);
+
+        let graph = resolver.resolve(&file("main.chr")).unwrap();
+        let files: Vec<_> = graph.modules.iter().map(|m| m.file.clone()).collect();
+
+        assert_eq!(
+            files,
+            vec![file("util.chr"), file("std/math.chr"), file("main.chr")]
+        );
+        assert_eq!(graph.entry().imports, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn reports_unresolved_modules() {
+        let resolver = resolver(&[("main.chr", "let a = 1;\nimport std.missing;")]);
+
+        let errors = resolver.resolve(&file("main.chr")).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ResolveError::Unresolved {
+                file: f,
+                span,
+                module,
+            } => {
+                assert_eq!(*f, file("main.chr"));
+                assert_eq!(*span, (18, 29));
+                assert_eq!(*module, file("std/missing.chr"));
+            }
+            other => panic!("expected an unresolved module, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let resolver = resolver(&[
+            ("main.chr", "import a;"),
+            ("a.chr", "import b;"),
+            ("b.chr", "import \"a\";"),
+        ]);
+
+        let errors = resolver.resolve(&file("main.chr")).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ResolveError::Cycle {
+                file: f,
+                span,
+                cycle,
+            } => {
+                assert_eq!(*f, file("b.chr"));
+                assert_eq!(*span, (7, 10));
+                assert_eq!(*cycle, vec![file("a.chr"), file("b.chr"), file("a.chr")]);
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn selective_imports_need_public_items() {
+        let resolver = resolver(&[
+            ("main.chr", "import lib { open, hidden, absent, second };"),
+            (
+                "lib.chr",
+                "pub fn open() {}\nfn hidden() {}\npub let [first, second] = [1, 2];",
+            ),
+        ]);
+
+        let errors = resolver.resolve(&file("main.chr")).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            ResolveError::Private { name, span: (19, 25), .. } if name == "hidden"
+        ));
+        assert!(matches!(
+            &errors[1],
+            ResolveError::MissingItem { name, span: (27, 33), .. } if name == "absent"
+        ));
+    }
+
+    #[test]
+    fn reports_broken_modules_once() {
+        let resolver = resolver(&[
+            ("main.chr", "import a; import b;"),
+            ("a.chr", "import broken;"),
+            ("b.chr", "import broken;"),
+            ("broken.chr", "let = 1;"),
+        ]);
+
+        let errors = resolver.resolve(&file("main.chr")).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(&errors[0], ResolveError::Parse { file: f, .. } if *f == file("broken.chr"))
+        );
+    }
+}
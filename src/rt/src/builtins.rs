@@ -0,0 +1,115 @@
+//! Functions available to every program.
+
+use lex::Span;
+
+use crate::{
+    error::RuntimeError,
+    value::{Caller, Native, Value},
+};
+
+/// every builtin, to be bound in the global scope
+pub fn builtins() -> Vec<Native> {
+    vec![
+        Native {
+            name: "print",
+            function: print,
+        },
+        Native {
+            name: "list",
+            function: list,
+        },
+        Native {
+            name: "len",
+            function: len,
+        },
+        Native {
+            name: "map",
+            function: map,
+        },
+        Native {
+            name: "filter",
+            function: filter,
+        },
+    ]
+}
+
+fn arity(args: &[Value], expected: usize, span: Span) -> Result<(), RuntimeError> {
+    if args.len() == expected {
+        Ok(())
+    } else {
+        Err(RuntimeError::WrongArity {
+            expected,
+            found: args.len(),
+            span,
+        })
+    }
+}
+
+fn mismatch(expected: &'static str, found: &Value, span: Span) -> RuntimeError {
+    RuntimeError::TypeMismatch {
+        expected,
+        found: found.type_name(),
+        span,
+    }
+}
+
+/// the items of a list argument, copied out so callbacks can change the list
+fn items(value: &Value, span: Span) -> Result<Vec<Value>, RuntimeError> {
+    match value {
+        Value::List(values) => Ok(values.borrow().clone()),
+        other => Err(mismatch("list", other, span)),
+    }
+}
+
+/// `print(a, b, ...)` writes its arguments to stdout, separated by spaces
+fn print(_: &mut dyn Caller, args: Vec<Value>, _: Span) -> Result<Value, RuntimeError> {
+    let line: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    println!("{}", line.join(" "));
+
+    Ok(Value::Nil)
+}
+
+/// `list(a, b, ...)` makes a list of its arguments
+fn list(_: &mut dyn Caller, args: Vec<Value>, _: Span) -> Result<Value, RuntimeError> {
+    Ok(Value::list(args))
+}
+
+/// `len(xs)`, the number of items in a list or map, or characters in a string
+fn len(_: &mut dyn Caller, args: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    arity(&args, 1, span)?;
+
+    match &args[0] {
+        Value::List(values) => Ok(Value::Number(values.borrow().len() as f64)),
+        Value::Map(entries) => Ok(Value::Number(entries.borrow().len() as f64)),
+        Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+        other => Err(mismatch("list", other, span)),
+    }
+}
+
+/// `map(xs, f)`, a new list of `f` applied to each item
+fn map(caller: &mut dyn Caller, args: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    arity(&args, 2, span)?;
+
+    let mut mapped = Vec::new();
+    for item in items(&args[0], span)? {
+        mapped.push(caller.call(&args[1], vec![item], span)?);
+    }
+
+    Ok(Value::list(mapped))
+}
+
+/// `filter(xs, f)`, a new list of the items for which `f` returns true
+fn filter(caller: &mut dyn Caller, args: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    arity(&args, 2, span)?;
+
+    let mut kept = Vec::new();
+    for item in items(&args[0], span)? {
+        match caller.call(&args[1], vec![item.clone()], span)? {
+            Value::Boolean(true) => kept.push(item),
+            Value::Boolean(false) => {}
+            other => return Err(mismatch("boolean", &other, span)),
+        }
+    }
+
+    Ok(Value::list(kept))
+}
@@ -0,0 +1,128 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use lex::suggest::closest;
+
+use crate::value::Value;
+
+/// a scope shared between the code running in it and the closures created there
+pub type Env = Rc<RefCell<Scope>>;
+
+#[derive(Default)]
+pub struct Scope {
+    values: HashMap<String, Value>,
+    parent: Option<Env>,
+}
+
+impl Scope {
+    /// an outermost scope
+    pub fn global() -> Env {
+        Rc::new(RefCell::new(Scope::default()))
+    }
+
+    /// a scope nested in `parent`, seeing its bindings
+    pub fn child(parent: &Env) -> Env {
+        Rc::new(RefCell::new(Scope {
+            values: HashMap::new(),
+            parent: Some(Rc::clone(parent)),
+        }))
+    }
+
+    /// binds `name` in this scope, shadowing any outer binding
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// rebinds `name` in the nearest scope that holds it, returning false if none does
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        match self.values.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => match &self.parent {
+                Some(parent) => parent.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+
+    /// looks `name` up in this scope, then each enclosing scope
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.borrow().get(name),
+        }
+    }
+
+    /// the name in scope closest to `name`, for suggesting when `name` isn't bound
+    pub fn similar(&self, name: &str) -> Option<String> {
+        let mut names = Vec::new();
+        self.names(&mut names);
+        names.sort();
+
+        closest(name, names.iter().map(String::as_str)).map(str::to_string)
+    }
+
+    /// every name visible from this scope, outer ones included
+    fn names(&self, names: &mut Vec<String>) {
+        names.extend(self.values.keys().cloned());
+        if let Some(parent) = &self.parent {
+            parent.borrow().names(names);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_reach_enclosing_scopes() {
+        let global = Scope::global();
+        global.borrow_mut().define("a", Value::Number(1.0));
+
+        let inner = Scope::child(&global);
+        inner.borrow_mut().define("b", Value::Number(2.0));
+        global.borrow_mut().define("c", Value::Number(3.0));
+
+        assert_eq!(inner.borrow().get("a"), Some(Value::Number(1.0)));
+        assert_eq!(inner.borrow().get("c"), Some(Value::Number(3.0)));
+        assert_eq!(global.borrow().get("b"), None);
+    }
+
+    #[test]
+    fn inner_bindings_shadow() {
+        let global = Scope::global();
+        global.borrow_mut().define("a", Value::Number(1.0));
+
+        let inner = Scope::child(&global);
+        inner.borrow_mut().define("a", Value::Boolean(true));
+
+        assert_eq!(inner.borrow().get("a"), Some(Value::Boolean(true)));
+        assert_eq!(global.borrow().get("a"), Some(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn assignment_changes_the_nearest_binding() {
+        let global = Scope::global();
+        global.borrow_mut().define("a", Value::Number(1.0));
+        let inner = Scope::child(&global);
+
+        assert!(inner.borrow_mut().assign("a", Value::Number(2.0)));
+        assert!(!inner.borrow_mut().assign("b", Value::Nil));
+        assert_eq!(global.borrow().get("a"), Some(Value::Number(2.0)));
+        assert_eq!(inner.borrow().get("b"), None);
+    }
+
+    #[test]
+    fn suggests_similar_names_from_every_scope() {
+        let global = Scope::global();
+        global.borrow_mut().define("total", Value::Nil);
+        let inner = Scope::child(&global);
+        inner.borrow_mut().define("count", Value::Nil);
+
+        assert_eq!(inner.borrow().similar("cuont"), Some(String::from("count")));
+        assert_eq!(inner.borrow().similar("totl"), Some(String::from("total")));
+        assert_eq!(global.borrow().similar("cuont"), None);
+    }
+}
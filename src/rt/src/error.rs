@@ -0,0 +1,49 @@
+use lex::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    /// a name bound nowhere in scope, with the closest name that is, if any is close
+    UndefinedVariable {
+        name: String,
+        span: Span,
+        similar: Option<String>,
+    },
+    /// a value of the wrong type given to an operator or builtin
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+        span: Span,
+    },
+    NotCallable(Span),
+    IndexOutOfBounds {
+        index: i64,
+        len: usize,
+        span: Span,
+    },
+    /// a slice `start..end` whose start comes after its end
+    ReversedSlice {
+        start: usize,
+        end: usize,
+        span: Span,
+    },
+    /// a map lookup of a key it doesn't hold, shown as written in source
+    MissingKey(String, Span),
+    /// a value without the shape of the pattern it's bound to
+    PatternMismatch(Span),
+    WrongArity {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    /// an `import`, which the interpreter can't load, at the module path
+    UnsupportedImport(Span),
+    /// `Enum::Variant` naming a variant the enum doesn't have, with the closest one it does
+    UnknownVariant {
+        name: String,
+        variant: String,
+        span: Span,
+        similar: Option<String>,
+    },
+    /// a `match` with no arm matching the value, at the value
+    NoMatchingArm(Span),
+}
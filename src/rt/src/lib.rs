@@ -1,7 +1,10 @@
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+//! Runtime support shared by the interpreter and builtins: values, scopes and errors.
+
+pub mod builtins;
+pub mod env;
+pub mod error;
+pub mod value;
+
+pub use env::{Env, Scope};
+pub use error::RuntimeError;
+pub use value::{Caller, EnumType, Function, Instance, MapKey, Native, StructType, Value, Variant};
@@ -0,0 +1,489 @@
+use std::{cell::RefCell, cmp::Ordering, collections::BTreeMap, fmt, rc::Rc};
+
+use lex::Span;
+use parse::expr::{Node, Param, Spanned};
+
+use crate::{env::Env, error::RuntimeError};
+
+#[derive(Clone)]
+pub enum Value {
+    /// the value of statements and of blocks without a trailing expression
+    Nil,
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    /// shared, so every copy of a list sees changes made through the others
+    List(Rc<RefCell<Vec<Value>>>),
+    /// shared like lists, and ordered by key
+    Map(Rc<RefCell<BTreeMap<MapKey, Value>>>),
+    /// fixed in length and contents once made
+    Tuple(Rc<Vec<Value>>),
+    /// an instance of a declared struct, with its fields in declaration order
+    Struct(Rc<Instance>),
+    /// a declared struct, called with its fields in order to make an instance
+    StructType(Rc<StructType>),
+    /// a variant of a declared enum, with the values it holds
+    Enum(Rc<Variant>),
+    /// a declared enum, whose variants are reached through it as `Enum::Variant`
+    EnumType(Rc<EnumType>),
+    /// a variant holding values, by its index in the enum, called with them to make one
+    Constructor(Rc<EnumType>, usize),
+    Function(Rc<Function>),
+    Native(Native),
+}
+
+/// a value usable as a map key
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Option<MapKey> {
+        match value {
+            Value::Boolean(b) => Some(MapKey::Boolean(*b)),
+            // 0 and -0 are the same number, so they must be the same key
+            Value::Number(n) if *n == 0.0 => Some(MapKey::Number(0.0)),
+            Value::Number(n) => Some(MapKey::Number(*n)),
+            Value::String(s) => Some(MapKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Boolean(b) => Value::Boolean(*b),
+            MapKey::Number(n) => Value::Number(*n),
+            MapKey::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &MapKey) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &MapKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MapKey {
+    /// booleans, then numbers, then strings
+    fn cmp(&self, other: &MapKey) -> Ordering {
+        match (self, other) {
+            (MapKey::Boolean(a), MapKey::Boolean(b)) => a.cmp(b),
+            (MapKey::Number(a), MapKey::Number(b)) => a.total_cmp(b),
+            (MapKey::String(a), MapKey::String(b)) => a.cmp(b),
+            (MapKey::Boolean(_), _) | (MapKey::Number(_), MapKey::String(_)) => Ordering::Less,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_value())
+    }
+}
+
+pub struct StructType {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+pub struct Instance {
+    pub ty: Rc<StructType>,
+    pub fields: Vec<Value>,
+}
+
+impl Instance {
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        let idx = self.ty.fields.iter().position(|f| f == field)?;
+        self.fields.get(idx)
+    }
+}
+
+pub struct EnumType {
+    pub name: String,
+    /// each variant's name and how many values it holds, in declaration order
+    pub variants: Vec<(String, usize)>,
+}
+
+pub struct Variant {
+    pub ty: Rc<EnumType>,
+    /// the variant's index in `ty.variants`
+    pub index: usize,
+    pub values: Vec<Value>,
+}
+
+impl Variant {
+    pub fn name(&self) -> &str {
+        &self.ty.variants[self.index].0
+    }
+
+    /// true if both are the same variant of the same enum, whatever values they hold
+    pub fn same_variant(&self, other: &Variant) -> bool {
+        Rc::ptr_eq(&self.ty, &other.ty) && self.index == other.index
+    }
+}
+
+/// a function or closure, along with the scope it was created in
+pub struct Function {
+    /// `None` for closures
+    pub name: Option<String>,
+    pub params: Vec<Param>,
+    pub body: Spanned<Node>,
+    pub env: Env,
+}
+
+/// calls back into the interpreter, for builtins taking functions
+pub trait Caller {
+    fn call(&mut self, callee: &Value, args: Vec<Value>, span: Span)
+        -> Result<Value, RuntimeError>;
+}
+
+/// a builtin's implementation, given the span of the call for its errors
+pub type NativeFn = fn(&mut dyn Caller, Vec<Value>, Span) -> Result<Value, RuntimeError>;
+
+/// a function implemented by the runtime
+#[derive(Clone, Copy)]
+pub struct Native {
+    pub name: &'static str,
+    pub function: NativeFn,
+}
+
+impl Value {
+    pub fn list(values: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn map(entries: BTreeMap<MapKey, Value>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn tuple(values: Vec<Value>) -> Value {
+        Value::Tuple(Rc::new(values))
+    }
+
+    /// the name of the value's type, as used in errors
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Tuple(_) => "tuple",
+            Value::Struct(_) => "struct",
+            Value::StructType(_) => "struct type",
+            Value::Enum(_) => "enum",
+            Value::EnumType(_) => "enum type",
+            Value::Function(_) | Value::Native(_) | Value::Constructor(..) => "function",
+        }
+    }
+
+    /// `==`, equality of contents, converting to numbers where needed
+    ///
+    /// - a number equals a string holding the same number, ignoring surrounding whitespace
+    /// - a number equals a boolean as `1` for `true` and `0` for `false`
+    /// - lists, tuples, maps, structs of the same struct and the same variant of an enum are
+    ///   compared item by item with `==`
+    /// - anything else only equals a value of its own type, as with `===`
+    pub fn loose_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Number(n), other) | (other, Value::Number(n)) => {
+                other.coerce_number() == Some(*n)
+            }
+            (Value::List(a), Value::List(b)) => {
+                Rc::ptr_eq(a, b) || all(&a.borrow(), &b.borrow(), Value::loose_eq)
+            }
+            (Value::Tuple(a), Value::Tuple(b)) => all(a, b, Value::loose_eq),
+            (Value::Map(a), Value::Map(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|((ka, va), (kb, vb))| ka == kb && va.loose_eq(vb))
+            }
+            (Value::Struct(a), Value::Struct(b)) => {
+                Rc::ptr_eq(&a.ty, &b.ty) && all(&a.fields, &b.fields, Value::loose_eq)
+            }
+            (Value::Enum(a), Value::Enum(b)) => {
+                a.same_variant(b) && all(&a.values, &b.values, Value::loose_eq)
+            }
+            _ => self.strict_eq(other),
+        }
+    }
+
+    /// `===`, equality without conversion, where both values must have the same type
+    ///
+    /// Lists and maps can be changed through any of their copies, so they're only strictly equal
+    /// to themselves. Tuples, structs and enums can't, so they're strictly equal when their items
+    /// are.
+    /// Numbers follow IEEE 754, so `0 === -0` but a NaN equals nothing, itself included.
+    pub fn strict_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Tuple(a), Value::Tuple(b)) => all(a, b, Value::strict_eq),
+            (Value::Struct(a), Value::Struct(b)) => {
+                Rc::ptr_eq(&a.ty, &b.ty) && all(&a.fields, &b.fields, Value::strict_eq)
+            }
+            (Value::Enum(a), Value::Enum(b)) => {
+                a.same_variant(b) && all(&a.values, &b.values, Value::strict_eq)
+            }
+            _ => self == other,
+        }
+    }
+
+    /// the number a string or boolean stands for under `==`
+    fn coerce_number(&self) -> Option<f64> {
+        match self {
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Boolean(b) => Some(if *b { 1.0 } else { 0.0 }),
+            _ => None,
+        }
+    }
+}
+
+/// true if both sequences are the same length and `eq` holds for each pair of items
+fn all(a: &[Value], b: &[Value], eq: fn(&Value, &Value) -> bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b))
+}
+
+impl PartialEq for Value {
+    /// structural equality for data, identity for functions
+    ///
+    /// This is neither of the language's equalities, which are `loose_eq` and `strict_eq`.
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::Struct(a), Value::Struct(b)) => {
+                Rc::ptr_eq(&a.ty, &b.ty) && a.fields == b.fields
+            }
+            (Value::StructType(a), Value::StructType(b)) => Rc::ptr_eq(a, b),
+            (Value::Enum(a), Value::Enum(b)) => a.same_variant(b) && a.values == b.values,
+            (Value::EnumType(a), Value::EnumType(b)) => Rc::ptr_eq(a, b),
+            (Value::Constructor(a, i), Value::Constructor(b, j)) => Rc::ptr_eq(a, b) && i == j,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => a.name == b.name,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::List(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.borrow().iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "#{{")?;
+                for (idx, (key, value)) in entries.borrow().iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {:?}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Tuple(values) if values.len() == 1 => write!(f, "({:?},)", values[0]),
+            Value::Tuple(values) => {
+                write!(f, "(")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", value)?;
+                }
+                write!(f, ")")
+            }
+            Value::Struct(instance) if instance.fields.is_empty() => {
+                write!(f, "{} {{}}", instance.ty.name)
+            }
+            Value::Struct(instance) => {
+                write!(f, "{} {{ ", instance.ty.name)?;
+                for (idx, (name, value)) in
+                    instance.ty.fields.iter().zip(&instance.fields).enumerate()
+                {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {:?}", name, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::StructType(ty) => write!(f, "<struct {}>", ty.name),
+            Value::Enum(variant) if variant.values.is_empty() => {
+                write!(f, "{}::{}", variant.ty.name, variant.name())
+            }
+            Value::Enum(variant) => {
+                write!(f, "{}::{}(", variant.ty.name, variant.name())?;
+                for (idx, value) in variant.values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", value)?;
+                }
+                write!(f, ")")
+            }
+            Value::EnumType(ty) => write!(f, "<enum {}>", ty.name),
+            Value::Constructor(ty, index) => {
+                write!(f, "<variant {}::{}>", ty.name, ty.variants[*index].0)
+            }
+            Value::Function(function) => match &function.name {
+                Some(name) => write!(f, "<fn {}>", name),
+                None => write!(f, "<closure>"),
+            },
+            Value::Native(native) => write!(f, "<builtin {}>", native.name),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_nested_lists() {
+        let list = Value::list(vec![
+            Value::Number(1.5),
+            Value::String(String::from("a")),
+            Value::list(vec![Value::Boolean(true), Value::Nil]),
+        ]);
+
+        assert_eq!(list.to_string(), "[1.5, \"a\", [true, nil]]");
+    }
+
+    #[test]
+    fn map_keys_are_ordered_by_type_then_value() {
+        let mut entries = BTreeMap::new();
+        entries.insert(MapKey::String(String::from("b")), Value::Nil);
+        entries.insert(MapKey::Number(2.0), Value::Nil);
+        entries.insert(
+            MapKey::from_value(&Value::Number(-0.0)).unwrap(),
+            Value::Nil,
+        );
+        entries.insert(MapKey::Number(0.0), Value::Boolean(true));
+        entries.insert(MapKey::Boolean(false), Value::list(Vec::new()));
+
+        assert_eq!(
+            Value::map(entries).to_string(),
+            "#{false: [], 0: true, 2: nil, \"b\": nil}"
+        );
+    }
+
+    #[test]
+    fn displays_tuples_and_structs() {
+        let point = Rc::new(StructType {
+            name: String::from("Point"),
+            fields: vec![String::from("x"), String::from("y")],
+        });
+        let instance = Value::Struct(Rc::new(Instance {
+            ty: Rc::clone(&point),
+            fields: vec![Value::Number(1.0), Value::String(String::from("a"))],
+        }));
+
+        assert_eq!(instance.to_string(), "Point { x: 1, y: \"a\" }");
+        assert_eq!(Value::tuple(vec![Value::Nil]).to_string(), "(nil,)");
+        assert_eq!(
+            Value::tuple(vec![Value::Boolean(true), instance]).to_string(),
+            "(true, Point { x: 1, y: \"a\" })"
+        );
+    }
+
+    #[test]
+    fn displays_and_compares_variants() {
+        let shape = Rc::new(EnumType {
+            name: String::from("Shape"),
+            variants: vec![(String::from("Circle"), 1), (String::from("Empty"), 0)],
+        });
+        let variant = |index, values| {
+            Value::Enum(Rc::new(Variant {
+                ty: Rc::clone(&shape),
+                index,
+                values,
+            }))
+        };
+        let circle = variant(0, vec![Value::String(String::from("1"))]);
+
+        assert_eq!(circle.to_string(), "Shape::Circle(\"1\")");
+        assert_eq!(variant(1, Vec::new()).to_string(), "Shape::Empty");
+        assert!(circle.loose_eq(&variant(0, vec![Value::Number(1.0)])));
+        assert!(!circle.strict_eq(&variant(0, vec![Value::Number(1.0)])));
+        assert!(variant(1, Vec::new()).strict_eq(&variant(1, Vec::new())));
+        assert_eq!(
+            Value::Constructor(Rc::clone(&shape), 0).to_string(),
+            "<variant Shape::Circle>"
+        );
+    }
+
+    #[test]
+    fn loose_equality_converts_to_numbers() {
+        let n = Value::Number(1.0);
+
+        assert!(n.loose_eq(&Value::String(String::from(" 1 "))));
+        assert!(Value::Boolean(true).loose_eq(&n));
+        assert!(!n.loose_eq(&Value::String(String::from("1x"))));
+        assert!(!Value::String(String::from("true")).loose_eq(&Value::Boolean(true)));
+        assert!(!n.strict_eq(&Value::Boolean(true)));
+    }
+
+    #[test]
+    fn strict_equality_of_lists_is_identity() {
+        let a = Value::list(vec![Value::Number(1.0)]);
+        let b = Value::list(vec![Value::Number(1.0)]);
+
+        assert!(a.loose_eq(&b));
+        assert!(!a.strict_eq(&b));
+        assert!(a.strict_eq(&a.clone()));
+        assert!(Value::tuple(vec![Value::Nil]).strict_eq(&Value::tuple(vec![Value::Nil])));
+    }
+
+    #[test]
+    fn lists_compare_by_contents() {
+        let a = Value::list(vec![Value::Number(1.0)]);
+        let b = Value::list(vec![Value::Number(1.0)]);
+
+        assert_eq!(a, b);
+        assert_ne!(a, Value::list(Vec::new()));
+        assert_ne!(Value::Number(0.0), Value::Boolean(false));
+    }
+}
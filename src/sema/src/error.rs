@@ -0,0 +1,125 @@
+use lex::Span;
+
+use crate::types::Type;
+
+/// a name that doesn't refer to a declaration it can
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameError {
+    /// a name with no declaration in any enclosing scope, with the closest name that has one
+    Undefined {
+        name: String,
+        span: Span,
+        similar: Option<String>,
+    },
+    /// a name declared twice in the same scope, pointing at both declarations
+    Duplicate {
+        name: String,
+        span: Span,
+        previous: Span,
+    },
+    /// a name read before it's declared, where it can't be read later instead as in a function
+    UseBeforeDeclaration {
+        name: String,
+        span: Span,
+        declaration: Span,
+    },
+    /// assigning to a `const`, pointing at both where it was declared and the assignment
+    ConstReassignment {
+        name: String,
+        declaration: Span,
+        assignment: Span,
+    },
+}
+
+impl NameError {
+    /// where the problem is, which errors are ordered by
+    pub fn span(&self) -> Span {
+        match self {
+            NameError::Undefined { span, .. }
+            | NameError::Duplicate { span, .. }
+            | NameError::UseBeforeDeclaration { span, .. } => *span,
+            NameError::ConstReassignment { assignment, .. } => *assignment,
+        }
+    }
+}
+
+/// a value whose type can't be what it's used as
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    /// a value at `span` of the wrong type, where `expected` comes from the code at `cause`, like
+    /// an annotation or an operator
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+        cause: Span,
+    },
+    /// a call with a different number of arguments than the function takes
+    WrongArity {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    NotCallable {
+        ty: Type,
+        span: Span,
+    },
+    /// indexing or slicing something other than a list, map or string
+    NotIndexable {
+        ty: Type,
+        span: Span,
+    },
+    /// an annotation naming no type, outside a function signature where it would be generic
+    UnknownType {
+        name: String,
+        span: Span,
+    },
+    /// a type given the wrong number of type arguments, like `List<Number, String>`
+    TypeArguments {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    /// `Enum::Variant` naming a variant the enum doesn't declare, with the closest one it does
+    UnknownVariant {
+        name: String,
+        variant: String,
+        span: Span,
+        similar: Option<String>,
+    },
+}
+
+impl TypeError {
+    /// where the problem is, which errors are ordered by
+    pub fn span(&self) -> Span {
+        match self {
+            TypeError::Mismatch { span, .. }
+            | TypeError::WrongArity { span, .. }
+            | TypeError::NotCallable { span, .. }
+            | TypeError::NotIndexable { span, .. }
+            | TypeError::UnknownType { span, .. }
+            | TypeError::TypeArguments { span, .. }
+            | TypeError::UnknownVariant { span, .. } => *span,
+        }
+    }
+}
+
+/// a `match` that can fail to match a value, or has arms it can never reach
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchError {
+    /// values of the matched type that no arm matches, written as patterns that would, at the
+    /// value being matched
+    NonExhaustive { span: Span, missing: Vec<String> },
+    /// an arm whose pattern only matches values an earlier arm already does
+    UnreachableArm { span: Span },
+}
+
+impl MatchError {
+    /// where the problem is, which errors are ordered by
+    pub fn span(&self) -> Span {
+        match self {
+            MatchError::NonExhaustive { span, .. } | MatchError::UnreachableArm { span } => *span,
+        }
+    }
+}
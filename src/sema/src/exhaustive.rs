@@ -0,0 +1,487 @@
+//! Exhaustiveness and reachability of patterns tried in turn against one value, as the arms of a
+//! `match` are.
+//!
+//! This is the usefulness algorithm from Maranget's "Warnings for pattern matching": a pattern is
+//! useful if it matches some value that no pattern before it does. An arm that isn't useful can
+//! never be reached, and the arms are exhaustive if a wildcard after them wouldn't be useful, with
+//! the values it would match named as the missing patterns.
+//!
+//! Values are only known to have one of a fixed set of shapes when their type says so, so without
+//! a wildcard only booleans, tuples, lists, structs and enums of a known type can be covered.
+
+use std::fmt;
+
+use lex::Span;
+use parse::expr::{Pattern, Spanned};
+
+use crate::types::{Enums, Type};
+
+/// what a list of arms leaves uncovered
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    /// patterns for values no arm matches, written as they would be in source
+    pub missing: Vec<String>,
+    /// the arms that only match values an earlier arm already does
+    pub unreachable: Vec<Span>,
+}
+
+impl Coverage {
+    pub fn is_exhaustive(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// the coverage of `arms` for a value of type `scrutinee`, where `enums` declares the variants
+/// of each enum
+pub fn coverage(arms: &[Spanned<Pattern>], scrutinee: &Type, enums: &Enums) -> Coverage {
+    let types = [scrutinee.clone()];
+    let mut rows: Vec<Vec<Pat>> = Vec::new();
+    let mut unreachable = Vec::new();
+
+    for arm in arms {
+        let row = vec![Pat::from(arm)];
+        if useful(&rows, &row, &types, enums).is_empty() {
+            unreachable.push(arm.span);
+        }
+        rows.push(row);
+    }
+
+    let missing = useful(&rows, &[Pat::Wild], &types, enums)
+        .into_iter()
+        .map(|mut witness| witness.remove(0).to_string())
+        .collect();
+
+    Coverage {
+        missing,
+        unreachable,
+    }
+}
+
+/// a pattern reduced to what decides which values it matches
+#[derive(Debug, Clone, PartialEq)]
+enum Pat {
+    /// a wildcard or a name, matching anything
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// a shape of value, matched with a pattern for each of its parts
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Tuple(usize),
+    /// a list of exactly this many items
+    List(usize),
+    /// a list of at least this many items, the rest being matched by `..`
+    ListRest(usize),
+    /// a struct, of any name if `None`, with patterns for these fields
+    Struct(Option<String>, Vec<String>),
+    /// a variant of an enum, by the enum's name and the variant's, holding this many values
+    Variant(String, String, usize),
+}
+
+impl From<&Spanned<Pattern>> for Pat {
+    fn from(pattern: &Spanned<Pattern>) -> Pat {
+        let parts = |items: &[Spanned<Pattern>]| items.iter().map(Pat::from).collect();
+        match &pattern.node {
+            Pattern::Identifier(_) | Pattern::Wildcard => Pat::Wild,
+            Pattern::Boolean(b) => Pat::Ctor(Ctor::Boolean(*b), Vec::new()),
+            Pattern::Number(n) => Pat::Ctor(Ctor::Number(*n), Vec::new()),
+            Pattern::String(s) => Pat::Ctor(Ctor::String(s.clone()), Vec::new()),
+            Pattern::Tuple(items) => Pat::Ctor(Ctor::Tuple(items.len()), parts(items)),
+            // the rest of a list is taken as it is, so only its length is matched
+            Pattern::List { items, rest: None } => Pat::Ctor(Ctor::List(items.len()), parts(items)),
+            Pattern::List {
+                items,
+                rest: Some(_),
+            } => Pat::Ctor(Ctor::ListRest(items.len()), parts(items)),
+            Pattern::Struct { name, fields } => Pat::Ctor(
+                Ctor::Struct(
+                    name.clone(),
+                    fields.iter().map(|f| f.name.clone()).collect(),
+                ),
+                fields.iter().map(|f| Pat::from(&f.pattern)).collect(),
+            ),
+            Pattern::Variant {
+                name,
+                variant,
+                fields,
+            } => Pat::Ctor(
+                Ctor::Variant(name.clone(), variant.clone(), fields.len()),
+                parts(fields),
+            ),
+        }
+    }
+}
+
+impl Ctor {
+    fn arity(&self) -> usize {
+        match self {
+            Ctor::Boolean(_) | Ctor::Number(_) | Ctor::String(_) => 0,
+            Ctor::Tuple(n) | Ctor::List(n) | Ctor::ListRest(n) | Ctor::Variant(_, _, n) => *n,
+            Ctor::Struct(_, fields) => fields.len(),
+        }
+    }
+
+    /// the types of the parts of a value of type `ty` with this shape
+    fn part_types(&self, ty: &Type, enums: &Enums) -> Vec<Type> {
+        let declared = |name: &String, variant: &String| {
+            enums
+                .get(name)?
+                .iter()
+                .find(|(v, _)| v == variant)
+                .map(|(_, types)| types.clone())
+        };
+
+        match (self, ty) {
+            (Ctor::Tuple(n), Type::Tuple(types)) if types.len() == *n => types.clone(),
+            (Ctor::List(n) | Ctor::ListRest(n), Type::List(item)) => vec![(**item).clone(); *n],
+            (Ctor::Variant(name, variant, n), _) => declared(name, variant)
+                .filter(|types| types.len() == *n)
+                .unwrap_or_else(|| vec![Type::Any; *n]),
+            _ => vec![Type::Any; self.arity()],
+        }
+    }
+}
+
+/// the shapes heading the patterns in the first column of `rows`
+fn heads(rows: &[Vec<Pat>]) -> impl Iterator<Item = (&Ctor, &[Pat])> {
+    rows.iter().filter_map(|row| match &row[0] {
+        Pat::Ctor(ctor, parts) => Some((ctor, parts.as_slice())),
+        Pat::Wild => None,
+    })
+}
+
+/// list shapes covering every length: each length up to the longest any pattern names exactly,
+/// and every length past it
+fn list_lengths(rows: &[Vec<Pat>], at_least: usize) -> Vec<Ctor> {
+    let longest = heads(rows)
+        .filter_map(|(ctor, _)| match ctor {
+            Ctor::List(n) | Ctor::ListRest(n) => Some(*n),
+            _ => None,
+        })
+        .fold(at_least, usize::max);
+
+    (at_least..=longest)
+        .map(Ctor::List)
+        .chain(std::iter::once(Ctor::ListRest(longest + 1)))
+        .collect()
+}
+
+/// every shape a value of type `ty` can have, if there are only a few
+fn signature(rows: &[Vec<Pat>], ty: &Type, enums: &Enums) -> Option<Vec<Ctor>> {
+    match ty {
+        Type::Boolean => Some(vec![Ctor::Boolean(true), Ctor::Boolean(false)]),
+        Type::Tuple(types) => Some(vec![Ctor::Tuple(types.len())]),
+        Type::List(_) => Some(list_lengths(rows, 0)),
+        Type::Struct(name) => {
+            let mut fields: Vec<String> = Vec::new();
+            for (ctor, _) in heads(rows) {
+                if let Ctor::Struct(_, names) = ctor {
+                    for field in names {
+                        if !fields.contains(field) {
+                            fields.push(field.clone());
+                        }
+                    }
+                }
+            }
+            Some(vec![Ctor::Struct(Some(name.clone()), fields)])
+        }
+        Type::Enum(name) => enums.get(name).map(|variants| {
+            variants
+                .iter()
+                .map(|(variant, types)| Ctor::Variant(name.clone(), variant.clone(), types.len()))
+                .collect()
+        }),
+        _ => None,
+    }
+}
+
+/// the parts of `row` if its first pattern matches values of shape `ctor`, followed by the rest
+/// of the row
+fn specialize(row: &[Pat], ctor: &Ctor) -> Option<Vec<Pat>> {
+    let mut parts = match &row[0] {
+        Pat::Wild => vec![Pat::Wild; ctor.arity()],
+        Pat::Ctor(head, parts) => match (ctor, head) {
+            (Ctor::List(n), Ctor::ListRest(k)) | (Ctor::ListRest(n), Ctor::ListRest(k))
+                if k <= n =>
+            {
+                let mut parts = parts.clone();
+                parts.resize(*n, Pat::Wild);
+                parts
+            }
+            (Ctor::Struct(name, fields), Ctor::Struct(head_name, head_fields))
+                if head_name.is_none() || head_name == name =>
+            {
+                fields
+                    .iter()
+                    .map(|field| match head_fields.iter().position(|f| f == field) {
+                        Some(idx) => parts[idx].clone(),
+                        None => Pat::Wild,
+                    })
+                    .collect()
+            }
+            (ctor, head) if ctor == head => parts.clone(),
+            _ => return None,
+        },
+    };
+
+    parts.extend_from_slice(&row[1..]);
+    Some(parts)
+}
+
+/// the values matched by `row` but by no row of `rows`, as one pattern for each column
+fn useful(rows: &[Vec<Pat>], row: &[Pat], types: &[Type], enums: &Enums) -> Vec<Vec<Pat>> {
+    if row.is_empty() {
+        return if rows.is_empty() {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let ctors = match &row[0] {
+        // a list with a rest matches every length from what it names up
+        Pat::Ctor(Ctor::ListRest(k), _) => list_lengths(rows, *k),
+        Pat::Ctor(ctor, _) => vec![ctor.clone()],
+        Pat::Wild => match signature(rows, &types[0], enums) {
+            Some(ctors) => ctors,
+            None => {
+                // values of other shapes are only matched by wildcards
+                let rest: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter(|r| r[0] == Pat::Wild)
+                    .map(|r| r[1..].to_vec())
+                    .collect();
+                return useful(&rest, &row[1..], &types[1..], enums)
+                    .into_iter()
+                    .map(|witness| std::iter::once(Pat::Wild).chain(witness).collect())
+                    .collect();
+            }
+        },
+    };
+
+    let mut witnesses = Vec::new();
+    for ctor in ctors {
+        let specialized: Vec<Vec<Pat>> = rows.iter().filter_map(|r| specialize(r, &ctor)).collect();
+        let row = match specialize(row, &ctor) {
+            Some(row) => row,
+            None => continue,
+        };
+        let mut part_types = ctor.part_types(&types[0], enums);
+        part_types.extend_from_slice(&types[1..]);
+
+        let arity = ctor.arity();
+        for mut witness in useful(&specialized, &row, &part_types, enums) {
+            let rest = witness.split_off(arity);
+            let mut rebuilt = vec![Pat::Ctor(ctor.clone(), witness)];
+            rebuilt.extend(rest);
+            witnesses.push(rebuilt);
+        }
+    }
+
+    witnesses
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ctor, parts) = match self {
+            Pat::Wild => return write!(f, "_"),
+            Pat::Ctor(ctor, parts) => (ctor, parts),
+        };
+        let parts: Vec<String> = parts.iter().map(Pat::to_string).collect();
+
+        match ctor {
+            Ctor::Boolean(b) => write!(f, "{}", b),
+            Ctor::Number(n) => write!(f, "{}", n),
+            Ctor::String(s) => write!(f, "{:?}", s),
+            Ctor::Tuple(1) => write!(f, "({},)", parts[0]),
+            Ctor::Tuple(_) => write!(f, "({})", parts.join(", ")),
+            Ctor::List(_) => write!(f, "[{}]", parts.join(", ")),
+            Ctor::ListRest(0) => write!(f, "[..]"),
+            Ctor::ListRest(_) => write!(f, "[{}, ..]", parts.join(", ")),
+            Ctor::Struct(name, fields) => {
+                if let Some(name) = name {
+                    write!(f, "{} ", name)?;
+                }
+                let fields: Vec<String> = fields
+                    .iter()
+                    .zip(&parts)
+                    .map(|(field, part)| format!("{}: {}", field, part))
+                    .collect();
+                if fields.is_empty() {
+                    write!(f, "{{}}")
+                } else {
+                    write!(f, "{{ {} }}", fields.join(", "))
+                }
+            }
+            Ctor::Variant(name, variant, 0) => write!(f, "{}::{}", name, variant),
+            Ctor::Variant(name, variant, _) => {
+                write!(f, "{}::{}({})", name, variant, parts.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parse::expr::FieldPattern;
+
+    use super::*;
+
+    fn pattern(node: Pattern) -> Spanned<Pattern> {
+        Spanned::new(node, (0, 0))
+    }
+
+    fn boolean(b: bool) -> Spanned<Pattern> {
+        pattern(Pattern::Boolean(b))
+    }
+
+    fn wild() -> Spanned<Pattern> {
+        pattern(Pattern::Wildcard)
+    }
+
+    fn list(items: Vec<Spanned<Pattern>>, rest: bool) -> Spanned<Pattern> {
+        pattern(Pattern::List {
+            items,
+            rest: rest.then(|| Box::new(wild())),
+        })
+    }
+
+    /// the coverage of `arms`, with each arm's span being its index
+    fn check(mut arms: Vec<Spanned<Pattern>>, ty: Type) -> Coverage {
+        for (idx, arm) in arms.iter_mut().enumerate() {
+            arm.span = (idx, idx);
+        }
+        coverage(&arms, &ty, &Enums::new())
+    }
+
+    #[test]
+    fn names_missing_booleans() {
+        assert_eq!(
+            check(vec![boolean(true)], Type::Boolean).missing,
+            vec!["false"]
+        );
+        assert!(check(vec![boolean(true), boolean(false)], Type::Boolean).is_exhaustive());
+        // without knowing the type, the value may not be a boolean at all
+        assert_eq!(
+            check(vec![boolean(true), boolean(false)], Type::Any).missing,
+            vec!["_"]
+        );
+    }
+
+    #[test]
+    fn names_missing_tuples() {
+        let arms = vec![
+            pattern(Pattern::Tuple(vec![boolean(true), wild()])),
+            pattern(Pattern::Tuple(vec![wild(), boolean(false)])),
+        ];
+
+        assert_eq!(
+            check(arms, Type::Tuple(vec![Type::Boolean, Type::Boolean])).missing,
+            vec!["(false, true)"]
+        );
+    }
+
+    #[test]
+    fn covers_list_lengths() {
+        let ty = Type::list(Type::Number);
+
+        assert!(check(
+            vec![list(vec![], false), list(vec![wild()], true)],
+            ty.clone()
+        )
+        .is_exhaustive());
+        assert_eq!(
+            check(
+                vec![list(vec![], false), list(vec![wild()], false)],
+                ty.clone()
+            )
+            .missing,
+            vec!["[_, _, ..]"]
+        );
+        assert_eq!(
+            check(vec![list(vec![wild()], true)], ty).missing,
+            vec!["[]"]
+        );
+    }
+
+    #[test]
+    fn covers_struct_fields() {
+        let point = |x| {
+            pattern(Pattern::Struct {
+                name: Some(String::from("Point")),
+                fields: vec![FieldPattern {
+                    name: String::from("x"),
+                    pattern: x,
+                }],
+            })
+        };
+        let ty = Type::Struct(String::from("Point"));
+
+        assert!(check(vec![point(boolean(true)), point(wild())], ty.clone()).is_exhaustive());
+        // the types of fields aren't known, so they can't be covered without a wildcard
+        assert_eq!(
+            check(vec![point(boolean(true)), point(boolean(false))], ty).missing,
+            vec!["Point { x: _ }"]
+        );
+    }
+
+    #[test]
+    fn names_missing_variants() {
+        let variant = |name: &str, fields| {
+            pattern(Pattern::Variant {
+                name: String::from("Shape"),
+                variant: name.to_string(),
+                fields,
+            })
+        };
+        let mut enums = Enums::new();
+        enums.insert(
+            String::from("Shape"),
+            vec![
+                (String::from("Circle"), vec![Type::Number]),
+                (String::from("Flag"), vec![Type::Boolean]),
+                (String::from("Empty"), Vec::new()),
+            ],
+        );
+        let ty = Type::Enum(String::from("Shape"));
+
+        let arms = vec![
+            variant("Flag", vec![boolean(true)]),
+            variant("Empty", Vec::new()),
+            variant("Empty", Vec::new()),
+        ];
+        let found = coverage(&arms, &ty, &enums);
+        assert_eq!(
+            found.missing,
+            vec!["Shape::Circle(_)", "Shape::Flag(false)"]
+        );
+        assert_eq!(found.unreachable, vec![arms[2].span]);
+
+        let arms = vec![variant("Circle", vec![wild()]), wild()];
+        assert!(coverage(&arms, &ty, &enums).is_exhaustive());
+    }
+
+    #[test]
+    fn finds_unreachable_arms() {
+        let numbers = vec![
+            pattern(Pattern::Number(1.0)),
+            pattern(Pattern::Number(2.0)),
+            pattern(Pattern::Number(1.0)),
+        ];
+        let coverage = check(numbers, Type::Number);
+        assert_eq!(coverage.missing, vec!["_"]);
+        assert_eq!(coverage.unreachable, vec![(2, 2)]);
+
+        let arms = vec![boolean(true), boolean(false), wild()];
+        assert_eq!(check(arms, Type::Boolean).unreachable, vec![(2, 2)]);
+        let lists = vec![list(vec![], true), list(vec![wild()], false)];
+        assert_eq!(
+            check(lists, Type::list(Type::Any)).unreachable,
+            vec![(1, 1)]
+        );
+    }
+}
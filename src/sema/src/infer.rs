@@ -0,0 +1,1239 @@
+//! Type inference, in the style of Hindley–Milner.
+//!
+//! Every expression gets a type, starting as a variable where nothing is known yet, and variables
+//! are unified with the types they're used as. Functions and closures bound with `let` are
+//! generalized, so each use of them can be given different types. Annotations are honoured, and
+//! names in a function's signature that aren't types are generic parameters of that function.
+//!
+//! Code without annotations is still accepted where it's valid when run: `Any` stands for a value
+//! of any type, like a builtin taking anything or a list of mixed items, and only problems that
+//! would fail when the program runs are reported, such as subtracting a string. Reassigning a
+//! variable to a value of another type is only reported when the variable has an annotation.
+//!
+//! Once every type is inferred, the arms of each `match` are checked against the type of the value
+//! it matches, since that may only be known from code after it.
+
+use std::collections::HashMap;
+
+use lex::{suggest::closest, trace, Span, TokenType};
+use parse::{
+    expr::{Node, Op, Param, Pattern, Spanned, TypeExpr, Variant},
+    visit::{walk_children, Visitor},
+};
+
+use crate::{
+    error::{MatchError, TypeError},
+    exhaustive::coverage,
+    types::{Enums, Type},
+    DeclId, DeclKind, Resolution,
+};
+
+/// the types found in a program
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Typing {
+    /// the type of every expression, by its span, with `Any` for whatever couldn't be inferred
+    pub expressions: HashMap<Span, Type>,
+    /// every problem found, in source order
+    pub errors: Vec<TypeError>,
+    /// every `match` missing patterns or with arms it can't reach, in source order
+    pub matches: Vec<MatchError>,
+}
+
+impl Typing {
+    /// the type of the expression at `span`
+    pub fn type_of(&self, span: Span) -> Option<&Type> {
+        self.expressions.get(&span)
+    }
+}
+
+pub(crate) fn infer(program: &[Spanned<Node>], resolution: &Resolution) -> Typing {
+    let mut checker = Checker {
+        resolution,
+        declarations: resolution
+            .declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, decl)| decl.kind != DeclKind::Builtin)
+            .map(|(idx, decl)| (decl.span, DeclId(idx)))
+            .collect(),
+        structs: resolution
+            .declarations
+            .iter()
+            .filter(|decl| decl.kind == DeclKind::Struct)
+            .map(|decl| decl.name.clone())
+            .collect(),
+        enums: Enums::new(),
+        bindings: Vec::new(),
+        levels: Vec::new(),
+        trail: Vec::new(),
+        level: 0,
+        schemes: HashMap::new(),
+        annotations: HashMap::new(),
+        generics: Vec::new(),
+        returns: Vec::new(),
+        closures: HashMap::new(),
+        matches: Vec::new(),
+        expressions: HashMap::new(),
+        errors: Vec::new(),
+    };
+
+    // enums are known everywhere, since a pattern may name one declared later
+    let mut declared = Declared(Vec::new());
+    for node in program {
+        declared.visit_node(node);
+    }
+    checker.declare_enums(declared.0);
+
+    for node in program {
+        trace::at(node.span);
+        checker.infer(node);
+    }
+
+    let mut matches = Vec::new();
+    for (span, arms, ty) in std::mem::take(&mut checker.matches) {
+        let ty = checker.resolve(&ty).map(&mut |ty| match ty {
+            Type::Var(_) => Type::Any,
+            ty => ty,
+        });
+        let coverage = coverage(&arms, &ty, &checker.enums);
+        if !coverage.is_exhaustive() {
+            matches.push(MatchError::NonExhaustive {
+                span,
+                missing: coverage.missing,
+            });
+        }
+        matches.extend(
+            coverage
+                .unreachable
+                .into_iter()
+                .map(|span| MatchError::UnreachableArm { span }),
+        );
+    }
+    matches.sort_by_key(MatchError::span);
+
+    let mut expressions = std::mem::take(&mut checker.expressions);
+    for ty in expressions.values_mut() {
+        *ty = checker.resolve(ty).map(&mut |ty| match ty {
+            Type::Var(_) => Type::Any,
+            ty => ty,
+        });
+    }
+    checker.errors.sort_by_key(TypeError::span);
+
+    Typing {
+        expressions,
+        errors: checker.errors,
+        matches,
+    }
+}
+
+/// the name and variants of every enum in a program
+struct Declared(Vec<(String, Vec<Variant>)>);
+
+impl Visitor for Declared {
+    fn visit_enum(&mut self, node: &Spanned<Node>) {
+        if let Node::Enum { name, variants, .. } = &node.node {
+            if let Node::Identifier(name) = &name.node {
+                self.0.push((name.clone(), variants.clone()));
+            }
+        }
+        walk_children(self, node);
+    }
+}
+
+/// a type that may be generic over some variables and parameters, each replaced with a new
+/// variable wherever it's used
+struct Scheme {
+    vars: Vec<usize>,
+    params: Vec<String>,
+    ty: Type,
+}
+
+impl Scheme {
+    fn mono(ty: Type) -> Scheme {
+        Scheme {
+            vars: Vec::new(),
+            params: Vec::new(),
+            ty,
+        }
+    }
+}
+
+/// a change to a variable, undone if the unification making it fails
+enum Change {
+    Bound(usize),
+    Level(usize, u32),
+}
+
+/// a function's generic parameters, by name
+type Generics = HashMap<String, Type>;
+
+struct Checker<'a> {
+    resolution: &'a Resolution,
+    /// every declaration but the builtins, by the span of its name
+    declarations: HashMap<Span, DeclId>,
+    /// the names of structs, which can be used as types
+    structs: Vec<String>,
+    /// the variants of every enum, which can be used as types too
+    enums: Enums,
+
+    /// what each variable is bound to, once it's known
+    bindings: Vec<Option<Type>>,
+    /// how deeply nested in `let` values each variable was made, so generalizing a value only
+    /// takes the variables made while inferring it
+    levels: Vec<u32>,
+    trail: Vec<Change>,
+    level: u32,
+
+    schemes: HashMap<DeclId, Scheme>,
+    /// the annotation of each declaration that has one
+    annotations: HashMap<DeclId, Span>,
+    /// the generic parameters of the function signatures being checked, the innermost last
+    generics: Vec<Generics>,
+    /// the return type of the functions being checked, with what it's expected because of
+    returns: Vec<(Type, Span)>,
+    /// the generic parameters named in each closure's signature, by the closure's span
+    closures: HashMap<Span, Vec<String>>,
+
+    /// the value matched, the pattern of each arm and the value's type, for each `match`
+    matches: Vec<(Span, Vec<Spanned<Pattern>>, Type)>,
+
+    expressions: HashMap<Span, Type>,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> Checker<'a> {
+    /// records the types each variant holds, once every enum's name is known to be a type
+    fn declare_enums(&mut self, enums: Vec<(String, Vec<Variant>)>) {
+        for (name, _) in &enums {
+            self.enums.insert(name.clone(), Vec::new());
+        }
+
+        for (name, variants) in enums {
+            let variants = variants
+                .iter()
+                .map(|variant| {
+                    let types = variant
+                        .fields
+                        .iter()
+                        .map(|ty| self.annotation(ty, false))
+                        .collect();
+                    match &variant.name.node {
+                        Node::Identifier(name) => (name.clone(), types),
+                        _ => (String::new(), types),
+                    }
+                })
+                .collect();
+            self.enums.insert(name, variants);
+        }
+    }
+
+    /// the types the variant `variant` of the enum `name` holds, reporting it at `span` if
+    /// there's no such variant
+    fn variant(&mut self, name: &str, variant: &str, span: Span) -> Option<Vec<Type>> {
+        let variants = match self.enums.get(name) {
+            Some(variants) => variants,
+            None => {
+                self.errors.push(TypeError::UnknownType {
+                    name: name.to_string(),
+                    span,
+                });
+                return None;
+            }
+        };
+
+        match variants.iter().find(|(v, _)| v == variant) {
+            Some((_, types)) => Some(types.clone()),
+            None => {
+                let similar = closest(variant, variants.iter().map(|(v, _)| v.as_str()));
+                self.errors.push(TypeError::UnknownVariant {
+                    name: name.to_string(),
+                    variant: variant.to_string(),
+                    span,
+                    similar: similar.map(str::to_string),
+                });
+                None
+            }
+        }
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.fresh_at(self.level)
+    }
+
+    fn fresh_at(&mut self, level: u32) -> Type {
+        self.bindings.push(None);
+        self.levels.push(level);
+        Type::Var(self.bindings.len() - 1)
+    }
+
+    /// `ty` with the variables at its top level replaced by what they're bound to
+    fn shallow(&self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        while let Type::Var(var) = ty {
+            match &self.bindings[var] {
+                Some(bound) => ty = bound.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    /// `ty` with every bound variable replaced by what it's bound to
+    fn resolve(&self, ty: &Type) -> Type {
+        ty.map(&mut |ty| match ty {
+            Type::Var(_) => {
+                let bound = self.shallow(&ty);
+                if bound == ty {
+                    ty
+                } else {
+                    self.resolve(&bound)
+                }
+            }
+            ty => ty,
+        })
+    }
+
+    fn bind(&mut self, var: usize, ty: Type) -> Result<(), ()> {
+        let ty = self.resolve(&ty);
+        let mut vars = Vec::new();
+        ty.walk(&mut |ty| {
+            if let Type::Var(inner) = ty {
+                vars.push(*inner);
+            }
+        });
+        if vars.contains(&var) {
+            return Err(());
+        }
+
+        // the type can only be generalized where the variable could be
+        let level = self.levels[var];
+        for inner in vars {
+            if self.levels[inner] > level {
+                self.trail.push(Change::Level(inner, self.levels[inner]));
+                self.levels[inner] = level;
+            }
+        }
+
+        self.trail.push(Change::Bound(var));
+        self.bindings[var] = Some(ty);
+        Ok(())
+    }
+
+    fn unify_inner(&mut self, a: &Type, b: &Type) -> Result<(), ()> {
+        match (self.shallow(a), self.shallow(b)) {
+            (Type::Any, _) | (_, Type::Any) => Ok(()),
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => self.bind(var, ty),
+            (Type::List(a), Type::List(b)) => self.unify_inner(&a, &b),
+            (Type::Map(ak, av), Type::Map(bk, bv)) => {
+                self.unify_inner(&ak, &bk)?;
+                self.unify_inner(&av, &bv)
+            }
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => a
+                .iter()
+                .zip(&b)
+                .try_for_each(|(a, b)| self.unify_inner(a, b)),
+            (Type::Fn(ap, ar), Type::Fn(bp, br)) if ap.len() == bp.len() => {
+                ap.iter()
+                    .zip(&bp)
+                    .try_for_each(|(a, b)| self.unify_inner(a, b))?;
+                self.unify_inner(&ar, &br)
+            }
+            (a, b) if a == b => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// makes `a` and `b` the same type, returning false and changing nothing if they can't be
+    fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let mark = self.trail.len();
+        if self.unify_inner(a, b).is_ok() {
+            return true;
+        }
+
+        while self.trail.len() > mark {
+            match self.trail.pop() {
+                Some(Change::Bound(var)) => self.bindings[var] = None,
+                Some(Change::Level(var, level)) => self.levels[var] = level,
+                None => {}
+            }
+        }
+        false
+    }
+
+    /// unifies the type `found` of the value at `span` with `expected`, reporting a mismatch
+    /// blamed on the code at `cause` if they can't be
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span, cause: Span) {
+        if !self.unify(expected, found) {
+            self.errors.push(TypeError::Mismatch {
+                expected: self.resolve(expected),
+                found: self.resolve(found),
+                span,
+                cause,
+            });
+        }
+    }
+
+    fn generalize(&self, ty: &Type, params: Vec<String>) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut vars = Vec::new();
+        ty.walk(&mut |ty| {
+            if let Type::Var(var) = ty {
+                if self.levels[*var] > self.level && !vars.contains(var) {
+                    vars.push(*var);
+                }
+            }
+        });
+
+        Scheme { vars, params, ty }
+    }
+
+    /// keeps the variables in `ty` from being generalized by an enclosing `let`
+    fn lower(&mut self, ty: &Type) {
+        let ty = self.resolve(ty);
+        ty.walk(&mut |ty| {
+            if let Type::Var(var) = ty {
+                self.levels[*var] = self.levels[*var].min(self.level);
+            }
+        });
+    }
+
+    fn instantiate(&mut self, id: DeclId) -> Type {
+        let scheme = match self.schemes.get(&id) {
+            Some(scheme) => scheme,
+            None => {
+                // read before it's declared, as functions can, so it can't be generic here
+                let ty = self.fresh_at(0);
+                self.schemes.insert(id, Scheme::mono(ty.clone()));
+                return ty;
+            }
+        };
+        if scheme.vars.is_empty() && scheme.params.is_empty() {
+            return scheme.ty.clone();
+        }
+
+        let (vars, params, ty) = (
+            scheme.vars.clone(),
+            scheme.params.clone(),
+            scheme.ty.clone(),
+        );
+        let mut fresh: HashMap<usize, Type> = HashMap::new();
+        for var in vars {
+            fresh.insert(var, self.fresh());
+        }
+        let mut named: HashMap<String, Type> = HashMap::new();
+        for param in params {
+            named.insert(param, self.fresh());
+        }
+
+        ty.map(&mut |ty| match &ty {
+            Type::Var(var) => fresh.get(var).cloned().unwrap_or(ty),
+            Type::Param(name) => named.get(name).cloned().unwrap_or(ty),
+            _ => ty,
+        })
+    }
+
+    /// gives the declaration named at `span` its type, which must agree with any uses before it
+    fn declare(&mut self, span: Span, ty: Type, scheme: Option<Vec<String>>) {
+        let id = match self.declarations.get(&span) {
+            Some(id) => *id,
+            None => return,
+        };
+
+        if let Some(earlier) = self.schemes.get(&id) {
+            let earlier = earlier.ty.clone();
+            self.expect(&earlier, &ty, span, span);
+        }
+
+        let scheme = match scheme {
+            Some(params) => self.generalize(&ty, params),
+            None => {
+                self.lower(&ty);
+                Scheme::mono(ty)
+            }
+        };
+        self.schemes.insert(id, scheme);
+    }
+
+    /// binds the names in `pattern` to the parts of a value of type `ty`
+    fn bind_pattern(&mut self, pattern: &Spanned<Pattern>, ty: &Type, annotation: Option<Span>) {
+        match &pattern.node {
+            Pattern::Identifier(_) => {
+                if let (Some(annotation), Some(id)) =
+                    (annotation, self.declarations.get(&pattern.span))
+                {
+                    self.annotations.insert(*id, annotation);
+                }
+                self.declare(pattern.span, ty.clone(), None);
+            }
+            Pattern::Wildcard => {}
+            Pattern::Number(_) => self.expect(&Type::Number, ty, pattern.span, pattern.span),
+            Pattern::String(_) => self.expect(&Type::String, ty, pattern.span, pattern.span),
+            Pattern::Boolean(_) => self.expect(&Type::Boolean, ty, pattern.span, pattern.span),
+            Pattern::Tuple(items) => {
+                let parts: Vec<Type> = match self.shallow(ty) {
+                    Type::Any => vec![Type::Any; items.len()],
+                    _ => {
+                        let parts: Vec<Type> = items.iter().map(|_| self.fresh()).collect();
+                        self.expect(&Type::Tuple(parts.clone()), ty, pattern.span, pattern.span);
+                        parts
+                    }
+                };
+                for (item, part) in items.iter().zip(&parts) {
+                    self.bind_pattern(item, part, None);
+                }
+            }
+            Pattern::List { items, rest } => {
+                let item_type = match self.shallow(ty) {
+                    Type::Any => Type::Any,
+                    _ => {
+                        let item_type = self.fresh();
+                        self.expect(
+                            &Type::list(item_type.clone()),
+                            ty,
+                            pattern.span,
+                            pattern.span,
+                        );
+                        item_type
+                    }
+                };
+                for item in items {
+                    self.bind_pattern(item, &item_type, None);
+                }
+                if let Some(rest) = rest {
+                    self.bind_pattern(rest, &Type::list(item_type), None);
+                }
+            }
+            // struct fields aren't typed
+            Pattern::Struct { fields, .. } => {
+                for field in fields {
+                    self.bind_pattern(&field.pattern, &Type::Any, None);
+                }
+            }
+            Pattern::Variant {
+                name,
+                variant,
+                fields,
+            } => {
+                let mut types = vec![Type::Any; fields.len()];
+                if let Some(declared) = self.variant(name, variant, pattern.span) {
+                    let expected = Type::Enum(name.clone());
+                    self.expect(&expected, ty, pattern.span, pattern.span);
+                    if declared.len() == fields.len() {
+                        types = declared;
+                    } else {
+                        self.errors.push(TypeError::WrongArity {
+                            expected: declared.len(),
+                            found: fields.len(),
+                            span: pattern.span,
+                        });
+                    }
+                }
+                for (field, ty) in fields.iter().zip(&types) {
+                    self.bind_pattern(field, ty, None);
+                }
+            }
+        }
+    }
+
+    /// the type an annotation names
+    fn annotation(&mut self, ty: &Spanned<TypeExpr>, signature: bool) -> Type {
+        match &ty.node {
+            TypeExpr::Fn { params, ret } => {
+                let params = params
+                    .iter()
+                    .map(|param| self.annotation(param, signature))
+                    .collect();
+                let ret = match ret {
+                    Some(ret) => self.annotation(ret, signature),
+                    None => Type::Nil,
+                };
+                Type::function(params, ret)
+            }
+            TypeExpr::Named { name, generics } => {
+                let mut args: Vec<Type> = generics
+                    .iter()
+                    .map(|arg| self.annotation(arg, signature))
+                    .collect();
+                let expected = match name.as_str() {
+                    "Any" | "Nil" | "Number" | "String" | "Boolean" => 0,
+                    "List" => 1,
+                    "Map" => 2,
+                    "Tuple" => args.len(),
+                    _ if self.structs.contains(name) || self.enums.contains_key(name) => 0,
+                    _ => return self.generic(name, ty.span, signature),
+                };
+
+                // `List` and `Map` on their own hold anything
+                if args.is_empty() && expected > 0 {
+                    args = vec![Type::Any; expected];
+                } else if args.len() != expected {
+                    self.errors.push(TypeError::TypeArguments {
+                        name: name.clone(),
+                        expected,
+                        found: args.len(),
+                        span: ty.span,
+                    });
+                    return Type::Any;
+                }
+
+                match name.as_str() {
+                    "Any" => Type::Any,
+                    "Nil" => Type::Nil,
+                    "Number" => Type::Number,
+                    "String" => Type::String,
+                    "Boolean" => Type::Boolean,
+                    "List" => Type::list(args.remove(0)),
+                    "Map" => Type::Map(Box::new(args.remove(0)), Box::new(args.remove(0))),
+                    "Tuple" => Type::Tuple(args),
+                    _ if self.enums.contains_key(name) => Type::Enum(name.clone()),
+                    _ => Type::Struct(name.clone()),
+                }
+            }
+        }
+    }
+
+    /// a generic parameter of an enclosing function, or a new one of the function whose
+    /// `signature` is being read
+    fn generic(&mut self, name: &str, span: Span, signature: bool) -> Type {
+        if let Some(ty) = self.generics.iter().rev().find_map(|g| g.get(name)) {
+            return ty.clone();
+        }
+
+        match self.generics.last_mut() {
+            Some(generics) if signature => {
+                let ty = Type::Param(name.to_string());
+                generics.insert(name.to_string(), ty.clone());
+                ty
+            }
+            _ => {
+                self.errors.push(TypeError::UnknownType {
+                    name: name.to_string(),
+                    span,
+                });
+                Type::Any
+            }
+        }
+    }
+
+    /// the type of a function or closure, with the generic parameters its signature names
+    fn function(
+        &mut self,
+        args: &[Param],
+        ret: Option<&Spanned<TypeExpr>>,
+        body: &Spanned<Node>,
+    ) -> (Type, Vec<String>) {
+        self.generics.push(Generics::new());
+        self.level += 1;
+
+        let mut params = Vec::new();
+        for arg in args {
+            let ty = match &arg.ty {
+                Some(ty) => self.annotation(ty, true),
+                None => self.fresh(),
+            };
+            self.bind_pattern(&arg.pattern, &ty, arg.ty.as_ref().map(|ty| ty.span));
+            params.push(ty);
+        }
+        let (ret_type, cause) = match ret {
+            Some(ret) => (self.annotation(ret, true), ret.span),
+            None => (self.fresh(), body.span),
+        };
+
+        self.returns.push((ret_type.clone(), cause));
+        let found = self.infer(body);
+        match &body.node {
+            Node::Block {
+                value: Some(value), ..
+            } => self.expect(&ret_type, &found, value.span, cause),
+            // a body ending in `return` only gives back the values returned
+            Node::Block {
+                body: statements, ..
+            } if statements
+                .iter()
+                .any(|statement| matches!(statement.node, Node::Return(_))) => {}
+            _ => self.expect(&ret_type, &found, body.span, cause),
+        }
+        self.returns.pop();
+
+        self.level -= 1;
+        let generics = self.generics.pop().unwrap_or_default();
+        (
+            Type::function(params, ret_type),
+            generics.into_keys().collect(),
+        )
+    }
+
+    /// the type of a builtin, with fresh variables for the generic ones
+    fn builtin(&mut self, name: &str) -> Type {
+        match name {
+            "len" => Type::function(vec![Type::Any], Type::Number),
+            "map" => {
+                let (item, mapped) = (self.fresh(), self.fresh());
+                Type::function(
+                    vec![
+                        Type::list(item.clone()),
+                        Type::function(vec![item], mapped.clone()),
+                    ],
+                    Type::list(mapped),
+                )
+            }
+            "filter" => {
+                let item = self.fresh();
+                Type::function(
+                    vec![
+                        Type::list(item.clone()),
+                        Type::function(vec![item.clone()], Type::Boolean),
+                    ],
+                    Type::list(item),
+                )
+            }
+            // `print` and `list` take any number of arguments
+            _ => Type::Any,
+        }
+    }
+
+    /// the type of a list's items, or `Any` if they aren't all of one type
+    fn items<'n>(&mut self, items: impl Iterator<Item = &'n Spanned<Node>>) -> Type {
+        let item_type = self.fresh();
+        let mut mixed = false;
+        for item in items {
+            let ty = self.infer(item);
+            mixed |= !self.unify(&item_type, &ty);
+        }
+
+        if mixed {
+            Type::Any
+        } else {
+            item_type
+        }
+    }
+
+    /// the result of `lhs operation rhs`, given the type and span of each operand and of the
+    /// whole operation, at `span`
+    fn binary(&mut self, span: Span, operation: &Op, lhs: (Type, Span), rhs: (Type, Span)) -> Type {
+        let ((lt, lhs_span), (rt, rhs_span)) = (lhs, rhs);
+        match operation {
+            Op::Equal | Op::NotEqual | Op::StrictEqual | Op::NotStrictEqual => Type::Boolean,
+            Op::And | Op::Or => {
+                self.expect(&Type::Boolean, &lt, lhs_span, span);
+                self.expect(&Type::Boolean, &rt, rhs_span, span);
+                Type::Boolean
+            }
+            Op::Subtract | Op::Multiply | Op::Divide => {
+                self.expect(&Type::Number, &lt, lhs_span, span);
+                self.expect(&Type::Number, &rt, rhs_span, span);
+                Type::Number
+            }
+            // numbers or strings, decided by whichever operand's type is known
+            Op::Add | Op::Greater | Op::GreaterEqual | Op::Less | Op::LessEqual => {
+                let operand = match (self.shallow(&lt), self.shallow(&rt)) {
+                    (Type::String, _) => Some((Type::String, &rt, rhs_span, lhs_span)),
+                    (_, Type::String) => Some((Type::String, &lt, lhs_span, rhs_span)),
+                    (Type::Number, _) => Some((Type::Number, &rt, rhs_span, lhs_span)),
+                    (_, Type::Number) => Some((Type::Number, &lt, lhs_span, rhs_span)),
+                    _ => None,
+                };
+                let operand = operand.map(|(expected, found, span, cause)| {
+                    let found = found.clone();
+                    self.expect(&expected, &found, span, cause);
+                    expected
+                });
+
+                match (operation, operand) {
+                    (Op::Add, Some(operand)) => operand,
+                    (Op::Add, None) => Type::Any,
+                    _ => Type::Boolean,
+                }
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        node: &Spanned<Node>,
+        function: &Spanned<Node>,
+        args: &[Spanned<Node>],
+    ) -> Type {
+        let callee = self.infer(function);
+        let found: Vec<Type> = args.iter().map(|arg| self.infer(arg)).collect();
+
+        match self.shallow(&callee) {
+            Type::Any => Type::Any,
+            Type::Fn(params, ret) => {
+                if params.len() != args.len() {
+                    self.errors.push(TypeError::WrongArity {
+                        expected: params.len(),
+                        found: args.len(),
+                        span: node.span,
+                    });
+                    return *ret;
+                }
+
+                for ((param, arg), ty) in params.iter().zip(args).zip(&found) {
+                    self.expect(param, ty, arg.span, function.span);
+                }
+                *ret
+            }
+            Type::Var(_) => {
+                let ret = self.fresh();
+                let expected = Type::function(found, ret.clone());
+                self.expect(&expected, &callee, function.span, node.span);
+                ret
+            }
+            other => {
+                self.errors.push(TypeError::NotCallable {
+                    ty: self.resolve(&other),
+                    span: function.span,
+                });
+                Type::Any
+            }
+        }
+    }
+
+    fn index(&mut self, target: &Spanned<Node>, index: &Spanned<Node>) -> Type {
+        let (target_type, index_type) = (self.infer(target), self.infer(index));
+        match self.shallow(&target_type) {
+            Type::List(item) => {
+                self.expect(&Type::Number, &index_type, index.span, target.span);
+                *item
+            }
+            Type::String => {
+                self.expect(&Type::Number, &index_type, index.span, target.span);
+                Type::String
+            }
+            Type::Map(key, value) => {
+                self.expect(&key, &index_type, index.span, target.span);
+                *value
+            }
+            Type::Any | Type::Var(_) => Type::Any,
+            other => {
+                self.errors.push(TypeError::NotIndexable {
+                    ty: self.resolve(&other),
+                    span: target.span,
+                });
+                Type::Any
+            }
+        }
+    }
+
+    fn infer(&mut self, node: &Spanned<Node>) -> Type {
+        let ty = self.infer_node(node);
+        self.expressions.insert(node.span, ty.clone());
+        ty
+    }
+
+    fn infer_node(&mut self, node: &Spanned<Node>) -> Type {
+        match &node.node {
+            Node::Variable {
+                pattern, ty, value, ..
+            } => {
+                self.level += 1;
+                let found = self.infer(value);
+                self.level -= 1;
+
+                let declared = match ty {
+                    Some(ty) => {
+                        let annotated = self.annotation(ty, false);
+                        self.expect(&annotated, &found, value.span, ty.span);
+                        annotated
+                    }
+                    None => found,
+                };
+
+                // only functions are generalized, as other values can't be used as more than
+                // one type each
+                match (&pattern.node, &value.node, ty) {
+                    (Pattern::Identifier(_), Node::Closure { .. }, None) => {
+                        let generics = self.closures.remove(&value.span).unwrap_or_default();
+                        self.declare(pattern.span, declared, Some(generics))
+                    }
+                    _ => self.bind_pattern(pattern, &declared, ty.as_ref().map(|ty| ty.span)),
+                }
+                Type::Nil
+            }
+            Node::Fn {
+                name,
+                args,
+                ret,
+                body,
+                ..
+            } => {
+                let (ty, generics) = self.function(args, ret.as_ref(), body);
+                self.declare(name.span, ty, Some(generics));
+                Type::Nil
+            }
+            Node::Closure { args, ret, body } => {
+                let (ty, generics) = self.function(args, ret.as_ref(), body);
+                self.closures.insert(node.span, generics);
+                ty
+            }
+            Node::FnCall { function, args } => self.call(node, function, args),
+            Node::Block { body, value } => {
+                for statement in body {
+                    self.infer(statement);
+                }
+                match value {
+                    Some(value) => self.infer(value),
+                    None => Type::Nil,
+                }
+            }
+            Node::Return(value) => {
+                let (found, span) = match value {
+                    Some(value) => (self.infer(value), value.span),
+                    None => (Type::Nil, node.span),
+                };
+                if let Some((expected, cause)) = self.returns.last().cloned() {
+                    self.expect(&expected, &found, span, cause);
+                }
+                Type::Any
+            }
+            Node::Struct { name, fields, .. } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| match &field.ty {
+                        Some(ty) => self.annotation(ty, false),
+                        None => Type::Any,
+                    })
+                    .collect();
+                let struct_name = match &name.node {
+                    Node::Identifier(name) => name.clone(),
+                    _ => String::new(),
+                };
+                self.declare(
+                    name.span,
+                    Type::function(fields, Type::Struct(struct_name)),
+                    None,
+                );
+                Type::Nil
+            }
+            // an enum's name only prefixes its variants
+            Node::Enum { name, .. } => {
+                self.declare(name.span, Type::Any, None);
+                Type::Nil
+            }
+            Node::Path { name, variant } => {
+                let enum_name = match (&name.node, self.resolution.resolved(name.span)) {
+                    (Node::Identifier(enum_name), Some(decl)) if decl.kind == DeclKind::Enum => {
+                        enum_name.clone()
+                    }
+                    // undefined names are reported by the resolver, and other values are checked
+                    // when the program runs
+                    _ => return Type::Any,
+                };
+
+                match self.variant(&enum_name, &variant.node, variant.span) {
+                    Some(types) if types.is_empty() => Type::Enum(enum_name),
+                    Some(types) => Type::function(types, Type::Enum(enum_name)),
+                    None => Type::Any,
+                }
+            }
+            Node::Match { value, arms } => {
+                let scrutinee = self.infer(value);
+                let result = self.fresh();
+                let mut mixed = false;
+                for arm in arms {
+                    self.bind_pattern(&arm.pattern, &scrutinee, None);
+                    let ty = self.infer(&arm.value);
+                    mixed |= !self.unify(&result, &ty);
+                }
+
+                let patterns = arms.iter().map(|arm| arm.pattern.clone()).collect();
+                self.matches.push((value.span, patterns, scrutinee));
+                // like a list's items, arms of different types make a value of any type
+                if mixed {
+                    Type::Any
+                } else {
+                    result
+                }
+            }
+            // other modules aren't checked, so what they export could be anything
+            Node::Import { alias, items, .. } => {
+                for name in alias.iter().map(|alias| &**alias).chain(items) {
+                    self.declare(name.span, Type::Any, None);
+                }
+                Type::Nil
+            }
+            Node::Attribute { .. } => Type::Nil,
+            Node::Assign {
+                operation,
+                target,
+                value,
+            } => {
+                let current = self.infer(target);
+                let mut found = self.infer(value);
+                if let Some(operation) = operation {
+                    let (lhs, rhs) = ((current.clone(), target.span), (found, value.span));
+                    found = self.binary(node.span, operation, lhs, rhs);
+                }
+
+                let annotation = self
+                    .resolution
+                    .uses
+                    .get(&target.span)
+                    .and_then(|id| self.annotations.get(id))
+                    .copied();
+                match annotation {
+                    Some(annotation) => self.expect(&current, &found, value.span, annotation),
+                    None => {
+                        self.unify(&current, &found);
+                    }
+                }
+                found
+            }
+            Node::BinaryExpr {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let lhs = (self.infer(lhs), lhs.span);
+                let rhs = (self.infer(rhs), rhs.span);
+                self.binary(node.span, operation, lhs, rhs)
+            }
+            Node::UnaryExpr { operation, body } => {
+                let found = self.infer(body);
+                let expected = match operation {
+                    TokenType::Not => Type::Boolean,
+                    _ => Type::Number,
+                };
+                self.expect(&expected, &found, body.span, node.span);
+                expected
+            }
+            Node::List(items) => Type::list(self.items(items.iter())),
+            Node::Tuple(items) => Type::Tuple(items.iter().map(|item| self.infer(item)).collect()),
+            Node::Map(entries) => {
+                let key = self.items(entries.iter().map(|entry| &entry.key));
+                let value = self.items(entries.iter().map(|entry| &entry.value));
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            Node::Index { target, index } => self.index(target, index),
+            Node::Slice { target, start, end } => {
+                let target_type = self.infer(target);
+                for bound in start.iter().chain(end) {
+                    let found = self.infer(bound);
+                    self.expect(&Type::Number, &found, bound.span, target.span);
+                }
+
+                match self.shallow(&target_type) {
+                    ty @ (Type::List(_) | Type::String) => ty,
+                    Type::Any | Type::Var(_) => Type::Any,
+                    other => {
+                        self.errors.push(TypeError::NotIndexable {
+                            ty: self.resolve(&other),
+                            span: target.span,
+                        });
+                        Type::Any
+                    }
+                }
+            }
+            Node::Number(_) => Type::Number,
+            Node::String(_) => Type::String,
+            Node::Boolean(_) => Type::Boolean,
+            Node::Identifier(_) => match self.resolution.uses.get(&node.span) {
+                Some(id) => {
+                    let decl = self.resolution.declaration(*id);
+                    if decl.kind == DeclKind::Builtin {
+                        let name = decl.name.clone();
+                        self.builtin(&name)
+                    } else {
+                        self.instantiate(*id)
+                    }
+                }
+                // undefined names are reported by the resolver
+                None => Type::Any,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use parse::parse;
+
+    use super::*;
+    use crate::resolve;
+
+    fn infer_source(src: &str) -> Typing {
+        let program = parse(tokenise(src).unwrap()).unwrap();
+        infer(&program, &resolve(&program))
+    }
+
+    /// the type of the last expression written as `expr` in `src`
+    fn type_of(src: &str, expr: &str) -> String {
+        let start = src.rfind(expr).unwrap();
+        let typing = infer_source(src);
+        assert_eq!(typing.errors, Vec::new());
+        typing
+            .type_of((start, start + expr.len()))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn infers_functions_and_lets() {
+        let src = "fn add(a, b) { a - b }\nlet n = add(1, 2);\nlet s = \"a\" + \"b\";\nadd";
+        assert_eq!(type_of(src, "add"), "fn(Number, Number) -> Number");
+        assert_eq!(type_of(src, "add(1, 2)"), "Number");
+        assert_eq!(type_of(src, "\"a\" + \"b\""), "String");
+    }
+
+    #[test]
+    fn generalizes_functions() {
+        let src = "fn id(x) { x }\nlet pair = (id(1), id(\"s\"));\nlet twice = |f, x| f(f(x));";
+        assert_eq!(type_of(src, "(id(1), id(\"s\"))"), "Tuple<Number, String>");
+        assert_eq!(
+            type_of("fn fact(n) { n * fact(n - 1) }", "fact(n - 1)"),
+            "Number"
+        );
+        assert_eq!(
+            type_of("let ys = map([1, 2], |x| x > 1);", "map([1, 2], |x| x > 1)"),
+            "List<Boolean>"
+        );
+    }
+
+    #[test]
+    fn reports_mismatches_with_their_cause() {
+        assert_eq!(
+            infer_source("let n: Number = \"a\";\nlet m = 2 - true;").errors,
+            vec![
+                TypeError::Mismatch {
+                    expected: Type::Number,
+                    found: Type::String,
+                    span: (16, 19),
+                    cause: (7, 13),
+                },
+                TypeError::Mismatch {
+                    expected: Type::Number,
+                    found: Type::Boolean,
+                    span: (33, 37),
+                    cause: (29, 37),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_generic_parameters() {
+        let src = "fn first(xs: List<T>) -> T { xs[0] }\nlet n: Number = first([1]);\nfn bad(x: T) -> T { x - 1 }";
+
+        assert_eq!(
+            infer_source(src).errors,
+            vec![
+                TypeError::Mismatch {
+                    expected: Type::Number,
+                    found: Type::Param(String::from("T")),
+                    span: (85, 86),
+                    cause: (85, 90),
+                },
+                TypeError::Mismatch {
+                    expected: Type::Param(String::from("T")),
+                    found: Type::Number,
+                    span: (85, 90),
+                    cause: (81, 82),
+                },
+            ]
+        );
+        assert_eq!(
+            infer_source("let x: Numbr = 1;").errors,
+            vec![TypeError::UnknownType {
+                name: String::from("Numbr"),
+                span: (7, 12),
+            }]
+        );
+    }
+
+    #[test]
+    fn checks_calls_and_returns() {
+        let src = "fn f(a) { a }\nf(1, 2);\nlet n = 1;\nn();\nfn g() -> Number { return \"a\"; }";
+
+        assert_eq!(
+            infer_source(src).errors,
+            vec![
+                TypeError::WrongArity {
+                    expected: 1,
+                    found: 2,
+                    span: (14, 21),
+                },
+                TypeError::NotCallable {
+                    ty: Type::Number,
+                    span: (34, 35),
+                },
+                TypeError::Mismatch {
+                    expected: Type::Number,
+                    found: Type::String,
+                    span: (65, 68),
+                    cause: (49, 55),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_matches_once_types_are_known() {
+        let src = "enum Shape { Circle(Number), Square(Number), Empty }
+fn area(s) {
+    match s {
+        Shape::Circle(r) => r * r,
+        Shape::Empty => 0,
+        _ => 1,
+        Shape::Square(x) => x,
+    }
+}
+let a = area(Shape::Circle(2));
+match true { true => 1 };";
+        let typing = infer_source(src);
+
+        assert_eq!(typing.errors, Vec::new());
+        assert_eq!(type_of(src, "area"), "fn(Shape) -> Number");
+        assert_eq!(
+            typing.matches,
+            vec![
+                MatchError::UnreachableArm { span: (166, 182) },
+                MatchError::NonExhaustive {
+                    span: (235, 239),
+                    missing: vec![String::from("false")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_variants() {
+        let src = "enum Shape { Circle(Number), Empty }\nlet s = Shape::Cirle(1);\nfn f(s) { match s { Shape::Circle(a, b) => a, Shape::Empty => \"\" } }";
+
+        assert_eq!(
+            infer_source(src).errors,
+            vec![
+                TypeError::UnknownVariant {
+                    name: String::from("Shape"),
+                    variant: String::from("Cirle"),
+                    span: (52, 57),
+                    similar: Some(String::from("Circle")),
+                },
+                TypeError::WrongArity {
+                    expected: 1,
+                    found: 2,
+                    span: (82, 101),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_untyped_code() {
+        let src = "let xs = [1, \"a\"];\nlet x = 1;\nx = \"b\";\nprint(len(xs), x);\nimport \"m\" { f };\nf(1) - 2;";
+
+        assert_eq!(type_of(src, "[1, \"a\"]"), "List<Any>");
+        assert_eq!(
+            infer_source("fn even(n) { odd(n) }\nfn odd(n) { even(n) }").errors,
+            Vec::new()
+        );
+        let typing = infer_source("let x: Number = 1;\nx = \"b\";");
+        assert!(matches!(
+            &typing.errors[..],
+            [TypeError::Mismatch { cause: (7, 13), .. }]
+        ));
+    }
+}
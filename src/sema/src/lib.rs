@@ -0,0 +1,297 @@
+//! Semantic analysis: resolving each name in a program to the declaration it refers to.
+//!
+//! The builtins, the module, every function and every block get a lexical scope. A name is
+//! resolved as it's read, to the nearest declaration in scope at that point. A name with no
+//! declaration in scope yet is looked at again once the whole program has been walked: inside a
+//! function it may refer to a declaration made later in an enclosing scope, since it isn't read
+//! until the function is called, but anywhere else it's read before it's declared.
+
+use std::collections::HashMap;
+
+use lex::Span;
+use parse::expr::{Node, Spanned};
+
+pub mod error;
+pub mod exhaustive;
+mod infer;
+mod resolver;
+pub mod types;
+
+pub use error::{MatchError, NameError, TypeError};
+pub use infer::Typing;
+pub use types::Type;
+
+/// a declaration, by its index in `Resolution::declarations`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeclId(pub usize);
+
+/// a scope, by its index in `Resolution::scopes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclKind {
+    Builtin,
+    Let,
+    Const,
+    Param,
+    Fn,
+    Struct,
+    Enum,
+    /// an import's alias, or one of the items it names
+    Import,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: DeclKind,
+    /// the name where it's declared, empty at the start of the file for builtins
+    pub span: Span,
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// the builtins, enclosing the module
+    Prelude,
+    Module,
+    /// a function or closure's parameters, enclosing its body
+    Function,
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// the scope enclosing this one, `None` only for the prelude
+    pub parent: Option<ScopeId>,
+    pub declarations: Vec<DeclId>,
+    /// for a function, the variables of enclosing functions and blocks it reads, in the order
+    /// they're first read; globals aren't captured
+    pub captures: Vec<DeclId>,
+}
+
+/// every scope and declaration in a program, and what each name in it refers to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resolution {
+    pub declarations: Vec<Declaration>,
+    pub scopes: Vec<Scope>,
+    /// the declaration each identifier that's read refers to, by the identifier's span
+    pub uses: HashMap<Span, DeclId>,
+    /// the function scope of each `fn` and closure, by the span of the whole function
+    pub functions: HashMap<Span, ScopeId>,
+    /// every problem found, in source order
+    pub errors: Vec<NameError>,
+}
+
+impl Resolution {
+    pub fn declaration(&self, id: DeclId) -> &Declaration {
+        &self.declarations[id.0]
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    /// the declaration the identifier at `span` refers to
+    pub fn resolved(&self, span: Span) -> Option<&Declaration> {
+        self.uses.get(&span).map(|id| self.declaration(*id))
+    }
+
+    /// the variables the function or closure at `span` captures
+    pub fn captures(&self, function: Span) -> Vec<&Declaration> {
+        self.functions
+            .get(&function)
+            .map_or_else(Vec::new, |scope| {
+                self.scope(*scope)
+                    .captures
+                    .iter()
+                    .map(|id| self.declaration(*id))
+                    .collect()
+            })
+    }
+}
+
+/// resolves every name in `program`, a single module
+pub fn resolve(program: &[Spanned<Node>]) -> Resolution {
+    resolver::resolve(program)
+}
+
+/// infers the type of every expression in `program`, with the names in it resolved, and checks
+/// the arms of each `match` against the type of the value matched
+pub fn infer(program: &[Spanned<Node>], resolution: &Resolution) -> Typing {
+    infer::infer(program, resolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use lex::tokenise;
+    use parse::parse;
+
+    use super::*;
+
+    fn resolve_source(src: &str) -> Resolution {
+        resolve(&parse(tokenise(src).unwrap()).unwrap())
+    }
+
+    /// the name and span of the declaration read at `span`
+    fn target(resolution: &Resolution, span: Span) -> Option<(&str, Span)> {
+        resolution
+            .resolved(span)
+            .map(|decl| (decl.name.as_str(), decl.span))
+    }
+
+    #[test]
+    fn resolves_to_the_nearest_declaration() {
+        let src = "let x = 1;\nfn f(x) { x }\nfn g() { let x = 2; print(x) }\nprint(x);";
+        let resolution = resolve_source(src);
+
+        assert_eq!(resolution.errors, Vec::new());
+        assert_eq!(target(&resolution, (21, 22)), Some(("x", (16, 17))));
+        assert_eq!(target(&resolution, (51, 52)), Some(("x", (38, 39))));
+        assert_eq!(target(&resolution, (62, 63)), Some(("x", (4, 5))));
+        assert_eq!(
+            resolution.resolved((45, 50)).map(|decl| decl.kind),
+            Some(DeclKind::Builtin)
+        );
+    }
+
+    #[test]
+    fn functions_read_later_declarations() {
+        let src = "fn even(n) { odd(n) }\nfn odd(n) { even(n) }\nconst limit = 3;";
+        let resolution = resolve_source(src);
+
+        assert_eq!(resolution.errors, Vec::new());
+        assert_eq!(target(&resolution, (13, 16)), Some(("odd", (25, 28))));
+        assert_eq!(target(&resolution, (34, 38)), Some(("even", (3, 7))));
+    }
+
+    #[test]
+    fn reports_undefined_and_early_names() {
+        let src = "print(count);\nlet count = 1;\nprint(cuont);\nfn f() { print(y); let y = 2; }";
+
+        assert_eq!(
+            resolve_source(src).errors,
+            vec![
+                NameError::UseBeforeDeclaration {
+                    name: String::from("count"),
+                    span: (6, 11),
+                    declaration: (18, 23),
+                },
+                NameError::Undefined {
+                    name: String::from("cuont"),
+                    span: (35, 40),
+                    similar: Some(String::from("count")),
+                },
+                NameError::UseBeforeDeclaration {
+                    name: String::from("y"),
+                    span: (58, 59),
+                    declaration: (66, 67),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicates_in_one_scope() {
+        let src = "let a = 1;\nfn a() {}\nfn f(b, b) { let a = 2; }";
+
+        assert_eq!(
+            resolve_source(src).errors,
+            vec![
+                NameError::Duplicate {
+                    name: String::from("a"),
+                    span: (14, 15),
+                    previous: (4, 5),
+                },
+                NameError::Duplicate {
+                    name: String::from("b"),
+                    span: (29, 30),
+                    previous: (26, 27),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn records_captured_variables() {
+        let src = "let g = 0;\nfn outer(a) {\n  let b = 1;\n  let f = |c| a + b + c + g;\n  f\n}";
+        let resolution = resolve_source(src);
+        let captured: Vec<&str> = resolution
+            .captures((48, 65))
+            .iter()
+            .map(|decl| decl.name.as_str())
+            .collect();
+        assert_eq!(captured, vec!["a", "b"]);
+        assert_eq!(resolution.captures((11, 71)), Vec::<&Declaration>::new());
+    }
+
+    #[test]
+    fn struct_fields_and_imports() {
+        let src = "import \"shapes\" { area };\nstruct Point { x, y }\nprint(area(Point));";
+        let resolution = resolve_source(src);
+
+        assert_eq!(resolution.errors, Vec::new());
+        assert_eq!(
+            resolution.resolved((54, 58)).map(|decl| decl.kind),
+            Some(DeclKind::Import)
+        );
+        assert_eq!(
+            resolution.resolved((59, 64)).map(|decl| decl.kind),
+            Some(DeclKind::Struct)
+        );
+    }
+
+    #[test]
+    fn enums_and_match_arms() {
+        let src = "enum Shape { Circle(Number) }\nfn area(s) { match s { Shape::Circle(r) => r * r, _ => r } }\nlet c = Shape::Circle(1);";
+        let resolution = resolve_source(src);
+
+        // each arm binds its own names
+        assert!(matches!(
+            &resolution.errors[..],
+            [NameError::Undefined { span: (85, 86), .. }]
+        ));
+        assert_eq!(target(&resolution, (73, 74)), Some(("r", (67, 68))));
+        assert_eq!(target(&resolution, (99, 104)), Some(("Shape", (5, 10))));
+    }
+
+    #[test]
+    fn rejects_reassigning_consts() {
+        assert_eq!(
+            resolve_source("const x = 1;\nx = 2;\nfn f() { x *= 3 }").errors,
+            vec![
+                NameError::ConstReassignment {
+                    name: String::from("x"),
+                    declaration: (6, 7),
+                    assignment: (13, 18),
+                },
+                NameError::ConstReassignment {
+                    name: String::from("x"),
+                    declaration: (6, 7),
+                    assignment: (29, 35),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_destructured_and_later_consts() {
+        let errors = resolve_source("const [a, ..rest] = xs;\nrest += [1];").errors;
+        assert!(matches!(
+            &errors[..],
+            [NameError::Undefined { .. }, NameError::ConstReassignment { name, declaration: (12, 16), .. }]
+                if name == "rest"
+        ));
+
+        let errors = resolve_source("fn f() { limit = 2 }\nconst limit = 1;").errors;
+        assert!(matches!(&errors[..], [NameError::ConstReassignment { .. }]));
+    }
+
+    #[test]
+    fn allows_shadowing_and_mutating_contents() {
+        let src = "const x = 1;\nfn f(x) { x = 2 }\nlet g = fn() { let x = 3; x = 4 };\nconst xs = [1];\nxs[0] = 2;\nlet y = 1;\ny = 2;";
+        assert_eq!(resolve_source(src).errors, Vec::new());
+    }
+}
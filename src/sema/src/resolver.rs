@@ -0,0 +1,338 @@
+use lex::{suggest::closest, trace, Span};
+use parse::{
+    expr::{Node, Param, Spanned, Var},
+    visit::{walk_children, Visitor},
+};
+use rt::builtins::builtins;
+
+use crate::{DeclId, DeclKind, Declaration, NameError, Resolution, Scope, ScopeId, ScopeKind};
+
+pub(crate) fn resolve(program: &[Spanned<Node>]) -> Resolution {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        stack: Vec::new(),
+        pending: Vec::new(),
+        assignments: Vec::new(),
+    };
+
+    resolver.push(ScopeKind::Prelude);
+    for native in builtins() {
+        resolver.declare(native.name, DeclKind::Builtin, (0, 0));
+    }
+    resolver.push(ScopeKind::Module);
+    for node in program {
+        trace::at(node.span);
+        resolver.visit_node(node);
+    }
+
+    resolver.finish()
+}
+
+/// a read of a name with no declaration in scope when it was read
+struct Pending {
+    name: String,
+    span: Span,
+    scope: ScopeId,
+}
+
+struct Resolver {
+    resolution: Resolution,
+    /// the scopes being walked, the innermost last
+    stack: Vec<ScopeId>,
+    pending: Vec<Pending>,
+    /// the name assigned to and the whole assignment, for each assignment to a name
+    assignments: Vec<(Span, Span)>,
+}
+
+impl Resolver {
+    fn current(&self) -> ScopeId {
+        *self.stack.last().expect("the module scope is never left")
+    }
+
+    fn push(&mut self, kind: ScopeKind) -> ScopeId {
+        let id = ScopeId(self.resolution.scopes.len());
+        self.resolution.scopes.push(Scope {
+            kind,
+            parent: self.stack.last().copied(),
+            declarations: Vec::new(),
+            captures: Vec::new(),
+        });
+        self.stack.push(id);
+        id
+    }
+
+    fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    /// the first declaration of `name` made directly in `scope`
+    fn declared_in(&self, scope: ScopeId, name: &str) -> Option<DeclId> {
+        self.resolution
+            .scope(scope)
+            .declarations
+            .iter()
+            .copied()
+            .find(|id| self.resolution.declaration(*id).name == name)
+    }
+
+    fn declare(&mut self, name: &str, kind: DeclKind, span: Span) {
+        let scope = self.current();
+        if let Some(previous) = self.declared_in(scope, name) {
+            self.resolution.errors.push(NameError::Duplicate {
+                name: name.to_string(),
+                span,
+                previous: self.resolution.declaration(previous).span,
+            });
+        }
+
+        let id = DeclId(self.resolution.declarations.len());
+        self.resolution.declarations.push(Declaration {
+            name: name.to_string(),
+            kind,
+            span,
+            scope,
+        });
+        self.resolution.scopes[scope.0].declarations.push(id);
+    }
+
+    fn read(&mut self, name: &str, span: Span) {
+        // the latest declaration in a scope is the one a read sees, when a name is declared twice
+        let found = self.stack.iter().rev().find_map(|scope| {
+            self.resolution
+                .scope(*scope)
+                .declarations
+                .iter()
+                .rev()
+                .copied()
+                .find(|id| self.resolution.declaration(*id).name == name)
+        });
+
+        let scope = self.current();
+        match found {
+            Some(id) => self.bind(scope, span, id),
+            None => self.pending.push(Pending {
+                name: name.to_string(),
+                span,
+                scope,
+            }),
+        }
+    }
+
+    /// resolves the read at `span` in `scope` to `id`, capturing it in every function between
+    fn bind(&mut self, scope: ScopeId, span: Span, id: DeclId) {
+        self.resolution.uses.insert(span, id);
+
+        let target = self.resolution.declaration(id).scope;
+        if matches!(
+            self.resolution.scope(target).kind,
+            ScopeKind::Prelude | ScopeKind::Module
+        ) {
+            return;
+        }
+
+        let mut scope = Some(scope);
+        while let Some(current) = scope.filter(|s| *s != target) {
+            let inner = &mut self.resolution.scopes[current.0];
+            if inner.kind == ScopeKind::Function && !inner.captures.contains(&id) {
+                inner.captures.push(id);
+            }
+            scope = inner.parent;
+        }
+    }
+
+    /// the declaration of `name` made after it was read in `scope`, and whether a function lies
+    /// between the two, which would make reading it later fine
+    fn later(&self, scope: ScopeId, name: &str) -> Option<(DeclId, bool)> {
+        let mut in_function = false;
+        let mut scope = Some(scope);
+        while let Some(current) = scope {
+            if let Some(id) = self.declared_in(current, name) {
+                return Some((id, in_function));
+            }
+
+            let current = self.resolution.scope(current);
+            in_function |= current.kind == ScopeKind::Function;
+            scope = current.parent;
+        }
+
+        None
+    }
+
+    /// every name declared in `scope` or a scope enclosing it
+    fn visible(&self, scope: ScopeId) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut scope = Some(scope);
+        while let Some(current) = scope {
+            let current = self.resolution.scope(current);
+            names.extend(
+                current
+                    .declarations
+                    .iter()
+                    .map(|id| self.resolution.declaration(*id).name.as_str()),
+            );
+            scope = current.parent;
+        }
+
+        names
+    }
+
+    fn finish(mut self) -> Resolution {
+        for pending in std::mem::take(&mut self.pending) {
+            match self.later(pending.scope, &pending.name) {
+                Some((id, true)) => self.bind(pending.scope, pending.span, id),
+                Some((id, false)) => self
+                    .resolution
+                    .errors
+                    .push(NameError::UseBeforeDeclaration {
+                        name: pending.name,
+                        span: pending.span,
+                        declaration: self.resolution.declaration(id).span,
+                    }),
+                None => {
+                    let similar =
+                        closest(&pending.name, self.visible(pending.scope)).map(str::to_string);
+                    self.resolution.errors.push(NameError::Undefined {
+                        name: pending.name,
+                        span: pending.span,
+                        similar,
+                    });
+                }
+            }
+        }
+
+        // checked once every read is resolved, since a function may assign to a later declaration
+        for (target, assignment) in std::mem::take(&mut self.assignments) {
+            if let Some(declaration) = self.resolution.resolved(target) {
+                if declaration.kind == DeclKind::Const {
+                    let err = NameError::ConstReassignment {
+                        name: declaration.name.clone(),
+                        declaration: declaration.span,
+                        assignment,
+                    };
+                    self.resolution.errors.push(err);
+                }
+            }
+        }
+
+        self.resolution.errors.sort_by_key(NameError::span);
+        self.resolution
+    }
+
+    /// walks a function's parameters and body in a new function scope
+    fn function(&mut self, span: Span, params: &[Param], body: &Spanned<Node>) {
+        let scope = self.push(ScopeKind::Function);
+        self.resolution.functions.insert(span, scope);
+        for param in params {
+            for (name, span) in param.pattern.bindings() {
+                self.declare(name, DeclKind::Param, span);
+            }
+        }
+        self.visit_node(body);
+        self.pop();
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_variable(&mut self, node: &Spanned<Node>) {
+        if let Node::Variable {
+            variable_type,
+            pattern,
+            value,
+            ..
+        } = &node.node
+        {
+            // the value can't see the names it's about to be bound to
+            self.visit_node(value);
+            let kind = match variable_type {
+                Var::Let => DeclKind::Let,
+                Var::Const => DeclKind::Const,
+            };
+            for (name, span) in pattern.bindings() {
+                self.declare(name, kind, span);
+            }
+        }
+    }
+
+    fn visit_fn(&mut self, node: &Spanned<Node>) {
+        if let Node::Fn {
+            name, args, body, ..
+        } = &node.node
+        {
+            // declared before the body, so it can call itself
+            if let Node::Identifier(ident) = &name.node {
+                self.declare(ident, DeclKind::Fn, name.span);
+            }
+            self.function(node.span, args, body);
+        }
+    }
+
+    fn visit_closure(&mut self, node: &Spanned<Node>) {
+        if let Node::Closure { args, body, .. } = &node.node {
+            self.function(node.span, args, body);
+        }
+    }
+
+    fn visit_block(&mut self, node: &Spanned<Node>) {
+        self.push(ScopeKind::Block);
+        walk_children(self, node);
+        self.pop();
+    }
+
+    fn visit_struct(&mut self, node: &Spanned<Node>) {
+        // field names aren't reads
+        if let Node::Struct { name, .. } = &node.node {
+            if let Node::Identifier(ident) = &name.node {
+                self.declare(ident, DeclKind::Struct, name.span);
+            }
+        }
+    }
+
+    fn visit_enum(&mut self, node: &Spanned<Node>) {
+        // variant names aren't reads
+        if let Node::Enum { name, .. } = &node.node {
+            if let Node::Identifier(ident) = &name.node {
+                self.declare(ident, DeclKind::Enum, name.span);
+            }
+        }
+    }
+
+    fn visit_match(&mut self, node: &Spanned<Node>) {
+        if let Node::Match { value, arms } = &node.node {
+            self.visit_node(value);
+            for arm in arms {
+                self.push(ScopeKind::Block);
+                for (name, span) in arm.pattern.bindings() {
+                    self.declare(name, DeclKind::Let, span);
+                }
+                self.visit_node(&arm.value);
+                self.pop();
+            }
+        }
+    }
+
+    fn visit_import(&mut self, node: &Spanned<Node>) {
+        if let Node::Import { alias, items, .. } = &node.node {
+            for name in alias.iter().map(|alias| &**alias).chain(items) {
+                if let Node::Identifier(ident) = &name.node {
+                    self.declare(ident, DeclKind::Import, name.span);
+                }
+            }
+        }
+    }
+
+    fn visit_assign(&mut self, node: &Spanned<Node>) {
+        if let Node::Assign { target, .. } = &node.node {
+            if let Node::Identifier(_) = &target.node {
+                self.assignments.push((target.span, node.span));
+            }
+        }
+
+        walk_children(self, node)
+    }
+
+    fn visit_identifier(&mut self, node: &Spanned<Node>) {
+        if let Node::Identifier(name) = &node.node {
+            self.read(name, node.span);
+        }
+    }
+}
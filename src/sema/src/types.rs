@@ -0,0 +1,140 @@
+//! The types of values, as written in annotations and inferred by the type checker.
+
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// anything at all, checked only when the program runs
+    Any,
+    Nil,
+    Number,
+    String,
+    Boolean,
+    List(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Tuple(Vec<Type>),
+    Fn(Vec<Type>, Box<Type>),
+    /// an instance of the struct with this name
+    Struct(String),
+    /// a variant of the enum with this name
+    Enum(String),
+    /// a generic type named in a function's signature, standing for whatever the caller gives it
+    Param(String),
+    /// a type not inferred yet, by its index in the checker
+    Var(usize),
+}
+
+impl Type {
+    pub fn list(item: Type) -> Type {
+        Type::List(Box::new(item))
+    }
+
+    pub fn function(params: Vec<Type>, ret: Type) -> Type {
+        Type::Fn(params, Box::new(ret))
+    }
+
+    /// calls `f` on this type and every type inside it
+    pub(crate) fn walk(&self, f: &mut impl FnMut(&Type)) {
+        f(self);
+        match self {
+            Type::List(item) => item.walk(f),
+            Type::Map(key, value) => {
+                key.walk(f);
+                value.walk(f);
+            }
+            Type::Tuple(items) => items.iter().for_each(|item| item.walk(f)),
+            Type::Fn(params, ret) => {
+                params.iter().for_each(|param| param.walk(f));
+                ret.walk(f);
+            }
+            Type::Any
+            | Type::Nil
+            | Type::Number
+            | Type::String
+            | Type::Boolean
+            | Type::Struct(_)
+            | Type::Enum(_)
+            | Type::Param(_)
+            | Type::Var(_) => {}
+        }
+    }
+
+    /// this type with `f` applied to every type inside it, innermost first
+    pub(crate) fn map(&self, f: &mut impl FnMut(Type) -> Type) -> Type {
+        let ty = match self {
+            Type::List(item) => Type::list(item.map(f)),
+            Type::Map(key, value) => Type::Map(Box::new(key.map(f)), Box::new(value.map(f))),
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|item| item.map(f)).collect()),
+            Type::Fn(params, ret) => Type::function(
+                params.iter().map(|param| param.map(f)).collect(),
+                ret.map(f),
+            ),
+            other => other.clone(),
+        };
+        f(ty)
+    }
+}
+
+/// the variants of each enum, by the enum's name, with the types of the values each one holds
+pub type Enums = HashMap<String, Vec<(String, Vec<Type>)>>;
+
+/// types are written as they are in annotations, with `_` for types that weren't inferred
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter<'_>, types: &[Type]) -> fmt::Result {
+            for (idx, ty) in types.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", ty)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Number => write!(f, "Number"),
+            Type::String => write!(f, "String"),
+            Type::Boolean => write!(f, "Boolean"),
+            Type::List(item) => write!(f, "List<{}>", item),
+            Type::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
+            Type::Tuple(items) => {
+                write!(f, "Tuple<")?;
+                list(f, items)?;
+                write!(f, ">")
+            }
+            Type::Fn(params, ret) => {
+                write!(f, "fn(")?;
+                list(f, params)?;
+                write!(f, ") -> {}", ret)
+            }
+            Type::Struct(name) | Type::Enum(name) | Type::Param(name) => write!(f, "{}", name),
+            Type::Var(_) => write!(f, "_"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_like_annotations() {
+        let ty = Type::function(
+            vec![
+                Type::list(Type::Param(String::from("T"))),
+                Type::Tuple(vec![Type::Number, Type::Var(0)]),
+            ],
+            Type::Map(
+                Box::new(Type::String),
+                Box::new(Type::Struct(String::from("Point"))),
+            ),
+        );
+
+        assert_eq!(
+            ty.to_string(),
+            "fn(List<T>, Tuple<Number, _>) -> Map<String, Point>"
+        );
+    }
+}
@@ -1,7 +1,1084 @@
+//! A tree-walking interpreter for parsed programs.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use lex::{suggest::closest, trace, Span, TokenType};
+use parse::expr::{Node, Op, Pattern, Spanned};
+use rt::{
+    builtins::builtins, Caller, EnumType, Env, Function, Instance, MapKey, RuntimeError, Scope,
+    StructType, Value, Variant,
+};
+
+/// why evaluation stopped before reaching the end of a node
+enum Unwind {
+    Return(Value),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Unwind {
+        Unwind::Error(err)
+    }
+}
+
+pub struct Interpreter {
+    globals: Env,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    /// an interpreter with the builtins bound in its global scope
+    pub fn new() -> Interpreter {
+        let globals = Scope::global();
+        for native in builtins() {
+            globals
+                .borrow_mut()
+                .define(native.name, Value::Native(native));
+        }
+
+        Interpreter { globals }
+    }
+
+    /// runs a program in the global scope, returning the value of its last statement
+    ///
+    /// Bindings persist between runs, so a program can be fed in one statement at a time.
+    pub fn run(&mut self, program: &[Spanned<Node>]) -> Result<Value, RuntimeError> {
+        let globals = Rc::clone(&self.globals);
+
+        let mut last = Value::Nil;
+        for node in program {
+            match self.eval(node, &globals) {
+                Ok(value) => last = value,
+                Err(Unwind::Return(value)) => return Ok(value),
+                Err(Unwind::Error(err)) => return Err(err),
+            }
+        }
+
+        Ok(last)
+    }
+
+    fn eval(&mut self, node: &Spanned<Node>, env: &Env) -> Result<Value, Unwind> {
+        trace::at(node.span);
+        match &node.node {
+            Node::Variable { pattern, value, .. } => {
+                let value = self.eval(value, env)?;
+                bind(pattern, value, env)?;
+                Ok(Value::Nil)
+            }
+            Node::BinaryExpr {
+                operation: operation @ (Op::And | Op::Or),
+                lhs,
+                rhs,
+            } => {
+                let lhs = boolean(self.eval(lhs, env)?, node.span)?;
+                // `and` stops at the first false operand, `or` at the first true one
+                if lhs == (*operation == Op::Or) {
+                    return Ok(Value::Boolean(lhs));
+                }
+
+                Ok(Value::Boolean(boolean(self.eval(rhs, env)?, node.span)?))
+            }
+            Node::BinaryExpr {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let lhs = self.eval(lhs, env)?;
+                let rhs = self.eval(rhs, env)?;
+                Ok(binary(operation, lhs, rhs, node.span)?)
+            }
+            Node::UnaryExpr { operation, body } => {
+                let body = self.eval(body, env)?;
+                match operation {
+                    TokenType::Not => Ok(Value::Boolean(!boolean(body, node.span)?)),
+                    _ => Ok(Value::Number(-number(body, node.span)?)),
+                }
+            }
+            Node::Fn {
+                name: fn_name,
+                args,
+                body,
+                ..
+            } => {
+                let function = Function {
+                    name: Some(name(fn_name).to_string()),
+                    params: args.clone(),
+                    body: (**body).clone(),
+                    env: Rc::clone(env),
+                };
+
+                env.borrow_mut()
+                    .define(name(fn_name), Value::Function(Rc::new(function)));
+                Ok(Value::Nil)
+            }
+            Node::Closure { args, body, .. } => Ok(Value::Function(Rc::new(Function {
+                name: None,
+                params: args.clone(),
+                body: (**body).clone(),
+                env: Rc::clone(env),
+            }))),
+            Node::FnCall { function, args } => {
+                let callee = self.eval(function, env)?;
+
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.eval(arg, env)?);
+                }
+
+                Ok(self.call(&callee, values, node.span)?)
+            }
+            Node::Block { body, value } => {
+                let scope = Scope::child(env);
+                for statement in body {
+                    self.eval(statement, &scope)?;
+                }
+
+                match value {
+                    Some(value) => self.eval(value, &scope),
+                    None => Ok(Value::Nil),
+                }
+            }
+            Node::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value, env)?,
+                    None => Value::Nil,
+                };
+
+                Err(Unwind::Return(value))
+            }
+            Node::Assign {
+                operation,
+                target,
+                value,
+            } => match &target.node {
+                Node::Identifier(name) => {
+                    let mut value = self.eval(value, env)?;
+                    if let Some(operation) = operation {
+                        let current = self.eval(target, env)?;
+                        value = binary(operation, current, value, node.span)?;
+                    }
+
+                    if !env.borrow_mut().assign(name, value.clone()) {
+                        return Err(undefined(env, name, target.span).into());
+                    }
+                    Ok(value)
+                }
+                Node::Index {
+                    target: container,
+                    index,
+                } => {
+                    let container = self.eval(container, env)?;
+                    let index = self.eval(index, env)?;
+                    let mut value = self.eval(value, env)?;
+                    if let Some(operation) = operation {
+                        let current = get_index(&container, &index, target.span)?;
+                        value = binary(operation, current, value, node.span)?;
+                    }
+
+                    set_index(&container, index, value.clone(), target.span)?;
+                    Ok(value)
+                }
+                _ => unreachable!("the parser only assigns to names and indexes"),
+            },
+            Node::List(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.eval(item, env)?);
+                }
+
+                Ok(Value::list(values))
+            }
+            Node::Tuple(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.eval(item, env)?);
+                }
+
+                Ok(Value::tuple(values))
+            }
+            Node::Map(entries) => {
+                let mut map = BTreeMap::new();
+                for entry in entries {
+                    let key = self.eval(&entry.key, env)?;
+                    let key = map_key(&key, entry.key.span)?;
+                    map.insert(key, self.eval(&entry.value, env)?);
+                }
+
+                Ok(Value::map(map))
+            }
+            Node::Index { target, index } => {
+                let target = self.eval(target, env)?;
+                let index = self.eval(index, env)?;
+                Ok(get_index(&target, &index, node.span)?)
+            }
+            Node::Slice { target, start, end } => {
+                let target = self.eval(target, env)?;
+                let start = match start {
+                    Some(start) => Some(self.eval(start, env)?),
+                    None => None,
+                };
+                let end = match end {
+                    Some(end) => Some(self.eval(end, env)?),
+                    None => None,
+                };
+
+                Ok(slice(&target, start, end, node.span)?)
+            }
+            Node::Struct {
+                name: struct_name,
+                fields,
+                ..
+            } => {
+                let ty = StructType {
+                    name: name(struct_name).to_string(),
+                    fields: fields.iter().map(|f| name(&f.name).to_string()).collect(),
+                };
+
+                env.borrow_mut()
+                    .define(name(struct_name), Value::StructType(Rc::new(ty)));
+                Ok(Value::Nil)
+            }
+            Node::Enum {
+                name: enum_name,
+                variants,
+                ..
+            } => {
+                let ty = EnumType {
+                    name: name(enum_name).to_string(),
+                    variants: variants
+                        .iter()
+                        .map(|v| (name(&v.name).to_string(), v.fields.len()))
+                        .collect(),
+                };
+
+                env.borrow_mut()
+                    .define(name(enum_name), Value::EnumType(Rc::new(ty)));
+                Ok(Value::Nil)
+            }
+            Node::Path {
+                name: enum_name,
+                variant,
+            } => {
+                let ty = match self.eval(enum_name, env)? {
+                    Value::EnumType(ty) => ty,
+                    other => {
+                        return Err(RuntimeError::TypeMismatch {
+                            expected: "enum",
+                            found: other.type_name(),
+                            span: enum_name.span,
+                        }
+                        .into())
+                    }
+                };
+
+                match ty.variants.iter().position(|(v, _)| *v == variant.node) {
+                    Some(index) if ty.variants[index].1 == 0 => Ok(Value::Enum(Rc::new(Variant {
+                        ty,
+                        index,
+                        values: Vec::new(),
+                    }))),
+                    Some(index) => Ok(Value::Constructor(ty, index)),
+                    None => {
+                        let similar =
+                            closest(&variant.node, ty.variants.iter().map(|(v, _)| v.as_str()));
+                        Err(RuntimeError::UnknownVariant {
+                            name: ty.name.clone(),
+                            variant: variant.node.clone(),
+                            span: variant.span,
+                            similar: similar.map(str::to_string),
+                        }
+                        .into())
+                    }
+                }
+            }
+            Node::Match { value, arms } => {
+                let value_span = value.span;
+                let value = self.eval(value, env)?;
+                for arm in arms {
+                    // a pattern may bind some names before failing to match, so each arm gets
+                    // its own scope
+                    let scope = Scope::child(env);
+                    if bind(&arm.pattern, value.clone(), &scope).is_ok() {
+                        return self.eval(&arm.value, &scope);
+                    }
+                }
+
+                Err(RuntimeError::NoMatchingArm(value_span).into())
+            }
+            // other modules are only resolved by `chrn check`, so nothing could be bound here
+            Node::Import { path, .. } => Err(RuntimeError::UnsupportedImport(path.span).into()),
+            // only read by passes over the source, like lints
+            Node::Attribute { .. } => Ok(Value::Nil),
+            Node::Number(n) => Ok(Value::Number(*n)),
+            Node::String(s) => Ok(Value::String(s.clone())),
+            Node::Boolean(b) => Ok(Value::Boolean(*b)),
+            Node::Identifier(i) => match env.borrow().get(i) {
+                Some(value) => Ok(value),
+                None => Err(undefined(env, i, node.span).into()),
+            },
+        }
+    }
+}
+
+impl Caller for Interpreter {
+    fn call(
+        &mut self,
+        callee: &Value,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Function(function) => {
+                if args.len() != function.params.len() {
+                    return Err(RuntimeError::WrongArity {
+                        expected: function.params.len(),
+                        found: args.len(),
+                        span,
+                    });
+                }
+
+                let scope = Scope::child(&function.env);
+                for (param, arg) in function.params.iter().zip(args) {
+                    bind(&param.pattern, arg, &scope)?;
+                }
+
+                match self.eval(&function.body, &scope) {
+                    Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+                    Err(Unwind::Error(err)) => Err(err),
+                }
+            }
+            Value::Native(native) => (native.function)(self, args, span),
+            Value::StructType(ty) => {
+                if args.len() != ty.fields.len() {
+                    return Err(RuntimeError::WrongArity {
+                        expected: ty.fields.len(),
+                        found: args.len(),
+                        span,
+                    });
+                }
+
+                Ok(Value::Struct(Rc::new(Instance {
+                    ty: Rc::clone(ty),
+                    fields: args,
+                })))
+            }
+            Value::Constructor(ty, index) => {
+                let expected = ty.variants[*index].1;
+                if args.len() != expected {
+                    return Err(RuntimeError::WrongArity {
+                        expected,
+                        found: args.len(),
+                        span,
+                    });
+                }
+
+                Ok(Value::Enum(Rc::new(Variant {
+                    ty: Rc::clone(ty),
+                    index: *index,
+                    values: args,
+                })))
+            }
+            _ => Err(RuntimeError::NotCallable(span)),
+        }
+    }
+}
+
+/// the error for `name` not being bound in `env`
+fn undefined(env: &Env, name: &str, span: Span) -> RuntimeError {
+    RuntimeError::UndefinedVariable {
+        name: name.to_string(),
+        span,
+        similar: env.borrow().similar(name),
+    }
+}
+
+/// the name held by an identifier node
+fn name(node: &Spanned<Node>) -> &str {
+    match &node.node {
+        Node::Identifier(name) => name,
+        _ => unreachable!("the parser only names items with identifiers"),
+    }
+}
+
+/// binds each name in `pattern` to the matching part of `value`
+///
+/// Struct patterns without a name also take apart maps, looking up each field as a string key.
+fn bind(pattern: &Spanned<Pattern>, value: Value, env: &Env) -> Result<(), RuntimeError> {
+    let mismatch = || RuntimeError::PatternMismatch(pattern.span);
+
+    match (&pattern.node, value) {
+        (Pattern::Identifier(name), value) => env.borrow_mut().define(name, value),
+        (Pattern::Wildcard, _) => {}
+        (Pattern::Number(n), Value::Number(value)) if *n == value => {}
+        (Pattern::String(s), Value::String(value)) if *s == value => {}
+        (Pattern::Boolean(b), Value::Boolean(value)) if *b == value => {}
+        (Pattern::Tuple(items), Value::Tuple(values)) if items.len() == values.len() => {
+            for (item, value) in items.iter().zip(values.iter()) {
+                bind(item, value.clone(), env)?;
+            }
+        }
+        (Pattern::List { items, rest }, Value::List(values)) => {
+            let values = values.borrow().clone();
+            let fits = match rest {
+                Some(_) => values.len() >= items.len(),
+                None => values.len() == items.len(),
+            };
+            if !fits {
+                return Err(mismatch());
+            }
+
+            let (head, tail) = values.split_at(items.len());
+            for (item, value) in items.iter().zip(head) {
+                bind(item, value.clone(), env)?;
+            }
+            if let Some(rest) = rest {
+                bind(rest, Value::list(tail.to_vec()), env)?;
+            }
+        }
+        (Pattern::Struct { name, fields }, Value::Struct(instance)) => {
+            if name.as_ref().is_some_and(|name| *name != instance.ty.name) {
+                return Err(mismatch());
+            }
+
+            for field in fields {
+                let value = instance.get(&field.name).ok_or_else(mismatch)?;
+                bind(&field.pattern, value.clone(), env)?;
+            }
+        }
+        (Pattern::Struct { name: None, fields }, Value::Map(entries)) => {
+            for field in fields {
+                let key = MapKey::String(field.name.clone());
+                let value = entries.borrow().get(&key).cloned().ok_or_else(mismatch)?;
+                bind(&field.pattern, value, env)?;
+            }
+        }
+        (
+            Pattern::Variant {
+                name,
+                variant: variant_name,
+                fields,
+            },
+            Value::Enum(variant),
+        ) if *name == variant.ty.name
+            && variant.name() == variant_name
+            && fields.len() == variant.values.len() =>
+        {
+            for (field, value) in fields.iter().zip(&variant.values) {
+                bind(field, value.clone(), env)?;
+            }
+        }
+        _ => return Err(mismatch()),
+    }
+
+    Ok(())
+}
+
+fn number(value: Value, span: Span) -> Result<f64, RuntimeError> {
+    match value {
+        Value::Number(n) => Ok(n),
+        other => Err(RuntimeError::TypeMismatch {
+            expected: "number",
+            found: other.type_name(),
+            span,
+        }),
+    }
+}
+
+fn boolean(value: Value, span: Span) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Boolean(b) => Ok(b),
+        other => Err(RuntimeError::TypeMismatch {
+            expected: "boolean",
+            found: other.type_name(),
+            span,
+        }),
+    }
+}
+
+fn map_key(key: &Value, span: Span) -> Result<MapKey, RuntimeError> {
+    MapKey::from_value(key).ok_or(RuntimeError::TypeMismatch {
+        expected: "string, number or boolean",
+        found: key.type_name(),
+        span,
+    })
+}
+
+/// a whole number, for indexes and slice bounds
+fn integer(value: &Value, span: Span) -> Result<i64, RuntimeError> {
+    match value {
+        Value::Number(n) if n.fract() == 0.0 => Ok(*n as i64),
+        other => Err(RuntimeError::TypeMismatch {
+            expected: "integer",
+            found: other.type_name(),
+            span,
+        }),
+    }
+}
+
+/// the position `index` refers to in a sequence of `len` items, up to `limit` past the end
+fn position(index: &Value, len: usize, limit: usize, span: Span) -> Result<usize, RuntimeError> {
+    let idx = integer(index, span)?;
+    if idx < 0 || idx as usize >= len + limit {
+        return Err(RuntimeError::IndexOutOfBounds {
+            index: idx,
+            len,
+            span,
+        });
+    }
+
+    Ok(idx as usize)
+}
+
+fn get_index(target: &Value, index: &Value, span: Span) -> Result<Value, RuntimeError> {
+    match target {
+        Value::List(items) => {
+            let items = items.borrow();
+            Ok(items[position(index, items.len(), 0, span)?].clone())
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let c = chars[position(index, chars.len(), 0, span)?];
+            Ok(Value::String(c.to_string()))
+        }
+        Value::Map(entries) => {
+            let key = map_key(index, span)?;
+            match entries.borrow().get(&key) {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::MissingKey(key.to_string(), span)),
+            }
+        }
+        other => Err(RuntimeError::TypeMismatch {
+            expected: "list, map or string",
+            found: other.type_name(),
+            span,
+        }),
+    }
+}
+
+/// `target[index] = value`, replacing a list item or inserting into a map
+fn set_index(target: &Value, index: Value, value: Value, span: Span) -> Result<(), RuntimeError> {
+    match target {
+        Value::List(items) => {
+            let mut items = items.borrow_mut();
+            let idx = position(&index, items.len(), 0, span)?;
+            items[idx] = value;
+        }
+        Value::Map(entries) => {
+            entries.borrow_mut().insert(map_key(&index, span)?, value);
+        }
+        other => {
+            return Err(RuntimeError::TypeMismatch {
+                expected: "list or map",
+                found: other.type_name(),
+                span,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+/// `target[start..end]`, a copy of the items from `start` up to but not including `end`
+fn slice(
+    target: &Value,
+    start: Option<Value>,
+    end: Option<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let len = match target {
+        Value::List(items) => items.borrow().len(),
+        Value::String(s) => s.chars().count(),
+        other => {
+            return Err(RuntimeError::TypeMismatch {
+                expected: "list or string",
+                found: other.type_name(),
+                span,
+            })
+        }
+    };
+
+    // either bound may be the length, for slices ending at or starting from the end
+    let start = match start {
+        Some(start) => position(&start, len, 1, span)?,
+        None => 0,
+    };
+    let end = match end {
+        Some(end) => position(&end, len, 1, span)?,
+        None => len,
+    };
+    if start > end {
+        return Err(RuntimeError::ReversedSlice { start, end, span });
+    }
+
+    match target {
+        Value::List(items) => Ok(Value::list(items.borrow()[start..end].to_vec())),
+        Value::String(s) => Ok(Value::String(
+            s.chars().skip(start).take(end - start).collect(),
+        )),
+        _ => unreachable!("checked above"),
+    }
+}
+
+fn binary(operation: &Op, lhs: Value, rhs: Value, span: Span) -> Result<Value, RuntimeError> {
+    let value = match (operation, lhs, rhs) {
+        (Op::Equal, lhs, rhs) => Value::Boolean(lhs.loose_eq(&rhs)),
+        (Op::NotEqual, lhs, rhs) => Value::Boolean(!lhs.loose_eq(&rhs)),
+        (Op::StrictEqual, lhs, rhs) => Value::Boolean(lhs.strict_eq(&rhs)),
+        (Op::NotStrictEqual, lhs, rhs) => Value::Boolean(!lhs.strict_eq(&rhs)),
+        (Op::Add, Value::String(lhs), Value::String(rhs)) => Value::String(lhs + &rhs),
+        (Op::Add, Value::String(_), rhs) => {
+            return Err(RuntimeError::TypeMismatch {
+                expected: "string",
+                found: rhs.type_name(),
+                span,
+            })
+        }
+        (Op::Less, Value::String(lhs), Value::String(rhs)) => Value::Boolean(lhs < rhs),
+        (Op::LessEqual, Value::String(lhs), Value::String(rhs)) => Value::Boolean(lhs <= rhs),
+        (Op::Greater, Value::String(lhs), Value::String(rhs)) => Value::Boolean(lhs > rhs),
+        (Op::GreaterEqual, Value::String(lhs), Value::String(rhs)) => Value::Boolean(lhs >= rhs),
+        (operation, lhs, rhs) => {
+            let (lhs, rhs) = (number(lhs, span)?, number(rhs, span)?);
+            match operation {
+                Op::Add => Value::Number(lhs + rhs),
+                Op::Subtract => Value::Number(lhs - rhs),
+                Op::Multiply => Value::Number(lhs * rhs),
+                Op::Divide => Value::Number(lhs / rhs),
+                Op::Less => Value::Boolean(lhs < rhs),
+                Op::LessEqual => Value::Boolean(lhs <= rhs),
+                Op::Greater => Value::Boolean(lhs > rhs),
+                Op::GreaterEqual => Value::Boolean(lhs >= rhs),
+                _ => unreachable!("equality and logical operators are handled above"),
+            }
+        }
+    };
+
+    Ok(value)
+}
+
 #[cfg(test)]
 mod tests {
+    use lex::tokenise;
+    use parse::parse;
+
+    use super::*;
+
+    fn eval(src: &str) -> Result<Value, RuntimeError> {
+        let ast = parse(tokenise(src).unwrap()).unwrap();
+        Interpreter::new().run(&ast)
+    }
+
+    #[test]
+    fn arithmetic_and_strings() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(Value::Number(7.0)));
+        assert_eq!(eval("-(4 - 6) / 4"), Ok(Value::Number(0.5)));
+        assert_eq!(eval("\"a\" + \"b\""), Ok(Value::String(String::from("ab"))));
+        assert_eq!(eval("!(1 < 2 and 2 >= 3)"), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn functions_and_returns() {
+        assert_eq!(
+            eval("fn add(a, b) { return a + b; 0 }\nadd(1, 2)"),
+            Ok(Value::Number(3.0))
+        );
+        assert_eq!(eval("fn f() { let x = 1; }\nf()"), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn closures_capture_their_scope() {
+        assert_eq!(
+            eval("fn adder(n) { |x| x + n }\nlet add2 = adder(2);\nadd2(3)"),
+            Ok(Value::Number(5.0))
+        );
+        assert_eq!(
+            eval("let k = 10;\nfn make() { let k = 1; fn(x) { x * k } }\nmake()(4) + k"),
+            Ok(Value::Number(14.0))
+        );
+        assert_eq!(eval("(|| 1)()"), Ok(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn closures_with_block_bodies() {
+        assert_eq!(
+            eval("let c = 0;\nlet inc = || { c += 1; c };\ninc();\ninc()"),
+            Ok(Value::Number(2.0))
+        );
+        assert_eq!(
+            eval("(|x| { let y = x + 1; y * 2 })(3)"),
+            Ok(Value::Number(8.0))
+        );
+    }
+
+    #[test]
+    fn callbacks_for_builtins() {
+        assert_eq!(
+            eval("let n = 2;\nmap(list(1, 2, 3), |x| x * n)"),
+            Ok(Value::list(vec![
+                Value::Number(2.0),
+                Value::Number(4.0),
+                Value::Number(6.0)
+            ]))
+        );
+        assert_eq!(
+            eval("fn big(x) { x > 1 }\nlen(filter(list(1, 2, 3), big))"),
+            Ok(Value::Number(2.0))
+        );
+    }
+
+    #[test]
+    fn undefined_names_suggest_similar_ones() {
+        assert_eq!(
+            eval("let count = 1;\nfn f() { cuont }\nf()"),
+            Err(RuntimeError::UndefinedVariable {
+                name: String::from("cuont"),
+                span: (24, 29),
+                similar: Some(String::from("count")),
+            })
+        );
+        assert_eq!(
+            eval("filtr(list(), len)"),
+            Err(RuntimeError::UndefinedVariable {
+                name: String::from("filtr"),
+                span: (0, 5),
+                similar: Some(String::from("filter")),
+            })
+        );
+    }
+
+    #[test]
+    fn runtime_errors_have_spans() {
+        assert_eq!(
+            eval("let a = 1;\nb"),
+            Err(RuntimeError::UndefinedVariable {
+                name: String::from("b"),
+                span: (11, 12),
+                similar: None,
+            })
+        );
+        assert_eq!(
+            eval("1 + true"),
+            Err(RuntimeError::TypeMismatch {
+                expected: "number",
+                found: "boolean",
+                span: (0, 8)
+            })
+        );
+        assert_eq!(
+            eval("(|x| x)()"),
+            Err(RuntimeError::WrongArity {
+                expected: 1,
+                found: 0,
+                span: (0, 9)
+            })
+        );
+        assert_eq!(eval("1(2)"), Err(RuntimeError::NotCallable((0, 4))));
+        assert!(matches!(
+            eval("filter(list(1), |x| x)"),
+            Err(RuntimeError::TypeMismatch {
+                expected: "boolean",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn enums_and_matches() {
+        let src = "enum Shape { Circle(Number), Square(Number), Empty }
+fn area(s) {
+    match s {
+        Shape::Circle(r) => 3 * r * r,
+        Shape::Square(x) => x * x,
+        Shape::Empty => 0,
+    }
+}
+";
+        let run = |tail: &str| eval(&format!("{}{}", src, tail));
+
+        assert_eq!(
+            run("[area(Shape::Circle(2)), area(Shape::Square(3)), area(Shape::Empty)]"),
+            eval("[12, 9, 0]")
+        );
+        assert_eq!(
+            run("Shape::Circle(1)").map(|v| v.to_string()),
+            Ok(String::from("Shape::Circle(1)"))
+        );
+        assert_eq!(
+            eval("match [1, 2] { [] => 0, [x, ..rest] => x + len(rest) }"),
+            Ok(Value::Number(2.0))
+        );
+        assert_eq!(
+            eval("match 3 { 1 => true, (a, b) => false }"),
+            Err(RuntimeError::NoMatchingArm((6, 7)))
+        );
+        assert_eq!(
+            run("Shape::Sqaure(1)"),
+            Err(RuntimeError::UnknownVariant {
+                name: String::from("Shape"),
+                variant: String::from("Sqaure"),
+                span: (196, 202),
+                similar: Some(String::from("Square")),
+            })
+        );
+        assert!(matches!(
+            run("Shape::Circle(1, 2)"),
+            Err(RuntimeError::WrongArity { expected: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(
+            eval("let xs = [1, 2, 3];\nxs[1] + len(xs)"),
+            Ok(Value::Number(5.0))
+        );
+        assert_eq!(
+            eval("let m = #{ \"a\": 1, 2: [true] };\nm[2][0]"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(eval("\"hello\"[1]"), Ok(Value::String(String::from("e"))));
+        assert_eq!(
+            eval("let m = #{};\nm[\"k\"] = 2;\nm"),
+            eval("#{ \"k\": 2 }")
+        );
+    }
+
+    #[test]
+    fn index_assignment_is_shared() {
+        assert_eq!(
+            eval("let xs = [0, 0];\nlet ys = xs;\nys[1] = xs[0] = 5;\nxs"),
+            Ok(Value::list(vec![Value::Number(5.0), Value::Number(5.0)]))
+        );
+    }
+
+    #[test]
+    fn slicing() {
+        let numbers = |ns: &[f64]| Value::list(ns.iter().map(|n| Value::Number(*n)).collect());
+
+        assert_eq!(eval("[1, 2, 3, 4][1..3]"), Ok(numbers(&[2.0, 3.0])));
+        assert_eq!(eval("[1, 2, 3][..1]"), Ok(numbers(&[1.0])));
+        assert_eq!(eval("[1, 2, 3][1..]"), Ok(numbers(&[2.0, 3.0])));
+        assert_eq!(eval("[1, 2, 3][3..]"), Ok(numbers(&[])));
+        assert_eq!(
+            eval("\"charon\"[2..4]"),
+            Ok(Value::String(String::from("ar")))
+        );
+    }
+
+    #[test]
+    fn collection_errors() {
+        assert_eq!(
+            eval("[1, 2][2]"),
+            Err(RuntimeError::IndexOutOfBounds {
+                index: 2,
+                len: 2,
+                span: (0, 9)
+            })
+        );
+        assert_eq!(
+            eval("[1][2..1]"),
+            Err(RuntimeError::IndexOutOfBounds {
+                index: 2,
+                len: 1,
+                span: (0, 9)
+            })
+        );
+        assert_eq!(
+            eval("[1, 2, 3][2..1]"),
+            Err(RuntimeError::ReversedSlice {
+                start: 2,
+                end: 1,
+                span: (0, 15)
+            })
+        );
+        assert_eq!(
+            eval("#{ \"a\": 1 }[\"b\"]"),
+            Err(RuntimeError::MissingKey(String::from("\"b\""), (0, 16)))
+        );
+        assert!(matches!(
+            eval("[1][0.5]"),
+            Err(RuntimeError::TypeMismatch {
+                expected: "integer",
+                ..
+            })
+        ));
+        assert!(matches!(
+            eval("#{ [1]: 2 }"),
+            Err(RuntimeError::TypeMismatch { span: (3, 6), .. })
+        ));
+    }
+
+    #[test]
+    fn destructuring() {
+        assert_eq!(
+            eval("let (a, b) = (1, 2);\nlet [h, ..t] = [3, 4, 5];\nlet { x, y: [y1, ..] } = #{ \"x\": 6, \"y\": [7] };\n[a, b, h, len(t), x, y1]"),
+            eval("[1, 2, 3, 2, 6, 7]")
+        );
+        assert_eq!(
+            eval("fn first([x, .._]) { x }\nlet swap = |(a, b)| (b, a);\n(first([1, 2]), swap((3, 4)))"),
+            eval("(1, (4, 3))")
+        );
+        assert_eq!(eval("let [..rest] = [];\nrest"), eval("[]"));
+    }
+
+    #[test]
+    fn structs() {
+        assert_eq!(
+            eval("struct Point { x, y }\nfn sum(Point { x, y: b }) { x + b }\nsum(Point(1, 2))"),
+            Ok(Value::Number(3.0))
+        );
+        assert_eq!(
+            eval("struct P { x }\nP(1) == P(1) and P(1) != P(2)"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            eval("struct P { x }\nP([1])").unwrap().to_string(),
+            "P { x: [1] }"
+        );
+        assert_eq!(
+            eval("struct P { x }\nP()"),
+            Err(RuntimeError::WrongArity {
+                expected: 1,
+                found: 0,
+                span: (15, 18)
+            })
+        );
+    }
+
+    #[test]
+    fn pattern_mismatches() {
+        assert_eq!(
+            eval("let (a, b) = (1,);"),
+            Err(RuntimeError::PatternMismatch((4, 10)))
+        );
+        assert_eq!(
+            eval("let [a] = [1, 2];"),
+            Err(RuntimeError::PatternMismatch((4, 7)))
+        );
+        assert_eq!(
+            eval("let { a: [b] } = #{ \"a\": 1 };"),
+            Err(RuntimeError::PatternMismatch((9, 12)))
+        );
+        assert_eq!(
+            eval("struct A {}\nstruct B {}\nlet B {} = A();"),
+            Err(RuntimeError::PatternMismatch((28, 32)))
+        );
+        assert_eq!(
+            eval("fn f((a, b)) { a }\nf(1)"),
+            Err(RuntimeError::PatternMismatch((5, 11)))
+        );
+    }
+
+    #[test]
+    fn imports_are_refused() {
+        assert_eq!(
+            eval("let x = 1;\nimport util.str;\nx"),
+            Err(RuntimeError::UnsupportedImport((18, 26)))
+        );
+    }
+
+    #[test]
+    fn reassignment() {
+        assert_eq!(
+            eval("let x = 1;\nfn bump() { x += 2 }\nbump();\nx *= 3;\nx"),
+            Ok(Value::Number(9.0))
+        );
+        assert_eq!(
+            eval("let s = \"a\";\ns += \"b\";\nlet xs = [1];\nxs[0] -= 3;\n(s, xs)"),
+            eval("(\"ab\", [-2])")
+        );
+        assert_eq!(
+            eval("let x = 1;\nlet f = |x| x = 5;\nf(0);\nx"),
+            Ok(Value::Number(1.0))
+        );
+        assert_eq!(
+            eval("y = 1"),
+            Err(RuntimeError::UndefinedVariable {
+                name: String::from("y"),
+                span: (0, 1),
+                similar: None,
+            })
+        );
+    }
+
+    /// `(lhs, rhs, lhs == rhs, lhs === rhs)`, evaluated after `EQUALITY_PRELUDE`
+    const EQUALITY: &[(&str, &str, bool, bool)] = &[
+        // numbers
+        ("1", "1", true, true),
+        ("0", "-0", true, true),
+        ("0 / 0", "0 / 0", false, false),
+        ("1", "\"1\"", true, false),
+        ("\" 2 \"", "2", true, false),
+        ("1", "\"one\"", false, false),
+        ("1", "true", true, false),
+        ("false", "0", true, false),
+        ("2", "true", false, false),
+        // strings
+        ("\"a\"", "\"a\"", true, true),
+        ("\"a\"", "\"A\"", false, false),
+        ("\"true\"", "true", false, false),
+        ("\"\"", "0", false, false),
+        // booleans
+        ("true", "true", true, true),
+        ("true", "false", false, false),
+        // lists, tuples and maps
+        ("[1, 2]", "[1, 2]", true, false),
+        ("[1, \"2\"]", "[1, 2]", true, false),
+        ("[1]", "[1, 1]", false, false),
+        ("xs", "xs", true, true),
+        ("[xs]", "[xs]", true, false),
+        ("[1]", "(1,)", false, false),
+        ("(1, \"a\")", "(1, \"a\")", true, true),
+        ("(1, [])", "(1, [])", true, false),
+        ("#{ 1: true }", "#{ 1: 1 }", true, false),
+        ("m", "m", true, true),
+        // structs
+        ("P(1)", "P(1)", true, true),
+        ("P(1)", "P(\"1\")", true, false),
+        ("P(1)", "Q(1)", false, false),
+        ("P([])", "P([])", true, false),
+        ("P(xs)", "P(xs)", true, true),
+        ("P", "P", true, true),
+        ("P(1)", "#{ \"x\": 1 }", false, false),
+    ];
+
+    const EQUALITY_PRELUDE: &str = "struct P { x }\nstruct Q { x }\nlet xs = [1];\nlet m = #{};\n";
+
+    #[test]
+    fn equality_conformance() {
+        for (lhs, rhs, loose, strict) in EQUALITY {
+            let src = format!(
+                "{}({l} == {r}, {l} === {r}, {l} != {r}, {l} !== {r}, {r} == {l}, {r} === {l})",
+                EQUALITY_PRELUDE,
+                l = lhs,
+                r = rhs
+            );
+            let expected = [*loose, *strict, !loose, !strict, *loose, *strict];
+
+            assert_eq!(
+                eval(&src),
+                Ok(Value::tuple(
+                    expected.iter().map(|b| Value::Boolean(*b)).collect()
+                )),
+                "{} against {}",
+                lhs,
+                rhs
+            );
+        }
+    }
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    fn bindings_persist_between_runs() {
+        let mut interpreter = Interpreter::new();
+        let first = parse(tokenise("let a = 2;").unwrap()).unwrap();
+        let second = parse(tokenise("a * a").unwrap()).unwrap();
+
+        interpreter.run(&first).unwrap();
+        assert_eq!(interpreter.run(&second), Ok(Value::Number(4.0)));
     }
 }